@@ -0,0 +1,15 @@
+#![no_main]
+
+use borsh::BorshDeserialize;
+use libfuzzer_sys::fuzz_target;
+use plasma_sdk::{PoolAccount, plasma::LpPosition};
+
+// Decoding must never panic, and anything that decodes must re-encode to the same bytes
+fuzz_target!(|data: &[u8]| {
+    if let Ok(pool) = PoolAccount::try_from_slice(data) {
+        assert_eq!(borsh::to_vec(&pool).unwrap(), data);
+    }
+    if let Ok(lp_position) = LpPosition::try_from_slice(data) {
+        assert_eq!(borsh::to_vec(&lp_position).unwrap(), data);
+    }
+});
@@ -0,0 +1,70 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use plasma_sdk::plasma::plasma_amm::BPS_BASE;
+use plasma_sdk_fuzz::PoolInput;
+
+// Quoting the output of an exact-in swap back as an exact-out swap on the same pool must not
+// require more than the original input, up to the fee rounding between the two paths.
+//
+// Exact-in rounds the fee down on each leg it fills (limit order and pool), while exact-out grosses
+// the whole amount up once with `pre_fee_adjust_rounded_down`. The `Amm` has to match the deployed
+// program, so the difference is bounded rather than removed:
+// - buy: `buy_exact_out(buy_exact_in(q).base_amount_to_transfer)` needs less than
+//   `q + BPS_BASE / (BPS_BASE - fee)` quote.
+// - sell: `sell_exact_out(sell_exact_in(b).quote_amount_to_transfer)` swaps less than
+//   `2 * BPS_BASE / (BPS_BASE - fee)` more quote before fees than the exact-in swap. If it swaps
+//   no more, it needs at most `b` base. Otherwise the extra quote can cost any amount of base near
+//   the end of the curve, so only the pre-fee bound applies.
+fuzz_target!(|input: (PoolInput, u64)| {
+    let (pool, amount_in) = input;
+    let Some((amm, slot)) = pool.build() else {
+        return;
+    };
+    let fee_denominator = BPS_BASE - amm.fee_in_bps as u128;
+
+    let (mut exact_in_pool, mut exact_out_pool) = (amm, amm);
+    if let Ok(exact_in) = exact_in_pool.buy_exact_in(slot, amount_in) {
+        let base_out = exact_in.base_amount_to_transfer;
+        let exact_out = exact_out_pool
+            .buy_exact_out(slot, base_out)
+            .unwrap_or_else(|e| panic!("buy_exact_out({}) failed: {}", base_out, e));
+        let excess = exact_out
+            .quote_amount_to_transfer
+            .saturating_sub(amount_in) as u128;
+        assert!(
+            excess * fee_denominator < BPS_BASE,
+            "buy_exact_out({}) requires {} quote but buy_exact_in spent {}",
+            base_out,
+            exact_out.quote_amount_to_transfer,
+            amount_in
+        );
+    }
+
+    let (mut exact_in_pool, mut exact_out_pool) = (amm, amm);
+    if let Ok(exact_in) = exact_in_pool.sell_exact_in(slot, amount_in) {
+        let quote_out = exact_in.quote_amount_to_transfer;
+        let exact_in_pre_fee = quote_out as u128 + exact_in.fee_in_quote as u128;
+        let exact_out_pre_fee = amm.pre_fee_adjust_rounded_down(quote_out as u128);
+        let excess = exact_out_pre_fee.saturating_sub(exact_in_pre_fee);
+        assert!(
+            excess * fee_denominator < 2 * BPS_BASE,
+            "sell_exact_out({}) swaps {} quote before fees but sell_exact_in swapped {}",
+            quote_out,
+            exact_out_pre_fee,
+            exact_in_pre_fee
+        );
+        if excess == 0 {
+            let exact_out = exact_out_pool
+                .sell_exact_out(slot, quote_out)
+                .unwrap_or_else(|e| panic!("sell_exact_out({}) failed: {}", quote_out, e));
+            assert!(
+                exact_out.base_amount_to_transfer <= amount_in,
+                "sell_exact_out({}) requires {} base but sell_exact_in spent {}",
+                quote_out,
+                exact_out.base_amount_to_transfer,
+                amount_in
+            );
+        }
+    }
+});
@@ -0,0 +1,69 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use plasma_sdk::plasma::plasma_amm::{Amm, Side};
+use plasma_sdk_fuzz::{PoolInput, SwapInput, k};
+
+fuzz_target!(|input: (PoolInput, Vec<SwapInput>)| {
+    let (pool, swaps) = input;
+    let Some((mut amm, slot)) = pool.build() else {
+        return;
+    };
+    for swap in swaps {
+        let before = amm;
+        let Ok(result) = swap.apply(&mut amm, slot) else {
+            amm = before;
+            continue;
+        };
+
+        assert!(k(&amm) >= k(&before), "k decreased on {:?}", swap);
+        assert_eq!(
+            result.base_amount_to_transfer,
+            result.base_matched_as_limit_order + result.base_matched_as_swap
+        );
+        let quote_matched = result.quote_matched_as_limit_order + result.quote_matched_as_swap;
+        match swap {
+            SwapInput::BuyExactIn(quote_in) => {
+                assert_eq!(result.quote_amount_to_transfer, quote_in)
+            }
+            SwapInput::BuyExactOut(base_out) => {
+                assert_eq!(result.base_amount_to_transfer, base_out)
+            }
+            SwapInput::SellExactIn(base_in) => assert_eq!(result.base_amount_to_transfer, base_in),
+            SwapInput::SellExactOut(quote_out) => {
+                assert_eq!(result.quote_amount_to_transfer, quote_out)
+            }
+        }
+        match result.side {
+            Side::Buy => {
+                assert_eq!(
+                    result.quote_amount_to_transfer,
+                    quote_matched + result.fee_in_quote
+                );
+                assert_eq!(
+                    before.base_reserves - amm.base_reserves,
+                    result.base_amount_to_transfer
+                );
+                assert_eq!(amm.quote_reserves - before.quote_reserves, quote_matched);
+            }
+            Side::Sell => {
+                assert_eq!(
+                    amm.base_reserves - before.base_reserves,
+                    result.base_amount_to_transfer
+                );
+                assert_eq!(
+                    before.quote_reserves - amm.quote_reserves,
+                    result.quote_amount_to_transfer + result.fee_in_quote
+                );
+            }
+        }
+        assert_eq!(
+            total_fees(&amm),
+            total_fees(&before) + result.fee_in_quote as u128
+        );
+    }
+});
+
+fn total_fees(amm: &Amm) -> u128 {
+    amm.cumulative_quote_lp_fees as u128 + amm.cumulative_quote_protocol_fees as u128
+}
@@ -0,0 +1,71 @@
+use arbitrary::Arbitrary;
+use plasma_sdk::plasma::{
+    PlasmaStateError, SlotWindow,
+    plasma_amm::{Amm, BPS_BASE, SwapResult},
+};
+
+/// A pool seeded through `mint`, optionally pushed off its snapshot by one swap so that the
+/// virtual limit order is non-empty
+#[derive(Debug, Arbitrary)]
+pub struct PoolInput {
+    pub fee_in_bps: u16,
+    pub protocol_allocation_in_pct: u8,
+    pub base_reserves: u64,
+    pub quote_reserves: u64,
+    pub pre_swap: Option<SwapInput>,
+    /// Slot windows between the pre-swap and the swaps under test
+    pub slot_windows_to_advance: u8,
+}
+
+impl PoolInput {
+    /// Returns the pool and the slot window to swap at, or `None` if the input does not describe
+    /// a valid pool
+    pub fn build(&self) -> Option<(Amm, SlotWindow)> {
+        let mut amm = Amm::new(
+            self.fee_in_bps as u32 % BPS_BASE as u32,
+            self.protocol_allocation_in_pct as u32 % 101,
+            0,
+            0,
+        );
+        amm.mint(
+            0,
+            self.base_reserves,
+            self.quote_reserves,
+            Some(Amm::initial_lp_shares(
+                self.base_reserves,
+                self.quote_reserves,
+            )),
+        )
+        .ok()?;
+        if let Some(pre_swap) = self.pre_swap {
+            let mut next = amm;
+            if pre_swap.apply(&mut next, 0).is_ok() {
+                amm = next;
+            }
+        }
+        Some((amm, self.slot_windows_to_advance as SlotWindow % 3))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Arbitrary)]
+pub enum SwapInput {
+    BuyExactIn(u64),
+    BuyExactOut(u64),
+    SellExactIn(u64),
+    SellExactOut(u64),
+}
+
+impl SwapInput {
+    pub fn apply(&self, amm: &mut Amm, slot: SlotWindow) -> Result<SwapResult, PlasmaStateError> {
+        match *self {
+            SwapInput::BuyExactIn(quote_in) => amm.buy_exact_in(slot, quote_in),
+            SwapInput::BuyExactOut(base_out) => amm.buy_exact_out(slot, base_out),
+            SwapInput::SellExactIn(base_in) => amm.sell_exact_in(slot, base_in),
+            SwapInput::SellExactOut(quote_out) => amm.sell_exact_out(slot, quote_out),
+        }
+    }
+}
+
+pub fn k(amm: &Amm) -> u128 {
+    amm.base_reserves as u128 * amm.quote_reserves as u128
+}
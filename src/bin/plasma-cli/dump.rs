@@ -0,0 +1,148 @@
+use std::{io::Read, path::PathBuf};
+
+use anyhow::{Context, Result, bail};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use clap::{Args, ValueEnum};
+use plasma_sdk::{
+    PoolAccount,
+    plasma::{LP_POSITION_LEN, LpPosition, POOL_LEN, account_json::parse_account_json},
+};
+use serde_json::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Encoding {
+    /// JSON if the dump starts with `{`, hex if it only has hex digits, base64 otherwise
+    Auto,
+    Base64,
+    Hex,
+    /// `solana account --output json`, or an RPC `getAccountInfo` value with base64 data
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Kind {
+    /// Decided by the length of the account data
+    Auto,
+    Pool,
+    LpPosition,
+}
+
+#[derive(Debug, Args)]
+pub struct DumpArgs {
+    /// File holding the account dump, or `-` for stdin
+    pub input: PathBuf,
+    #[arg(long, value_enum, default_value_t = Encoding::Auto)]
+    pub encoding: Encoding,
+}
+
+#[derive(Debug)]
+pub enum Account {
+    Pool(Box<PoolAccount>),
+    LpPosition(LpPosition),
+}
+
+impl DumpArgs {
+    pub fn read_data(&self) -> Result<Vec<u8>> {
+        let mut text = String::new();
+        if self.input.as_os_str() == "-" {
+            std::io::stdin().read_to_string(&mut text)?;
+        } else {
+            text = std::fs::read_to_string(&self.input)
+                .with_context(|| format!("Failed to read {}", self.input.display()))?;
+        }
+        decode_data(text.trim(), self.encoding)
+    }
+
+    pub fn read_account(&self, kind: Kind) -> Result<Account> {
+        decode_account(&self.read_data()?, kind)
+    }
+
+    pub fn read_pool(&self) -> Result<PoolAccount> {
+        match self.read_account(Kind::Pool)? {
+            Account::Pool(pool) => Ok(*pool),
+            Account::LpPosition(_) => unreachable!(),
+        }
+    }
+}
+
+pub fn decode_data(text: &str, encoding: Encoding) -> Result<Vec<u8>> {
+    let encoding = match encoding {
+        Encoding::Auto if text.starts_with('{') => Encoding::Json,
+        Encoding::Auto
+            if text.len().is_multiple_of(2) && text.bytes().all(|b| b.is_ascii_hexdigit()) =>
+        {
+            Encoding::Hex
+        }
+        Encoding::Auto => Encoding::Base64,
+        encoding => encoding,
+    };
+    match encoding {
+        Encoding::Auto => unreachable!(),
+        Encoding::Base64 => STANDARD.decode(text).context("Invalid base64 account data"),
+        Encoding::Hex => decode_hex(text),
+        Encoding::Json => decode_json(text),
+    }
+}
+
+fn decode_hex(text: &str) -> Result<Vec<u8>> {
+    let text = text.strip_prefix("0x").unwrap_or(text);
+    if !text.is_ascii() || !text.len().is_multiple_of(2) {
+        bail!("Hex account data must be an even number of hex digits");
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).context("Invalid hex account data"))
+        .collect()
+}
+
+fn decode_json(text: &str) -> Result<Vec<u8>> {
+    let value: Value = serde_json::from_str(text).context("Invalid JSON account dump")?;
+    if value.get("pubkey").is_some() {
+        let (_, account) = parse_account_json(text)?;
+        return Ok(account.data);
+    }
+    let account = value.get("account").unwrap_or(&value);
+    let account = account.get("value").unwrap_or(account);
+    let Some(data) = account.get("data") else {
+        bail!("JSON account dump has no `data` field");
+    };
+    match data {
+        Value::Array(items) => match items.as_slice() {
+            [Value::String(data), Value::String(encoding)] if encoding == "base64" => {
+                STANDARD.decode(data).context("Invalid base64 account data")
+            }
+            [Value::String(_), Value::String(encoding)] => {
+                bail!("Unsupported account data encoding `{}`", encoding)
+            }
+            _ => bail!("Expected `data` to be a [data, encoding] pair"),
+        },
+        Value::String(data) => STANDARD.decode(data).context("Invalid base64 account data"),
+        _ => bail!("Expected `data` to be a [data, encoding] pair"),
+    }
+}
+
+pub fn decode_account(data: &[u8], kind: Kind) -> Result<Account> {
+    let kind = match kind {
+        Kind::Auto if data.len() == POOL_LEN as usize => Kind::Pool,
+        Kind::Auto if data.len() == LP_POSITION_LEN as usize => Kind::LpPosition,
+        Kind::Auto => bail!(
+            "Account data is {} bytes, expected {} for a pool or {} for an LP position",
+            data.len(),
+            POOL_LEN,
+            LP_POSITION_LEN
+        ),
+        kind => kind,
+    };
+    match kind {
+        Kind::Auto => unreachable!(),
+        Kind::Pool => {
+            let pool = PoolAccount::decode(data).context("Account data is not a pool account")?;
+            Ok(Account::Pool(Box::new(pool)))
+        }
+        Kind::LpPosition => {
+            let lp_position =
+                LpPosition::decode(data).context("Account data is not an LP position account")?;
+            Ok(Account::LpPosition(lp_position))
+        }
+    }
+}
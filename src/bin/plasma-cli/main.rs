@@ -0,0 +1,233 @@
+//! Offline inspection, quoting and transaction building for Plasma pools
+
+mod dump;
+mod tx;
+
+use anyhow::Result;
+use clap::{Args, CommandFactory, Parser, Subcommand, error::ErrorKind};
+use plasma_sdk::{
+    PoolAccount,
+    plasma::{
+        ID, PlasmaStateError, SlotWindow, get_log_authority, get_lp_position_address,
+        get_vault_address,
+        plasma_amm::{Amm, SwapResult},
+        slot_window,
+    },
+};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    dump::{Account, DumpArgs, Kind},
+    tx::TxArgs,
+};
+
+#[derive(Debug, Parser)]
+#[command(name = "plasma-cli", version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Decode a pool or LP position from an account dump
+    Decode {
+        #[command(flatten)]
+        dump: DumpArgs,
+        #[arg(long, value_enum, default_value_t = Kind::Auto)]
+        kind: Kind,
+    },
+    /// Derive program addresses
+    #[command(subcommand)]
+    Pda(PdaCommand),
+    /// Quote swaps against a pool dump. Buys spend or receive `--quote-amount` and
+    /// `--base-amount` respectively, sells the reverse.
+    Quote {
+        #[command(flatten)]
+        dump: DumpArgs,
+        #[command(flatten)]
+        at: SlotArgs,
+        /// Quote in for buy exact in, quote out for sell exact out
+        #[arg(long)]
+        quote_amount: Option<u64>,
+        /// Base out for buy exact out, base in for sell exact in
+        #[arg(long)]
+        base_amount: Option<u64>,
+    },
+    /// Preview an `add_liquidity` deposit against a pool dump
+    PreviewMint {
+        #[command(flatten)]
+        dump: DumpArgs,
+        #[command(flatten)]
+        at: SlotArgs,
+        #[arg(long)]
+        base_amount: u64,
+        #[arg(long)]
+        quote_amount: u64,
+    },
+    /// Preview a `remove_liquidity` withdrawal against a pool dump
+    PreviewBurn {
+        #[command(flatten)]
+        dump: DumpArgs,
+        #[command(flatten)]
+        at: SlotArgs,
+        #[arg(long)]
+        shares: u64,
+    },
+    /// Print an unsigned, serialized transaction
+    Tx(Box<TxArgs>),
+}
+
+#[derive(Debug, Subcommand)]
+enum PdaCommand {
+    Vault {
+        #[arg(long)]
+        pool: Pubkey,
+        #[arg(long)]
+        mint: Pubkey,
+        #[arg(long, default_value_t = ID)]
+        program_id: Pubkey,
+    },
+    LpPosition {
+        #[arg(long)]
+        pool: Pubkey,
+        #[arg(long)]
+        owner: Pubkey,
+        #[arg(long, default_value_t = ID)]
+        program_id: Pubkey,
+    },
+    LogAuthority {
+        #[arg(long, default_value_t = ID)]
+        program_id: Pubkey,
+    },
+}
+
+/// The slot window to evaluate at. Defaults to the pool's snapshot window, so the snapshot is not
+/// refreshed.
+#[derive(Debug, Args)]
+struct SlotArgs {
+    #[arg(long, conflicts_with = "slot")]
+    slot_window: Option<SlotWindow>,
+    /// Cluster slot, converted to its slot window
+    #[arg(long)]
+    slot: Option<u64>,
+}
+
+impl SlotArgs {
+    fn window(&self, pool: &PoolAccount) -> SlotWindow {
+        self.slot_window
+            .or(self.slot.map(slot_window))
+            .unwrap_or(pool.amm.get_slot())
+    }
+}
+
+type SwapFn = fn(&mut Amm, SlotWindow, u64) -> Result<SwapResult, PlasmaStateError>;
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    if let Command::Tx(args) = &cli.command
+        && let Err(message) = args.validate()
+    {
+        Cli::command()
+            .error(ErrorKind::TooManyValues, message)
+            .exit();
+    }
+    match cli.command {
+        Command::Decode { dump, kind } => match dump.read_account(kind)? {
+            Account::Pool(pool) => {
+                let amm = &pool.amm;
+                println!("{:#?}", pool);
+                if amm.base_reserves > 0 && amm.base_reserves_snapshot > 0 {
+                    let scale = 10_f64.powi(
+                        pool.header.base_params.decimals as i32
+                            - pool.header.quote_params.decimals as i32,
+                    );
+                    let price = |base: u64, quote: u64| quote as f64 / base as f64 * scale;
+                    println!(
+                        "spot price: {}",
+                        price(amm.base_reserves, amm.quote_reserves)
+                    );
+                    println!(
+                        "snapshot price: {}",
+                        price(amm.base_reserves_snapshot, amm.quote_reserves_snapshot)
+                    );
+                }
+            }
+            Account::LpPosition(lp_position) => {
+                println!("{:#?}", lp_position);
+                println!(
+                    "reward factor snapshot: {}",
+                    lp_position.reward_factor_snapshot()
+                );
+                println!("uncollected fees: {}", lp_position.uncollected_fees());
+                println!("collected fees: {}", lp_position.collected_fees());
+            }
+        },
+        Command::Pda(command) => match command {
+            PdaCommand::Vault {
+                pool,
+                mint,
+                program_id,
+            } => {
+                let (vault, bump) = get_vault_address(&program_id, &pool, &mint);
+                println!("{} {}", vault, bump);
+            }
+            PdaCommand::LpPosition {
+                pool,
+                owner,
+                program_id,
+            } => {
+                let (lp_position, bump) = get_lp_position_address(&program_id, &pool, &owner);
+                println!("{} {}", lp_position, bump);
+            }
+            PdaCommand::LogAuthority { program_id } => {
+                println!("{}", get_log_authority(&program_id));
+            }
+        },
+        Command::Quote {
+            dump,
+            at,
+            quote_amount,
+            base_amount,
+        } => {
+            let pool = dump.read_pool()?;
+            let window = at.window(&pool);
+            println!("slot window: {}", window);
+            let quotes = [
+                ("buy exact in", quote_amount, Amm::buy_exact_in as SwapFn),
+                ("buy exact out", base_amount, Amm::buy_exact_out),
+                ("sell exact in", base_amount, Amm::sell_exact_in),
+                ("sell exact out", quote_amount, Amm::sell_exact_out),
+            ];
+            for (name, amount, swap) in quotes {
+                let Some(amount) = amount else {
+                    continue;
+                };
+                let mut amm = pool.amm;
+                match swap(&mut amm, window, amount) {
+                    Ok(result) => println!("{} {}: {:#?}", name, amount, result),
+                    Err(error) => println!("{} {}: {}", name, amount, error),
+                }
+            }
+        }
+        Command::PreviewMint {
+            dump,
+            at,
+            base_amount,
+            quote_amount,
+        } => {
+            let pool = dump.read_pool()?;
+            let preview = pool
+                .amm
+                .preview_mint(at.window(&pool), base_amount, quote_amount)?;
+            println!("{:#?}", preview);
+        }
+        Command::PreviewBurn { dump, at, shares } => {
+            let pool = dump.read_pool()?;
+            let preview = pool.amm.preview_burn(at.window(&pool), shares)?;
+            println!("{:#?}", preview);
+        }
+        Command::Tx(args) => println!("{}", args.run()?),
+    }
+    Ok(())
+}
@@ -0,0 +1,313 @@
+use anyhow::Result;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use clap::{Args, Subcommand, ValueEnum};
+use plasma_sdk::plasma::{
+    AddLiquidityParams, InitializePoolParams, ProtocolFeeRecipientParams, Side, SwapParams,
+    SwapType, add_liquidity, initialize_lp_position, initialize_pool,
+    initialize_pool_with_liquidity_with_rent, remove_liquidity, swap, transfer_liquidity,
+};
+use solana_sdk::{
+    hash::Hash, instruction::Instruction, pubkey::Pubkey, rent::Rent, transaction::Transaction,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputEncoding {
+    Base64,
+    Hex,
+}
+
+#[derive(Debug, Args)]
+pub struct TxArgs {
+    /// Fee payer of the transaction
+    #[arg(long)]
+    pub payer: Pubkey,
+    #[arg(long, default_value_t = Hash::default())]
+    pub recent_blockhash: Hash,
+    #[arg(long, value_enum, default_value_t = OutputEncoding::Base64)]
+    pub output: OutputEncoding,
+    #[command(subcommand)]
+    pub instruction: TxCommand,
+}
+
+#[derive(Debug, Args)]
+pub struct PoolArgs {
+    #[arg(long)]
+    pub pool: Pubkey,
+    #[arg(long)]
+    pub base_mint: Pubkey,
+    #[arg(long)]
+    pub quote_mint: Pubkey,
+}
+
+#[derive(Debug, Args)]
+pub struct PoolParamsArgs {
+    #[arg(long)]
+    pub lp_fee_in_bps: u64,
+    #[arg(long, default_value_t = 0)]
+    pub protocol_fee_allocation_in_pct: u64,
+    /// Up to three `recipient:shares` pairs
+    #[arg(long = "fee-recipient", value_parser = parse_fee_recipient, num_args = 0..=3)]
+    pub fee_recipients: Vec<ProtocolFeeRecipientParams>,
+    #[arg(long)]
+    pub num_slots_to_vest_lp_shares: Option<u64>,
+}
+
+impl PoolParamsArgs {
+    /// `num_args` only bounds a single `--fee-recipient`, not how often it is repeated
+    fn validate(&self) -> Result<(), String> {
+        if self.fee_recipients.len() > 3 {
+            return Err(format!(
+                "at most 3 `--fee-recipient` values are allowed, got {}",
+                self.fee_recipients.len()
+            ));
+        }
+        Ok(())
+    }
+
+    fn params(&self) -> InitializePoolParams {
+        let mut fee_recipients_params = [ProtocolFeeRecipientParams::default(); 3];
+        fee_recipients_params[..self.fee_recipients.len()].copy_from_slice(&self.fee_recipients);
+        InitializePoolParams {
+            lp_fee_in_bps: self.lp_fee_in_bps,
+            protocol_fee_allocation_in_pct: self.protocol_fee_allocation_in_pct,
+            fee_recipients_params,
+            num_slots_to_vest_lp_shares: self.num_slots_to_vest_lp_shares,
+        }
+    }
+}
+
+fn parse_fee_recipient(value: &str) -> Result<ProtocolFeeRecipientParams, String> {
+    let (recipient, shares) = value
+        .split_once(':')
+        .ok_or_else(|| "expected `recipient:shares`".to_string())?;
+    Ok(ProtocolFeeRecipientParams {
+        recipient: recipient.parse().map_err(|e| format!("{}", e))?,
+        shares: shares.parse().map_err(|e| format!("{}", e))?,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SwapKind {
+    BuyExactIn,
+    BuyExactOut,
+    SellExactIn,
+    SellExactOut,
+}
+
+/// Instructions signed by `--payer` unless stated otherwise
+#[derive(Debug, Subcommand)]
+pub enum TxCommand {
+    /// `initialize_pool` for a pool account that already exists
+    InitializePool {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[command(flatten)]
+        params: PoolParamsArgs,
+    },
+    /// Create the pool account, initialize it and seed it. The pool keypair must also sign.
+    InitializePoolWithLiquidity {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[command(flatten)]
+        params: PoolParamsArgs,
+        #[arg(long)]
+        base_account: Pubkey,
+        #[arg(long)]
+        quote_account: Pubkey,
+        #[arg(long)]
+        base_amount: u64,
+        #[arg(long)]
+        quote_amount: u64,
+        /// Rent rate of the target cluster, from its rent sysvar. The pool account is funded to
+        /// be rent-exempt at this rate.
+        #[arg(long)]
+        lamports_per_byte_year: u64,
+    },
+    InitializeLpPosition {
+        #[arg(long)]
+        pool: Pubkey,
+        /// Defaults to the payer
+        #[arg(long)]
+        owner: Option<Pubkey>,
+    },
+    AddLiquidity {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[arg(long)]
+        base_account: Pubkey,
+        #[arg(long)]
+        quote_account: Pubkey,
+        #[arg(long)]
+        base_amount: u64,
+        #[arg(long)]
+        quote_amount: u64,
+        /// Only for the first deposit into an empty pool
+        #[arg(long)]
+        initial_lp_shares: Option<u64>,
+    },
+    RemoveLiquidity {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[arg(long)]
+        base_account: Pubkey,
+        #[arg(long)]
+        quote_account: Pubkey,
+        #[arg(long)]
+        shares: u64,
+    },
+    /// Move the payer's whole LP position to `destination`'s position
+    TransferLiquidity {
+        #[arg(long)]
+        pool: Pubkey,
+        #[arg(long)]
+        destination: Pubkey,
+    },
+    Swap {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[arg(long)]
+        base_account: Pubkey,
+        #[arg(long)]
+        quote_account: Pubkey,
+        #[arg(long = "type", value_enum)]
+        kind: SwapKind,
+        /// Amount in for exact-in swaps, amount out for exact-out swaps
+        #[arg(long)]
+        amount: u64,
+        /// Minimum amount out for exact-in swaps, maximum amount in for exact-out swaps
+        #[arg(long)]
+        limit: u64,
+    },
+}
+
+impl TxArgs {
+    /// Checks the arguments clap cannot express constraints for
+    pub fn validate(&self) -> Result<(), String> {
+        match &self.instruction {
+            TxCommand::InitializePool { params, .. }
+            | TxCommand::InitializePoolWithLiquidity { params, .. } => params.validate(),
+            _ => Ok(()),
+        }
+    }
+
+    fn instructions(&self) -> Vec<Instruction> {
+        let payer = &self.payer;
+        match &self.instruction {
+            TxCommand::InitializePool { pool, params } => vec![initialize_pool(
+                &pool.pool,
+                payer,
+                &pool.base_mint,
+                &pool.quote_mint,
+                params.params(),
+            )],
+            TxCommand::InitializePoolWithLiquidity {
+                pool,
+                params,
+                base_account,
+                quote_account,
+                base_amount,
+                quote_amount,
+                lamports_per_byte_year,
+            } => initialize_pool_with_liquidity_with_rent(
+                &pool.pool,
+                payer,
+                &pool.base_mint,
+                base_account,
+                &pool.quote_mint,
+                quote_account,
+                params.params(),
+                *base_amount,
+                *quote_amount,
+                &Rent {
+                    lamports_per_byte_year: *lamports_per_byte_year,
+                    ..Rent::default()
+                },
+            ),
+            TxCommand::InitializeLpPosition { pool, owner } => vec![initialize_lp_position(
+                pool,
+                payer,
+                owner.as_ref().unwrap_or(payer),
+            )],
+            TxCommand::AddLiquidity {
+                pool,
+                base_account,
+                quote_account,
+                base_amount,
+                quote_amount,
+                initial_lp_shares,
+            } => vec![add_liquidity(
+                &pool.pool,
+                payer,
+                &pool.base_mint,
+                base_account,
+                &pool.quote_mint,
+                quote_account,
+                AddLiquidityParams {
+                    desired_base_amount_in: *base_amount,
+                    desired_quote_amount_in: *quote_amount,
+                    initial_lp_shares: *initial_lp_shares,
+                },
+            )],
+            TxCommand::RemoveLiquidity {
+                pool,
+                base_account,
+                quote_account,
+                shares,
+            } => vec![remove_liquidity(
+                &pool.pool,
+                payer,
+                &pool.base_mint,
+                &pool.quote_mint,
+                base_account,
+                quote_account,
+                *shares,
+            )],
+            TxCommand::TransferLiquidity { pool, destination } => {
+                vec![transfer_liquidity(pool, payer, destination)]
+            }
+            TxCommand::Swap {
+                pool,
+                base_account,
+                quote_account,
+                kind,
+                amount,
+                limit,
+            } => {
+                let side = match kind {
+                    SwapKind::BuyExactIn | SwapKind::BuyExactOut => Side::Buy,
+                    SwapKind::SellExactIn | SwapKind::SellExactOut => Side::Sell,
+                };
+                let swap_type = match kind {
+                    SwapKind::BuyExactIn | SwapKind::SellExactIn => SwapType::ExactIn {
+                        amount_in: *amount,
+                        min_amount_out: *limit,
+                    },
+                    SwapKind::BuyExactOut | SwapKind::SellExactOut => SwapType::ExactOut {
+                        amount_out: *amount,
+                        max_amount_in: *limit,
+                    },
+                };
+                vec![swap(
+                    &pool.pool,
+                    payer,
+                    &pool.base_mint,
+                    &pool.quote_mint,
+                    base_account,
+                    quote_account,
+                    SwapParams { side, swap_type },
+                )]
+            }
+        }
+    }
+
+    /// Serializes the unsigned transaction, with every signature left zeroed
+    pub fn run(&self) -> Result<String> {
+        let mut transaction = Transaction::new_with_payer(&self.instructions(), Some(&self.payer));
+        transaction.message.recent_blockhash = self.recent_blockhash;
+        let bytes = bincode::serialize(&transaction)?;
+        Ok(match self.output {
+            OutputEncoding::Base64 => STANDARD.encode(bytes),
+            OutputEncoding::Hex => bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+        })
+    }
+}
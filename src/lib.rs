@@ -1,11 +1,39 @@
+//! Features:
+//! - `math`: the `Amm`, fixed point numbers and state errors. Without `client` the crate is
+//!   `no_std`, for on-chain programs and WebAssembly builds.
+//! - `state`: account layouts (`PoolAccount`, `LpPosition`), which only need `solana-pubkey`.
+//! - `client` (default): instruction builders and everything else built on the Solana SDK.
+//! - `program-test`: `solana-program-test` harness for the Plasma program.
+//! - `wasm`: `wasm-bindgen` bindings to the `Amm` math.
+
+#![cfg_attr(not(feature = "client"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "math")]
 pub mod plasma;
 
+#[cfg(feature = "state")]
 use borsh::{BorshDeserialize, BorshSerialize};
+#[cfg(feature = "state")]
 use plasma::{PoolHeader, plasma_amm::Amm as PlasmaAmmState};
 
+#[cfg(feature = "state")]
 #[derive(Debug, Copy, Clone, BorshDeserialize, BorshSerialize)]
 #[repr(C)]
 pub struct PoolAccount {
     pub header: PoolHeader,
     pub amm: PlasmaAmmState,
 }
+
+#[cfg(feature = "state")]
+impl PoolAccount {
+    /// Decodes the data of a pool account, or `None` if it has the wrong length or discriminator.
+    /// The owner is checked by the caller.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() != plasma::POOL_LEN as usize || data[..8] != plasma::POOL_DISCRIMINATOR {
+            return None;
+        }
+        Self::try_from_slice(data).ok()
+    }
+}
@@ -0,0 +1,285 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde::Deserialize;
+use solana_account::Account;
+use solana_account_decoder::UiAccount;
+use solana_program::pubkey::Pubkey;
+
+use crate::{
+    PoolAccount,
+    plasma::{LP_POSITION_LEN, LpPosition, POOL_LEN, get_lp_position_address, get_vault_address},
+};
+
+/// The layout written by `solana account <address> --output json`
+#[derive(Deserialize)]
+struct KeyedUiAccount {
+    pubkey: String,
+    account: UiAccount,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccountJsonError {
+    Io {
+        path: PathBuf,
+        error: String,
+    },
+    InvalidJson {
+        path: Option<PathBuf>,
+        error: String,
+    },
+    InvalidPubkey(String),
+    /// The account data is `jsonParsed` or uses an encoding that cannot be decoded
+    UnsupportedData(Pubkey),
+    /// The account is not owned by the Plasma program
+    WrongOwner {
+        pubkey: Pubkey,
+        owner: Pubkey,
+    },
+    /// The account is owned by the Plasma program but is neither a pool nor an LP position
+    UnknownAccount(Pubkey),
+    InvalidPool(Pubkey),
+    /// A vault key or bump in the pool header is not the PDA derived from the pool and mint
+    InvalidVault {
+        pool: Pubkey,
+        vault: Pubkey,
+    },
+    InvalidLpPosition(Pubkey),
+    DuplicateAccount(Pubkey),
+}
+
+impl Display for AccountJsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AccountJsonError::Io { path, error } => {
+                write!(f, "Failed to read {}: {}", path.display(), error)
+            }
+            AccountJsonError::InvalidJson { path, error } => match path {
+                Some(path) => write!(f, "Invalid account JSON in {}: {}", path.display(), error),
+                None => write!(f, "Invalid account JSON: {}", error),
+            },
+            AccountJsonError::InvalidPubkey(pubkey) => write!(f, "Invalid pubkey {}", pubkey),
+            AccountJsonError::UnsupportedData(pubkey) => {
+                write!(f, "Account {} data is not base58 or base64 encoded", pubkey)
+            }
+            AccountJsonError::WrongOwner { pubkey, owner } => write!(
+                f,
+                "Account {} is owned by {}, not the Plasma program",
+                pubkey, owner
+            ),
+            AccountJsonError::UnknownAccount(pubkey) => {
+                write!(f, "Account {} is not a pool or an LP position", pubkey)
+            }
+            AccountJsonError::InvalidPool(pubkey) => write!(f, "Invalid pool account {}", pubkey),
+            AccountJsonError::InvalidVault { pool, vault } => {
+                write!(
+                    f,
+                    "Vault {} of pool {} is not the expected PDA",
+                    vault, pool
+                )
+            }
+            AccountJsonError::InvalidLpPosition(pubkey) => {
+                write!(f, "Invalid LP position account {}", pubkey)
+            }
+            AccountJsonError::DuplicateAccount(pubkey) => {
+                write!(f, "Account {} was loaded more than once", pubkey)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AccountJsonError {}
+
+/// Parses the output of `solana account <address> --output json`
+pub fn parse_account_json(json: &str) -> Result<(Pubkey, Account), AccountJsonError> {
+    let keyed: KeyedUiAccount =
+        serde_json::from_str(json).map_err(|error| AccountJsonError::InvalidJson {
+            path: None,
+            error: error.to_string(),
+        })?;
+    let pubkey = Pubkey::from_str(&keyed.pubkey)
+        .map_err(|_| AccountJsonError::InvalidPubkey(keyed.pubkey.clone()))?;
+    if Pubkey::from_str(&keyed.account.owner).is_err() {
+        return Err(AccountJsonError::InvalidPubkey(keyed.account.owner));
+    }
+    let account = keyed
+        .account
+        .decode::<Account>()
+        .ok_or(AccountJsonError::UnsupportedData(pubkey))?;
+    Ok((pubkey, account))
+}
+
+pub fn read_account_json(path: &Path) -> Result<(Pubkey, Account), AccountJsonError> {
+    let json = std::fs::read_to_string(path).map_err(|error| AccountJsonError::Io {
+        path: path.to_path_buf(),
+        error: error.to_string(),
+    })?;
+    parse_account_json(&json).map_err(|error| match error {
+        AccountJsonError::InvalidJson { error, .. } => AccountJsonError::InvalidJson {
+            path: Some(path.to_path_buf()),
+            error,
+        },
+        error => error,
+    })
+}
+
+fn check_owner(
+    plasma_program_id: &Pubkey,
+    pubkey: &Pubkey,
+    account: &Account,
+) -> Result<(), AccountJsonError> {
+    if account.owner != *plasma_program_id {
+        return Err(AccountJsonError::WrongOwner {
+            pubkey: *pubkey,
+            owner: account.owner,
+        });
+    }
+    Ok(())
+}
+
+/// Decodes a pool account and checks its owner, discriminator and vault PDAs
+pub fn decode_pool(
+    plasma_program_id: &Pubkey,
+    pubkey: &Pubkey,
+    account: &Account,
+) -> Result<PoolAccount, AccountJsonError> {
+    check_owner(plasma_program_id, pubkey, account)?;
+    let pool = PoolAccount::decode(&account.data).ok_or(AccountJsonError::InvalidPool(*pubkey))?;
+    for params in [&pool.header.base_params, &pool.header.quote_params] {
+        let (vault, bump) = get_vault_address(plasma_program_id, pubkey, &params.mint_key);
+        if params.vault_key != vault || params.vault_bump != bump as u32 {
+            return Err(AccountJsonError::InvalidVault {
+                pool: *pubkey,
+                vault: params.vault_key,
+            });
+        }
+    }
+    Ok(pool)
+}
+
+/// Decodes an LP position and checks its owner and that its withdrawable and vesting shares are
+/// covered by its LP shares
+pub fn decode_lp_position(
+    plasma_program_id: &Pubkey,
+    pubkey: &Pubkey,
+    account: &Account,
+) -> Result<LpPosition, AccountJsonError> {
+    check_owner(plasma_program_id, pubkey, account)?;
+    let lp_position =
+        LpPosition::decode(&account.data).ok_or(AccountJsonError::InvalidLpPosition(*pubkey))?;
+    let accounted_shares = lp_position
+        .withdrawable_lp_shares
+        .checked_add(lp_position.pending_shares_to_vest.1);
+    if accounted_shares.is_none_or(|shares| shares > lp_position.lp_shares) {
+        return Err(AccountJsonError::InvalidLpPosition(*pubkey));
+    }
+    Ok(lp_position)
+}
+
+/// Accounts loaded from JSON dumps, with the Plasma program's accounts decoded and validated.
+///
+/// Accounts owned by other programs, such as mints, vaults and token accounts, are kept as is so
+/// the whole set can be loaded into a [`ReferenceRuntime`](crate::plasma::reference::ReferenceRuntime).
+#[derive(Debug, Clone)]
+pub struct AccountDump {
+    plasma_program_id: Pubkey,
+    accounts: BTreeMap<Pubkey, Account>,
+    pools: BTreeMap<Pubkey, PoolAccount>,
+    lp_positions: BTreeMap<Pubkey, LpPosition>,
+}
+
+impl AccountDump {
+    pub fn new(plasma_program_id: Pubkey) -> Self {
+        Self {
+            plasma_program_id,
+            accounts: BTreeMap::new(),
+            pools: BTreeMap::new(),
+            lp_positions: BTreeMap::new(),
+        }
+    }
+
+    /// Loads a single JSON file, or every `.json` file in a directory
+    pub fn load(plasma_program_id: Pubkey, path: &Path) -> Result<Self, AccountJsonError> {
+        let mut dump = Self::new(plasma_program_id);
+        if path.is_dir() {
+            dump.load_dir(path)?;
+        } else {
+            dump.load_file(path)?;
+        }
+        Ok(dump)
+    }
+
+    pub fn insert(&mut self, pubkey: Pubkey, account: Account) -> Result<(), AccountJsonError> {
+        if self.accounts.contains_key(&pubkey) {
+            return Err(AccountJsonError::DuplicateAccount(pubkey));
+        }
+        if account.owner == self.plasma_program_id {
+            match account.data.len() as u64 {
+                POOL_LEN => {
+                    let pool = decode_pool(&self.plasma_program_id, &pubkey, &account)?;
+                    self.pools.insert(pubkey, pool);
+                }
+                LP_POSITION_LEN => {
+                    let lp_position =
+                        decode_lp_position(&self.plasma_program_id, &pubkey, &account)?;
+                    self.lp_positions.insert(pubkey, lp_position);
+                }
+                _ => return Err(AccountJsonError::UnknownAccount(pubkey)),
+            }
+        }
+        self.accounts.insert(pubkey, account);
+        Ok(())
+    }
+
+    pub fn load_file(&mut self, path: &Path) -> Result<Pubkey, AccountJsonError> {
+        let (pubkey, account) = read_account_json(path)?;
+        self.insert(pubkey, account)?;
+        Ok(pubkey)
+    }
+
+    /// Loads every `.json` file in `path` in file name order. Subdirectories are not searched.
+    pub fn load_dir(&mut self, path: &Path) -> Result<Vec<Pubkey>, AccountJsonError> {
+        let io_error = |error: std::io::Error| AccountJsonError::Io {
+            path: path.to_path_buf(),
+            error: error.to_string(),
+        };
+        let mut files = std::fs::read_dir(path)
+            .map_err(io_error)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(io_error)?;
+        files.retain(|file| file.is_file() && file.extension().is_some_and(|ext| ext == "json"));
+        files.sort();
+        files.iter().map(|file| self.load_file(file)).collect()
+    }
+
+    pub fn plasma_program_id(&self) -> &Pubkey {
+        &self.plasma_program_id
+    }
+
+    /// Every loaded account, including the ones not owned by the Plasma program
+    pub fn accounts(&self) -> &BTreeMap<Pubkey, Account> {
+        &self.accounts
+    }
+
+    pub fn pools(&self) -> &BTreeMap<Pubkey, PoolAccount> {
+        &self.pools
+    }
+
+    pub fn lp_positions(&self) -> &BTreeMap<Pubkey, LpPosition> {
+        &self.lp_positions
+    }
+
+    pub fn pool(&self, pubkey: &Pubkey) -> Option<&PoolAccount> {
+        self.pools.get(pubkey)
+    }
+
+    pub fn lp_position(&self, pool: &Pubkey, owner: &Pubkey) -> Option<&LpPosition> {
+        let (lp_position_key, _) = get_lp_position_address(&self.plasma_program_id, pool, owner);
+        self.lp_positions.get(&lp_position_key)
+    }
+}
@@ -0,0 +1,319 @@
+use std::{collections::HashMap, fmt::Display};
+
+use solana_account::Account;
+use solana_program::{
+    clock::Clock,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    sysvar,
+};
+
+use crate::{
+    PoolAccount,
+    plasma::{
+        ID, PlasmaStateError, Side, SlotWindow, SwapParams, SwapType, client,
+        plasma_amm::SwapResult, slot_window, swap,
+    },
+};
+
+pub type AccountMap = HashMap<Pubkey, Account>;
+
+#[derive(Debug, Clone)]
+pub struct KeyedAccount {
+    pub key: Pubkey,
+    pub account: Account,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapMode {
+    ExactIn,
+    ExactOut,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuoteParams {
+    /// Amount in for [`SwapMode::ExactIn`], amount out for [`SwapMode::ExactOut`]
+    pub amount: u64,
+    pub input_mint: Pubkey,
+    pub output_mint: Pubkey,
+    pub swap_mode: SwapMode,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quote {
+    pub in_amount: u64,
+    pub out_amount: u64,
+    /// LP and protocol fees, always charged in the quote mint
+    pub fee_amount: u64,
+    pub fee_mint: Pubkey,
+    /// Part of the trade filled by the snapshot limit order instead of the curve, in the output
+    /// mint
+    pub out_amount_from_limit_order: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapAccountsParams {
+    pub source_mint: Pubkey,
+    pub destination_mint: Pubkey,
+    pub source_token_account: Pubkey,
+    pub destination_token_account: Pubkey,
+    pub token_transfer_authority: Pubkey,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AggregatorError {
+    /// An account returned by `accounts_to_update` was not in the account map
+    MissingAccount(Pubkey),
+    InvalidPoolAccount(Pubkey),
+    InvalidClockAccount,
+    /// The mint pair does not match the pool's base and quote mints
+    UnsupportedMints {
+        input_mint: Pubkey,
+        output_mint: Pubkey,
+    },
+    Swap(PlasmaStateError),
+}
+
+impl Display for AggregatorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AggregatorError::MissingAccount(key) => write!(f, "Account {} was not fetched", key),
+            AggregatorError::InvalidPoolAccount(key) => {
+                write!(f, "Account {} is not a Plasma pool", key)
+            }
+            AggregatorError::InvalidClockAccount => write!(f, "Invalid clock sysvar account"),
+            AggregatorError::UnsupportedMints {
+                input_mint,
+                output_mint,
+            } => write!(f, "Pool does not swap {} for {}", input_mint, output_mint),
+            AggregatorError::Swap(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for AggregatorError {}
+
+impl From<PlasmaStateError> for AggregatorError {
+    fn from(error: PlasmaStateError) -> Self {
+        AggregatorError::Swap(error)
+    }
+}
+
+/// The interface aggregators use to route through an AMM: build it from the pool account, keep it
+/// fresh from fetched accounts, quote against the cached state, and emit the swap accounts.
+pub trait AggregatorAmm {
+    fn from_keyed_account(
+        keyed_account: &KeyedAccount,
+        clock: &Clock,
+    ) -> Result<Self, AggregatorError>
+    where
+        Self: Sized;
+
+    fn label(&self) -> String;
+
+    fn program_id(&self) -> Pubkey;
+
+    fn key(&self) -> Pubkey;
+
+    fn reserve_mints(&self) -> Vec<Pubkey>;
+
+    fn accounts_to_update(&self) -> Vec<Pubkey>;
+
+    fn update(&mut self, accounts: &AccountMap) -> Result<(), AggregatorError>;
+
+    fn quote(&self, params: &QuoteParams) -> Result<Quote, AggregatorError>;
+
+    /// Builds the swap instruction. `other_amount_threshold` is the minimum amount out for
+    /// [`SwapMode::ExactIn`] and the maximum amount in for [`SwapMode::ExactOut`].
+    fn swap_instruction(
+        &self,
+        params: &SwapAccountsParams,
+        swap_mode: SwapMode,
+        amount: u64,
+        other_amount_threshold: u64,
+    ) -> Result<Instruction, AggregatorError>;
+
+    fn swap_account_metas(
+        &self,
+        params: &SwapAccountsParams,
+    ) -> Result<Vec<AccountMeta>, AggregatorError> {
+        Ok(self
+            .swap_instruction(params, SwapMode::ExactIn, 0, 0)?
+            .accounts)
+    }
+}
+
+/// Plasma pool adapter. Quotes are simulated on a copy of the cached `Amm` at the slot window of
+/// the last clock it was given, so the snapshot refresh matches what the program would do.
+#[derive(Debug, Clone)]
+pub struct PlasmaPool {
+    key: Pubkey,
+    pool: PoolAccount,
+    slot_window: SlotWindow,
+}
+
+impl PlasmaPool {
+    pub fn pool(&self) -> &PoolAccount {
+        &self.pool
+    }
+
+    pub fn slot_window(&self) -> SlotWindow {
+        self.slot_window
+    }
+
+    pub fn set_clock(&mut self, clock: &Clock) {
+        self.slot_window = slot_window(clock.slot);
+    }
+
+    fn decode_pool(keyed_account: &KeyedAccount) -> Result<PoolAccount, AggregatorError> {
+        let KeyedAccount { key, account } = keyed_account;
+        client::decode_pool(key, account).map_err(|_| AggregatorError::InvalidPoolAccount(*key))
+    }
+
+    /// Buys spend the quote mint and receive the base mint, sells the reverse
+    fn side(&self, input_mint: &Pubkey, output_mint: &Pubkey) -> Result<Side, AggregatorError> {
+        let base_mint = &self.pool.header.base_params.mint_key;
+        let quote_mint = &self.pool.header.quote_params.mint_key;
+        if input_mint == quote_mint && output_mint == base_mint {
+            Ok(Side::Buy)
+        } else if input_mint == base_mint && output_mint == quote_mint {
+            Ok(Side::Sell)
+        } else {
+            Err(AggregatorError::UnsupportedMints {
+                input_mint: *input_mint,
+                output_mint: *output_mint,
+            })
+        }
+    }
+}
+
+impl AggregatorAmm for PlasmaPool {
+    fn from_keyed_account(
+        keyed_account: &KeyedAccount,
+        clock: &Clock,
+    ) -> Result<Self, AggregatorError> {
+        Ok(Self {
+            key: keyed_account.key,
+            pool: Self::decode_pool(keyed_account)?,
+            slot_window: slot_window(clock.slot),
+        })
+    }
+
+    fn label(&self) -> String {
+        "Plasma".to_string()
+    }
+
+    fn program_id(&self) -> Pubkey {
+        ID
+    }
+
+    fn key(&self) -> Pubkey {
+        self.key
+    }
+
+    fn reserve_mints(&self) -> Vec<Pubkey> {
+        vec![
+            self.pool.header.base_params.mint_key,
+            self.pool.header.quote_params.mint_key,
+        ]
+    }
+
+    /// The pool and the clock sysvar, which sets the slot window used for quotes
+    fn accounts_to_update(&self) -> Vec<Pubkey> {
+        vec![self.key, sysvar::clock::ID]
+    }
+
+    fn update(&mut self, accounts: &AccountMap) -> Result<(), AggregatorError> {
+        let get = |key: &Pubkey| {
+            accounts
+                .get(key)
+                .ok_or(AggregatorError::MissingAccount(*key))
+        };
+        let pool = Self::decode_pool(&KeyedAccount {
+            key: self.key,
+            account: get(&self.key)?.clone(),
+        })?;
+        let clock: Clock = bincode::deserialize(&get(&sysvar::clock::ID)?.data)
+            .map_err(|_| AggregatorError::InvalidClockAccount)?;
+        self.pool = pool;
+        self.set_clock(&clock);
+        Ok(())
+    }
+
+    fn quote(&self, params: &QuoteParams) -> Result<Quote, AggregatorError> {
+        let side = self.side(&params.input_mint, &params.output_mint)?;
+        let mut amm = self.pool.amm;
+        let window = self.slot_window;
+        let SwapResult {
+            base_amount_to_transfer,
+            quote_amount_to_transfer,
+            base_matched_as_limit_order,
+            quote_matched_as_limit_order,
+            fee_in_quote,
+            ..
+        } = match (side, params.swap_mode) {
+            (Side::Buy, SwapMode::ExactIn) => amm.buy_exact_in(window, params.amount)?,
+            (Side::Buy, SwapMode::ExactOut) => amm.buy_exact_out(window, params.amount)?,
+            (Side::Sell, SwapMode::ExactIn) => amm.sell_exact_in(window, params.amount)?,
+            (Side::Sell, SwapMode::ExactOut) => amm.sell_exact_out(window, params.amount)?,
+        };
+        let (in_amount, out_amount, out_amount_from_limit_order) = match side {
+            Side::Buy => (
+                quote_amount_to_transfer,
+                base_amount_to_transfer,
+                base_matched_as_limit_order,
+            ),
+            Side::Sell => (
+                base_amount_to_transfer,
+                quote_amount_to_transfer,
+                quote_matched_as_limit_order,
+            ),
+        };
+        Ok(Quote {
+            in_amount,
+            out_amount,
+            fee_amount: fee_in_quote,
+            fee_mint: self.pool.header.quote_params.mint_key,
+            out_amount_from_limit_order,
+        })
+    }
+
+    fn swap_instruction(
+        &self,
+        params: &SwapAccountsParams,
+        swap_mode: SwapMode,
+        amount: u64,
+        other_amount_threshold: u64,
+    ) -> Result<Instruction, AggregatorError> {
+        let side = self.side(&params.source_mint, &params.destination_mint)?;
+        let (base_account, quote_account) = match side {
+            Side::Buy => (
+                &params.destination_token_account,
+                &params.source_token_account,
+            ),
+            Side::Sell => (
+                &params.source_token_account,
+                &params.destination_token_account,
+            ),
+        };
+        let swap_type = match swap_mode {
+            SwapMode::ExactIn => SwapType::ExactIn {
+                amount_in: amount,
+                min_amount_out: other_amount_threshold,
+            },
+            SwapMode::ExactOut => SwapType::ExactOut {
+                amount_out: amount,
+                max_amount_in: other_amount_threshold,
+            },
+        };
+        Ok(swap(
+            &self.key,
+            &params.token_transfer_authority,
+            &self.pool.header.base_params.mint_key,
+            &self.pool.header.quote_params.mint_key,
+            base_account,
+            quote_account,
+            SwapParams { side, swap_type },
+        ))
+    }
+}
@@ -0,0 +1,399 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::pubkey::Pubkey;
+
+use crate::{
+    PoolAccount,
+    plasma::{
+        LpPosition, PlasmaStateError, SlotWindow, Upcast,
+        plasma_amm::{BPS_BASE, MintPreview},
+    },
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnalyticsError {
+    /// The pool has no LP shares or reserves to value a position against
+    EmptyPool,
+    /// The entry records a deposit that minted no LP shares
+    EmptyEntry,
+    /// The position holds more LP shares than its entry minted, so it was topped up or received
+    /// a transfer after the entry was recorded
+    PositionExceedsEntry {
+        entry_lp_shares: u64,
+        position_lp_shares: u64,
+    },
+    /// Fewer than two snapshots at distinct slots were given
+    NotEnoughSnapshots,
+    /// The snapshots are not all of the same pool
+    MixedPools,
+    /// A cumulative fee counter decreased between two snapshots
+    FeeCountersDecreased {
+        start_slot: u64,
+        end_slot: u64,
+    },
+    /// The time between two snapshots, or the total time covered, does not fit in an `i64`
+    TimestampOverflow {
+        start_slot: u64,
+        end_slot: u64,
+    },
+    /// Every interval between snapshots was skipped
+    NoUsableIntervals {
+        skipped_intervals: usize,
+    },
+    State(PlasmaStateError),
+}
+
+impl std::fmt::Display for AnalyticsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AnalyticsError::EmptyPool => write!(f, "Pool has no liquidity"),
+            AnalyticsError::EmptyEntry => write!(f, "LP entry minted no shares"),
+            AnalyticsError::PositionExceedsEntry {
+                entry_lp_shares,
+                position_lp_shares,
+            } => write!(
+                f,
+                "LP position holds {} shares but its entry only minted {}",
+                position_lp_shares, entry_lp_shares
+            ),
+            AnalyticsError::NotEnoughSnapshots => {
+                write!(f, "At least two snapshots at distinct slots are required")
+            }
+            AnalyticsError::MixedPools => write!(f, "Snapshots are not all of the same pool"),
+            AnalyticsError::FeeCountersDecreased {
+                start_slot,
+                end_slot,
+            } => write!(
+                f,
+                "Cumulative fees decreased between slots {} and {}",
+                start_slot, end_slot
+            ),
+            AnalyticsError::TimestampOverflow {
+                start_slot,
+                end_slot,
+            } => write!(
+                f,
+                "Time between slots {} and {} overflows",
+                start_slot, end_slot
+            ),
+            AnalyticsError::NoUsableIntervals { skipped_intervals } => write!(
+                f,
+                "All {} intervals between snapshots were skipped",
+                skipped_intervals
+            ),
+            AnalyticsError::State(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for AnalyticsError {}
+
+impl From<PlasmaStateError> for AnalyticsError {
+    fn from(error: PlasmaStateError) -> Self {
+        AnalyticsError::State(error)
+    }
+}
+
+/// The deposit that opened an LP position, as recorded at mint time.
+///
+/// Together with a `PoolAccount` and `LpPosition` snapshot this is all that is needed to
+/// reproduce an [`LpPnl`] later on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshDeserialize, BorshSerialize)]
+pub struct LpEntry {
+    pub slot: SlotWindow,
+    pub base_amount_deposited: u64,
+    pub quote_amount_deposited: u64,
+    /// LP shares minted for the deposit
+    pub lp_shares: u64,
+}
+
+impl LpEntry {
+    pub fn from_mint_preview(slot: SlotWindow, preview: &MintPreview) -> Self {
+        Self {
+            slot,
+            base_amount_deposited: preview.base_amount_deposited,
+            quote_amount_deposited: preview.quote_amount_deposited,
+            lp_shares: preview.lp_shares,
+        }
+    }
+}
+
+/// Profit and loss of an LP position. All values are denominated in quote atoms and base is
+/// converted at the pool's current spot price.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshDeserialize, BorshSerialize)]
+pub struct LpPnl {
+    pub entry_slot: SlotWindow,
+    pub current_slot: SlotWindow,
+    /// Amounts `burn` would return for all of the position's LP shares
+    pub base_amount_redeemable: u64,
+    pub quote_amount_redeemable: u64,
+    /// Collected, uncollected and not yet accounted fees earned through the reward factor
+    pub fees_earned_in_quote: u64,
+    /// Value of the deposit at the entry price
+    pub entry_value_in_quote: u128,
+    /// Value of the deposited tokens had they been held instead
+    pub hold_value_in_quote: u128,
+    /// Value of the redeemable tokens, excluding fees
+    pub lp_value_in_quote: u128,
+    /// `lp_value - hold_value`, never positive for a constant product pool
+    pub impermanent_loss_in_quote: i128,
+    /// `lp_value + fees - entry_value`
+    pub net_pnl_in_quote: i128,
+    /// `lp_value + fees - hold_value`
+    pub net_pnl_versus_hold_in_quote: i128,
+    pub quote_decimals: u32,
+}
+
+/// [`LpPnl`] values converted to UI units of the quote token
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LpPnlUi {
+    pub fees_earned: f64,
+    pub entry_value: f64,
+    pub hold_value: f64,
+    pub lp_value: f64,
+    pub impermanent_loss: f64,
+    pub net_pnl: f64,
+    pub net_pnl_versus_hold: f64,
+}
+
+impl LpPnl {
+    pub fn to_ui(&self) -> LpPnlUi {
+        let scale = 10_f64.powi(self.quote_decimals as i32);
+        LpPnlUi {
+            fees_earned: self.fees_earned_in_quote as f64 / scale,
+            entry_value: self.entry_value_in_quote as f64 / scale,
+            hold_value: self.hold_value_in_quote as f64 / scale,
+            lp_value: self.lp_value_in_quote as f64 / scale,
+            impermanent_loss: self.impermanent_loss_in_quote as f64 / scale,
+            net_pnl: self.net_pnl_in_quote as f64 / scale,
+            net_pnl_versus_hold: self.net_pnl_versus_hold_in_quote as f64 / scale,
+        }
+    }
+}
+
+/// Computes the PnL of `position` in `pool` relative to the deposit described by `entry`.
+///
+/// If part of the position was withdrawn, the entry and hold values only cover the deposit
+/// backing the shares still held, so withdrawn liquidity is not counted as a loss. Fees earned
+/// by the withdrawn shares are still included.
+pub fn compute_lp_pnl(
+    entry: &LpEntry,
+    pool: &PoolAccount,
+    position: &LpPosition,
+) -> Result<LpPnl, AnalyticsError> {
+    let amm = &pool.amm;
+    if amm.total_lp_shares == 0 || amm.base_reserves == 0 {
+        return Err(AnalyticsError::EmptyPool);
+    }
+    if entry.lp_shares == 0 {
+        return Err(AnalyticsError::EmptyEntry);
+    }
+    if position.lp_shares > entry.lp_shares {
+        return Err(AnalyticsError::PositionExceedsEntry {
+            entry_lp_shares: entry.lp_shares,
+            position_lp_shares: position.lp_shares,
+        });
+    }
+    let current_slot = amm.get_slot();
+
+    let (base_amount_redeemable, quote_amount_redeemable) = if position.lp_shares == 0 {
+        (0, 0)
+    } else {
+        let preview = amm.preview_burn(current_slot, position.lp_shares)?;
+        (
+            preview.base_amount_withdrawn,
+            preview.quote_amount_withdrawn,
+        )
+    };
+
+    let fees_earned_in_quote = position
+        .collected_fees()
+        .checked_add(position.uncollected_fees())
+        .zip(position.pending_fees(amm))
+        .and_then(|(fees, pending_fees)| fees.checked_add(pending_fees))
+        .ok_or(PlasmaStateError::Overflow)?;
+
+    let base_to_quote = |base_amount: u64| {
+        base_amount.upcast() * amm.quote_reserves.upcast() / amm.base_reserves.upcast()
+    };
+
+    // The part of the deposit backing the shares that are still held
+    let held = |amount: u64| {
+        (amount.upcast() * position.lp_shares.upcast() / entry.lp_shares.upcast()) as u64
+    };
+    let base_amount_held = held(entry.base_amount_deposited);
+    let quote_amount_held = held(entry.quote_amount_deposited);
+
+    // The entry price is implied by the deposit ratio, which `mint` keeps equal to the pool price
+    let entry_value_in_quote = if entry.base_amount_deposited == 0 {
+        quote_amount_held.upcast()
+    } else {
+        2 * quote_amount_held.upcast()
+    };
+    let hold_value_in_quote = base_to_quote(base_amount_held) + quote_amount_held.upcast();
+    let lp_value_in_quote =
+        base_to_quote(base_amount_redeemable) + quote_amount_redeemable.upcast();
+
+    let fees = fees_earned_in_quote.upcast() as i128;
+    let impermanent_loss_in_quote = lp_value_in_quote as i128 - hold_value_in_quote as i128;
+
+    Ok(LpPnl {
+        entry_slot: entry.slot,
+        current_slot,
+        base_amount_redeemable,
+        quote_amount_redeemable,
+        fees_earned_in_quote,
+        entry_value_in_quote,
+        hold_value_in_quote,
+        lp_value_in_quote,
+        impermanent_loss_in_quote,
+        net_pnl_in_quote: lp_value_in_quote as i128 + fees - entry_value_in_quote as i128,
+        net_pnl_versus_hold_in_quote: impermanent_loss_in_quote + fees,
+        quote_decimals: pool.header.quote_params.decimals,
+    })
+}
+
+pub const SECONDS_PER_YEAR: f64 = 365.0 * 24.0 * 60.0 * 60.0;
+
+/// A `PoolAccount` observed at a known slot and cluster time
+#[derive(Debug, Clone, Copy)]
+pub struct PoolSnapshot {
+    pub pool_key: Pubkey,
+    pub slot: u64,
+    pub unix_timestamp: i64,
+    pub pool: PoolAccount,
+}
+
+impl PoolSnapshot {
+    /// Total value locked in quote atoms, valuing base at the spot price
+    pub fn tvl_in_quote(&self) -> u128 {
+        2 * self.pool.amm.quote_reserves.upcast()
+    }
+}
+
+/// Fee rates derived from the growth of the cumulative fee counters between snapshots
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FeeRateEstimate {
+    pub start_slot: u64,
+    pub end_slot: u64,
+    /// Seconds covered by the intervals that were used
+    pub elapsed_seconds: i64,
+    /// Intervals dropped because they exceeded `max_gap_seconds` or time did not advance
+    pub skipped_intervals: usize,
+    pub lp_fees_in_quote: u64,
+    pub protocol_fees_in_quote: u64,
+    /// Swap volume implied by the collected fees and `fee_in_bps`
+    pub implied_volume_in_quote: u128,
+    /// Time-weighted TVL over the used intervals, so mints and burns in between are accounted for
+    pub average_tvl_in_quote: f64,
+    pub lp_fee_apr: f64,
+    /// `lp_fee_apr` compounded once per observed period
+    pub lp_fee_apy: f64,
+    pub protocol_revenue_per_year_in_quote: f64,
+}
+
+/// Estimates LP fee APR/APY and the protocol revenue rate from a series of snapshots of the
+/// same pool.
+///
+/// Snapshots are ordered by slot. Consecutive snapshots further apart than `max_gap_seconds`,
+/// or whose timestamps do not increase, are not used, since fees and TVL in between are unknown.
+pub fn estimate_fee_rates(
+    snapshots: &[PoolSnapshot],
+    max_gap_seconds: Option<i64>,
+) -> Result<FeeRateEstimate, AnalyticsError> {
+    let mut snapshots = snapshots.to_vec();
+    snapshots.sort_by_key(|snapshot| snapshot.slot);
+    snapshots.dedup_by_key(|snapshot| snapshot.slot);
+
+    let [first, .., last] = snapshots.as_slice() else {
+        return Err(AnalyticsError::NotEnoughSnapshots);
+    };
+    if snapshots
+        .iter()
+        .any(|snapshot| snapshot.pool_key != first.pool_key)
+    {
+        return Err(AnalyticsError::MixedPools);
+    }
+
+    let mut elapsed_seconds = 0_i64;
+    let mut skipped_intervals = 0;
+    let mut lp_fees_in_quote = 0_u64;
+    let mut protocol_fees_in_quote = 0_u64;
+    let mut implied_volume_in_quote = 0_u128;
+    let mut tvl_seconds = 0_f64;
+
+    for window in snapshots.windows(2) {
+        let (start, end) = (&window[0], &window[1]);
+        let timestamp_overflow = || AnalyticsError::TimestampOverflow {
+            start_slot: start.slot,
+            end_slot: end.slot,
+        };
+        let dt = end
+            .unix_timestamp
+            .checked_sub(start.unix_timestamp)
+            .ok_or_else(timestamp_overflow)?;
+        if dt <= 0 || max_gap_seconds.is_some_and(|max_gap| dt > max_gap) {
+            skipped_intervals += 1;
+            continue;
+        }
+        let counters_decreased = || AnalyticsError::FeeCountersDecreased {
+            start_slot: start.slot,
+            end_slot: end.slot,
+        };
+        let lp_fees = end
+            .pool
+            .amm
+            .cumulative_quote_lp_fees
+            .checked_sub(start.pool.amm.cumulative_quote_lp_fees)
+            .ok_or_else(counters_decreased)?;
+        let protocol_fees = end
+            .pool
+            .amm
+            .cumulative_quote_protocol_fees
+            .checked_sub(start.pool.amm.cumulative_quote_protocol_fees)
+            .ok_or_else(counters_decreased)?;
+
+        implied_volume_in_quote += ((lp_fees.upcast() + protocol_fees.upcast()) * BPS_BASE)
+            .checked_div(end.pool.amm.fee_in_bps.upcast())
+            .unwrap_or(0);
+
+        elapsed_seconds = elapsed_seconds
+            .checked_add(dt)
+            .ok_or_else(timestamp_overflow)?;
+        lp_fees_in_quote += lp_fees;
+        protocol_fees_in_quote += protocol_fees;
+        // Trapezoidal average of the TVL at both ends of the interval
+        tvl_seconds += (start.tvl_in_quote() + end.tvl_in_quote()) as f64 / 2.0 * dt as f64;
+    }
+
+    if elapsed_seconds == 0 {
+        return Err(AnalyticsError::NoUsableIntervals { skipped_intervals });
+    }
+
+    let elapsed = elapsed_seconds as f64;
+    let average_tvl_in_quote = tvl_seconds / elapsed;
+    let (lp_fee_apr, lp_fee_apy) = if average_tvl_in_quote > 0.0 {
+        let period_return = lp_fees_in_quote as f64 / average_tvl_in_quote;
+        let periods_per_year = SECONDS_PER_YEAR / elapsed;
+        (
+            period_return * periods_per_year,
+            (1.0 + period_return).powf(periods_per_year) - 1.0,
+        )
+    } else {
+        (0.0, 0.0)
+    };
+
+    Ok(FeeRateEstimate {
+        start_slot: first.slot,
+        end_slot: last.slot,
+        elapsed_seconds,
+        skipped_intervals,
+        lp_fees_in_quote,
+        protocol_fees_in_quote,
+        implied_volume_in_quote,
+        average_tvl_in_quote,
+        lp_fee_apr,
+        lp_fee_apy,
+        protocol_revenue_per_year_in_quote: protocol_fees_in_quote as f64 / elapsed
+            * SECONDS_PER_YEAR,
+    })
+}
@@ -0,0 +1,546 @@
+use crate::plasma::{
+    I80F48, PlasmaStateError, SlotWindow, Upcast,
+    plasma_amm::{Amm, BPS_BASE, Side, SwapResult},
+    slot_window,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BacktestError {
+    /// No price points were given
+    NoPrices,
+    /// The price points are not ordered by slot
+    UnorderedPrices,
+    /// The price at this slot is not finite and positive
+    InvalidPrice { slot: u64 },
+    /// The JIT deposit, `jit_liquidity_in_bps` of the reserves, does not fit in a `u64`
+    JitLiquidityOverflow,
+    /// A JIT position would vest after the last slot window
+    VestingOverflow,
+    /// The pool rejected the configuration
+    State(PlasmaStateError),
+}
+
+impl std::fmt::Display for BacktestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BacktestError::NoPrices => write!(f, "At least one price point is required"),
+            BacktestError::UnorderedPrices => write!(f, "Price points are not ordered by slot"),
+            BacktestError::InvalidPrice { slot } => {
+                write!(f, "Price at slot {} is not finite and positive", slot)
+            }
+            BacktestError::JitLiquidityOverflow => {
+                write!(f, "JIT liquidity does not fit in a u64")
+            }
+            BacktestError::VestingOverflow => {
+                write!(f, "JIT position vests after the last slot window")
+            }
+            BacktestError::State(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for BacktestError {}
+
+impl From<PlasmaStateError> for BacktestError {
+    fn from(error: PlasmaStateError) -> Self {
+        BacktestError::State(error)
+    }
+}
+
+/// An external reference price, in quote atoms per base atom
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PricePoint {
+    pub slot: u64,
+    pub price: f64,
+}
+
+/// An uninformed exact-in swap
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoiseOrder {
+    pub side: Side,
+    /// Quote atoms for buys, base atoms for sells
+    pub amount_in: u64,
+}
+
+/// Generates the noise orders that arrive at each price point. The same orders are sent to both
+/// pools of a backtest.
+pub trait OrderFlow {
+    fn orders(&mut self, point: &PricePoint) -> Vec<NoiseOrder>;
+}
+
+impl<F: FnMut(&PricePoint) -> Vec<NoiseOrder>> OrderFlow for F {
+    fn orders(&mut self, point: &PricePoint) -> Vec<NoiseOrder> {
+        self(point)
+    }
+}
+
+/// Deterministic order flow of `orders_per_point` orders per price point, each a buy or a sell
+/// with equal probability and an exponentially distributed size
+#[derive(Debug, Clone, Copy)]
+pub struct RandomOrderFlow {
+    state: u64,
+    pub orders_per_point: u32,
+    pub mean_size_in_quote: f64,
+}
+
+impl RandomOrderFlow {
+    pub fn new(seed: u64, orders_per_point: u32, mean_size_in_quote: f64) -> Self {
+        Self {
+            state: seed,
+            orders_per_point,
+            mean_size_in_quote,
+        }
+    }
+
+    /// splitmix64, mapped to a float in (0, 1]
+    fn next_unit(&mut self) -> f64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        ((z >> 11) + 1) as f64 / (1_u64 << 53) as f64
+    }
+}
+
+impl OrderFlow for RandomOrderFlow {
+    fn orders(&mut self, point: &PricePoint) -> Vec<NoiseOrder> {
+        (0..self.orders_per_point)
+            .map(|_| {
+                let side = if self.next_unit() <= 0.5 {
+                    Side::Buy
+                } else {
+                    Side::Sell
+                };
+                let size_in_quote = -self.mean_size_in_quote * self.next_unit().ln();
+                let amount_in = match side {
+                    Side::Buy => size_in_quote,
+                    Side::Sell => size_in_quote / point.price,
+                };
+                NoiseOrder {
+                    side,
+                    amount_in: amount_in as u64,
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BacktestConfig {
+    pub fee_in_bps: u32,
+    pub protocol_allocation_in_pct: u32,
+    /// Slot windows before shares minted into the Plasma pool can be withdrawn. The constant
+    /// product pool has no vesting.
+    pub lp_vesting_window: SlotWindow,
+    /// Deposit of the passive LP that seeds both pools. Arbitrageurs move the pools to the first
+    /// external price.
+    pub base_liquidity: u64,
+    pub quote_liquidity: u64,
+    /// Arbitrageurs only trade when they make at least this much, valued at the external price
+    pub min_arbitrage_profit_in_quote: f64,
+    /// Liquidity a just-in-time LP adds ahead of noise orders, in basis points of the pool's
+    /// reserves. The position is withdrawn as soon as it vests. Zero disables the JIT LP.
+    pub jit_liquidity_in_bps: u32,
+}
+
+/// Activity in one pool during one slot window. Balances and values are taken at the last price
+/// point of the window.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct WindowStats {
+    pub noise_volume_in_quote: u128,
+    pub arbitrage_volume_in_quote: u128,
+    /// Profit arbitrageurs made, valued at the external price. This is value lost by the LPs.
+    pub arbitrage_profit_in_quote: f64,
+    pub rejected_orders: usize,
+    pub lp_fees_in_quote: u64,
+    pub protocol_fees_in_quote: u64,
+    /// Portion of `lp_fees_in_quote` earned by the passive LP. Each LP's fees are rounded down,
+    /// so the two portions may differ from the total by a few atoms.
+    pub passive_lp_fees_in_quote: u64,
+    /// Portion of `lp_fees_in_quote` earned by the JIT LP
+    pub jit_lp_fees_in_quote: u64,
+    pub base_reserves: u64,
+    pub quote_reserves: u64,
+    /// The passive LP's share of the reserves at the external price, excluding fees
+    pub passive_lp_value_in_quote: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WindowReport {
+    pub slot_window: SlotWindow,
+    /// External price at the last price point of the window
+    pub external_price: f64,
+    pub plasma: WindowStats,
+    pub constant_product: WindowStats,
+}
+
+/// Totals over a whole backtest for one pool
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BacktestSummary {
+    pub noise_volume_in_quote: u128,
+    pub arbitrage_volume_in_quote: u128,
+    pub arbitrage_profit_in_quote: f64,
+    pub rejected_orders: usize,
+    pub lp_fees_in_quote: u64,
+    pub protocol_fees_in_quote: u64,
+    pub passive_lp_fees_in_quote: u64,
+    pub jit_lp_fees_in_quote: u64,
+    /// The passive LP's share of the reserves at the last external price, excluding fees
+    pub passive_lp_value_in_quote: f64,
+    /// Value of the passive LP's deposit at the last external price had it been held
+    pub hold_value_in_quote: f64,
+    /// `passive_lp_value + passive_lp_fees - hold_value`
+    pub passive_lp_pnl_versus_hold_in_quote: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BacktestReport {
+    /// One entry per slot window that contains a price point, in order
+    pub windows: Vec<WindowReport>,
+    pub plasma: BacktestSummary,
+    pub constant_product: BacktestSummary,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct LpShares {
+    lp_shares: u64,
+    reward_factor_snapshot: I80F48,
+}
+
+impl LpShares {
+    fn fees(&self, amm: &Amm) -> u64 {
+        let reward_factor_delta = amm.reward_factor - self.reward_factor_snapshot;
+        if reward_factor_delta <= I80F48::ZERO {
+            return 0;
+        }
+        (reward_factor_delta * I80F48::from_num(self.lp_shares)).floor()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct JitPosition {
+    shares: LpShares,
+    entry_window: SlotWindow,
+}
+
+/// One of the two simulated pools, along with the LPs in it
+#[derive(Debug, Clone, Copy)]
+struct Market {
+    amm: Amm,
+    /// Refresh the snapshot before every operation, which keeps the virtual limit order empty
+    /// and turns the `Amm` into a plain constant product pool with the same fees and rounding
+    constant_product: bool,
+    lp_vesting_window: SlotWindow,
+    passive: LpShares,
+    jit: Option<JitPosition>,
+    jit_fees_realized: u64,
+    /// Stats of the current window, with fee counters holding their values at the window start
+    window: WindowStats,
+    totals: BacktestSummary,
+}
+
+impl Market {
+    fn new(config: &BacktestConfig, constant_product: bool) -> Result<Self, BacktestError> {
+        let mut amm = Amm::new(config.fee_in_bps, config.protocol_allocation_in_pct, 0, 0);
+        let (_, _, lp_shares) = amm.mint(
+            0,
+            config.base_liquidity,
+            config.quote_liquidity,
+            Some(Amm::initial_lp_shares(
+                config.base_liquidity,
+                config.quote_liquidity,
+            )),
+        )?;
+        Ok(Self {
+            amm,
+            constant_product,
+            lp_vesting_window: if constant_product {
+                0
+            } else {
+                config.lp_vesting_window
+            },
+            passive: LpShares {
+                lp_shares,
+                reward_factor_snapshot: amm.reward_factor,
+            },
+            jit: None,
+            jit_fees_realized: 0,
+            window: WindowStats::default(),
+            totals: BacktestSummary::default(),
+        })
+    }
+
+    fn prepare(amm: &mut Amm, constant_product: bool) {
+        if constant_product {
+            amm.base_reserves_snapshot = amm.base_reserves;
+            amm.quote_reserves_snapshot = amm.quote_reserves;
+        }
+    }
+
+    fn swap(
+        &self,
+        window: SlotWindow,
+        side: Side,
+        amount_in: u64,
+    ) -> Result<(Amm, SwapResult), PlasmaStateError> {
+        let mut amm = self.amm;
+        Self::prepare(&mut amm, self.constant_product);
+        let result = match side {
+            Side::Buy => amm.buy_exact_in(window, amount_in)?,
+            Side::Sell => amm.sell_exact_in(window, amount_in)?,
+        };
+        Ok((amm, result))
+    }
+
+    fn jit_fees(&self) -> u64 {
+        self.jit_fees_realized + self.jit.map(|jit| jit.shares.fees(&self.amm)).unwrap_or(0)
+    }
+
+    /// Starts a new window by recording the fee counters the window's fees are measured from
+    fn open_window(&mut self) {
+        self.window = WindowStats {
+            lp_fees_in_quote: self.amm.cumulative_quote_lp_fees,
+            protocol_fees_in_quote: self.amm.cumulative_quote_protocol_fees,
+            passive_lp_fees_in_quote: self.passive.fees(&self.amm),
+            jit_lp_fees_in_quote: self.jit_fees(),
+            ..Default::default()
+        };
+    }
+
+    fn close_window(&mut self, price: f64) -> WindowStats {
+        let amm = &self.amm;
+        let mut stats = self.window;
+        stats.lp_fees_in_quote = amm.cumulative_quote_lp_fees - stats.lp_fees_in_quote;
+        stats.protocol_fees_in_quote =
+            amm.cumulative_quote_protocol_fees - stats.protocol_fees_in_quote;
+        stats.passive_lp_fees_in_quote = self.passive.fees(amm) - stats.passive_lp_fees_in_quote;
+        stats.jit_lp_fees_in_quote = self.jit_fees() - stats.jit_lp_fees_in_quote;
+        stats.base_reserves = amm.base_reserves;
+        stats.quote_reserves = amm.quote_reserves;
+        stats.passive_lp_value_in_quote = self.passive_lp_value(price);
+
+        let totals = &mut self.totals;
+        totals.noise_volume_in_quote += stats.noise_volume_in_quote;
+        totals.arbitrage_volume_in_quote += stats.arbitrage_volume_in_quote;
+        totals.arbitrage_profit_in_quote += stats.arbitrage_profit_in_quote;
+        totals.rejected_orders += stats.rejected_orders;
+        totals.lp_fees_in_quote += stats.lp_fees_in_quote;
+        totals.protocol_fees_in_quote += stats.protocol_fees_in_quote;
+        totals.passive_lp_fees_in_quote += stats.passive_lp_fees_in_quote;
+        totals.jit_lp_fees_in_quote += stats.jit_lp_fees_in_quote;
+        stats
+    }
+
+    fn passive_lp_value(&self, price: f64) -> f64 {
+        let amm = &self.amm;
+        (amm.base_reserves as f64 * price + amm.quote_reserves as f64)
+            * self.passive.lp_shares as f64
+            / amm.total_lp_shares as f64
+    }
+
+    /// Makes the most profitable exact-in trade against the pool, valued at the external `price`
+    fn arbitrage(&mut self, window: SlotWindow, price: f64, min_profit: f64) {
+        let profit = |side: Side, amount_in: u64| -> Option<(f64, Amm, SwapResult)> {
+            let (amm, result) = self.swap(window, side, amount_in).ok()?;
+            let profit = match side {
+                Side::Buy => {
+                    result.base_amount_to_transfer as f64 * price
+                        - result.quote_amount_to_transfer as f64
+                }
+                Side::Sell => {
+                    result.quote_amount_to_transfer as f64
+                        - result.base_amount_to_transfer as f64 * price
+                }
+            };
+            Some((profit, amm, result))
+        };
+
+        let best = [Side::Buy, Side::Sell]
+            .into_iter()
+            .filter_map(|side| {
+                let amount_in = maximize(|amount_in| profit(side, amount_in).map(|p| p.0))?;
+                profit(side, amount_in)
+            })
+            .max_by(|a, b| a.0.total_cmp(&b.0));
+        if let Some((profit, amm, result)) = best
+            && profit >= min_profit.max(f64::MIN_POSITIVE)
+        {
+            self.amm = amm;
+            self.window.arbitrage_volume_in_quote += result.quote_amount_to_transfer.upcast();
+            self.window.arbitrage_profit_in_quote += profit;
+        }
+    }
+
+    fn fill(&mut self, window: SlotWindow, order: &NoiseOrder) {
+        match self.swap(window, order.side, order.amount_in) {
+            Ok((amm, result)) => {
+                self.amm = amm;
+                self.window.noise_volume_in_quote += result.quote_amount_to_transfer.upcast();
+            }
+            Err(_) => self.window.rejected_orders += 1,
+        }
+    }
+
+    fn enter_jit(
+        &mut self,
+        window: SlotWindow,
+        jit_liquidity_in_bps: u32,
+    ) -> Result<(), BacktestError> {
+        if jit_liquidity_in_bps == 0 || self.jit.is_some() {
+            return Ok(());
+        }
+        let mut amm = self.amm;
+        Self::prepare(&mut amm, self.constant_product);
+        let deposit = |reserves: u64, round_up: u128| {
+            u64::try_from(reserves.upcast() * jit_liquidity_in_bps.upcast() / BPS_BASE + round_up)
+                .map_err(|_| BacktestError::JitLiquidityOverflow)
+        };
+        let base_amount = deposit(amm.base_reserves, 0)?;
+        let quote_amount = deposit(amm.quote_reserves, 1)?;
+        // A deposit the pool rejects is skipped like a rejected order
+        if let Ok((_, _, lp_shares)) = amm.mint(window, base_amount, quote_amount, None) {
+            self.jit = Some(JitPosition {
+                shares: LpShares {
+                    lp_shares,
+                    reward_factor_snapshot: amm.reward_factor,
+                },
+                entry_window: window,
+            });
+            self.amm = amm;
+        }
+        Ok(())
+    }
+
+    fn exit_jit_if_vested(&mut self, window: SlotWindow) -> Result<(), BacktestError> {
+        let Some(jit) = self.jit else {
+            return Ok(());
+        };
+        let vested_window = jit
+            .entry_window
+            .checked_add(self.lp_vesting_window)
+            .ok_or(BacktestError::VestingOverflow)?;
+        if window < vested_window {
+            return Ok(());
+        }
+        let mut amm = self.amm;
+        Self::prepare(&mut amm, self.constant_product);
+        if amm.burn(window, jit.shares.lp_shares).is_ok() {
+            self.jit_fees_realized += jit.shares.fees(&amm);
+            self.jit = None;
+            self.amm = amm;
+        }
+        Ok(())
+    }
+}
+
+/// Finds the amount in that maximizes a concave `profit`, which returns `None` for amounts the
+/// pool rejects. Returns `None` if no amount is profitable.
+fn maximize(profit: impl Fn(u64) -> Option<f64>) -> Option<u64> {
+    let value = |amount_in: u64| profit(amount_in).unwrap_or(f64::NEG_INFINITY);
+    // Rounding makes tiny trades unprofitable, so the maximum is bracketed by scanning powers of
+    // two rather than by walking up from 1
+    let exponent = (0..64)
+        .max_by(|a, b| value(1 << a).total_cmp(&value(1 << b)))
+        .unwrap();
+    let (mut lo, mut hi) = (
+        (1_u64 << exponent) / 2,
+        (1_u64 << exponent).saturating_mul(2),
+    );
+    while hi - lo > 2 {
+        let m1 = lo + (hi - lo) / 3;
+        let m2 = hi - (hi - lo) / 3;
+        if value(m1) < value(m2) {
+            lo = m1;
+        } else {
+            hi = m2;
+        }
+    }
+    (lo..=hi)
+        .max_by(|a, b| value(*a).total_cmp(&value(*b)))
+        .filter(|amount_in| value(*amount_in) > 0.0)
+}
+
+/// Runs arbitrageurs and noise traders against a Plasma pool and, with the same prices and
+/// orders, against a constant product pool without the snapshot limit order or LP vesting.
+///
+/// At every price point, vested JIT positions are withdrawn, arbitrageurs trade each pool towards
+/// the external price, and then the noise orders are filled, with the JIT LP depositing ahead of
+/// them. `prices` must be ordered by slot.
+pub fn run_backtest(
+    config: &BacktestConfig,
+    prices: &[PricePoint],
+    order_flow: &mut impl OrderFlow,
+) -> Result<BacktestReport, BacktestError> {
+    let Some(first) = prices.first() else {
+        return Err(BacktestError::NoPrices);
+    };
+    if prices.windows(2).any(|pair| pair[0].slot > pair[1].slot) {
+        return Err(BacktestError::UnorderedPrices);
+    }
+    if let Some(point) = prices
+        .iter()
+        .find(|point| !point.price.is_finite() || point.price <= 0.0)
+    {
+        return Err(BacktestError::InvalidPrice { slot: point.slot });
+    }
+
+    let mut markets = [Market::new(config, false)?, Market::new(config, true)?];
+    let mut windows = vec![];
+    let mut current_window = slot_window(first.slot);
+    let mut last_price = first.price;
+    markets.iter_mut().for_each(Market::open_window);
+
+    for point in prices {
+        let window = slot_window(point.slot);
+        if window != current_window {
+            let [plasma, constant_product] = markets.each_mut().map(|m| m.close_window(last_price));
+            windows.push(WindowReport {
+                slot_window: current_window,
+                external_price: last_price,
+                plasma,
+                constant_product,
+            });
+            markets.iter_mut().for_each(Market::open_window);
+            current_window = window;
+        }
+        last_price = point.price;
+
+        let orders = order_flow.orders(point);
+        for market in markets.iter_mut() {
+            market.exit_jit_if_vested(window)?;
+            market.arbitrage(window, point.price, config.min_arbitrage_profit_in_quote);
+            if !orders.is_empty() {
+                market.enter_jit(window, config.jit_liquidity_in_bps)?;
+            }
+            for order in orders.iter() {
+                market.fill(window, order);
+            }
+            market.exit_jit_if_vested(window)?;
+        }
+    }
+
+    let [plasma, constant_product] = markets.each_mut().map(|m| m.close_window(last_price));
+    windows.push(WindowReport {
+        slot_window: current_window,
+        external_price: last_price,
+        plasma,
+        constant_product,
+    });
+
+    let hold_value_in_quote =
+        config.base_liquidity as f64 * last_price + config.quote_liquidity as f64;
+    let [plasma, constant_product] = markets.map(|market| BacktestSummary {
+        passive_lp_value_in_quote: market.passive_lp_value(last_price),
+        hold_value_in_quote,
+        passive_lp_pnl_versus_hold_in_quote: market.passive_lp_value(last_price)
+            + market.totals.passive_lp_fees_in_quote as f64
+            - hold_value_in_quote,
+        ..market.totals
+    });
+    Ok(BacktestReport {
+        windows,
+        plasma,
+        constant_product,
+    })
+}
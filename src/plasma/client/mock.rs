@@ -0,0 +1,105 @@
+use std::sync::{Mutex, MutexGuard};
+
+use solana_account::Account;
+use solana_program::{
+    hash::{Hash, hash},
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+use solana_sdk::{sanitize::Sanitize, signature::Signature, transaction::Transaction};
+
+use crate::plasma::{
+    client::{AccountFetcher, ClientError, TransactionSender},
+    reference::ReferenceRuntime,
+};
+
+/// In-memory backend that executes transactions on a [`ReferenceRuntime`].
+///
+/// Signatures are verified and transactions are atomic, like on a validator. The blockhash is
+/// derived from the current slot and is not checked.
+#[derive(Default)]
+pub struct MockRpc {
+    runtime: Mutex<ReferenceRuntime>,
+    transactions: Mutex<Vec<Transaction>>,
+}
+
+impl MockRpc {
+    pub fn new(runtime: ReferenceRuntime) -> Self {
+        Self {
+            runtime: Mutex::new(runtime),
+            transactions: Mutex::new(vec![]),
+        }
+    }
+
+    /// Locks the runtime to set up or inspect accounts and the slot
+    pub fn runtime(&self) -> MutexGuard<'_, ReferenceRuntime> {
+        self.runtime.lock().unwrap()
+    }
+
+    /// Every transaction that was executed successfully, in order
+    pub fn transactions(&self) -> Vec<Transaction> {
+        self.transactions.lock().unwrap().clone()
+    }
+}
+
+impl AccountFetcher for MockRpc {
+    async fn get_multiple_accounts(
+        &self,
+        keys: &[Pubkey],
+    ) -> Result<Vec<Option<Account>>, ClientError> {
+        let runtime = self.runtime();
+        Ok(keys
+            .iter()
+            .map(|key| runtime.get_account(key).cloned())
+            .collect())
+    }
+
+    async fn get_slot(&self) -> Result<u64, ClientError> {
+        Ok(self.runtime().slot())
+    }
+}
+
+impl TransactionSender for MockRpc {
+    async fn get_latest_blockhash(&self) -> Result<Hash, ClientError> {
+        Ok(hash(&self.runtime().slot().to_le_bytes()))
+    }
+
+    async fn send_transaction(&self, transaction: &Transaction) -> Result<Signature, ClientError> {
+        // The RPC node rejects malformed messages before they reach the runtime, so the account
+        // indices below are in bounds
+        transaction
+            .sanitize()
+            .map_err(|error| ClientError::Rpc(format!("invalid transaction: {}", error)))?;
+        transaction
+            .verify()
+            .map_err(|error| ClientError::TransactionFailed(error.to_string()))?;
+        let message = &transaction.message;
+        let meta = |index: u8| {
+            let index = index as usize;
+            AccountMeta {
+                pubkey: message.account_keys[index],
+                is_signer: message.is_signer(index),
+                is_writable: message.is_maybe_writable(index, None),
+            }
+        };
+        let instructions = message
+            .instructions
+            .iter()
+            .map(|instruction| Instruction {
+                program_id: message.account_keys[instruction.program_id_index as usize],
+                accounts: instruction.accounts.iter().copied().map(meta).collect(),
+                data: instruction.data.clone(),
+            })
+            .collect::<Vec<_>>();
+        let signers = message
+            .signer_keys()
+            .into_iter()
+            .copied()
+            .collect::<Vec<_>>();
+        self.runtime()
+            .process_transaction(&instructions, &signers)
+            .map_err(|error| ClientError::TransactionFailed(error.to_string()))?;
+        self.transactions.lock().unwrap().push(transaction.clone());
+        Ok(transaction.signatures[0])
+    }
+}
@@ -0,0 +1,355 @@
+use std::fmt::Display;
+
+use solana_account::Account;
+use solana_program::{instruction::Instruction, pubkey::Pubkey};
+use solana_sdk::{
+    hash::Hash,
+    signature::{Keypair, Signature},
+    signer::Signer,
+    transaction::Transaction,
+};
+
+use crate::{
+    PoolAccount,
+    plasma::{
+        AddLiquidityParams, ID, LpPosition, PlasmaStateError, Side, SlotWindow, SwapParams,
+        SwapType, add_liquidity, get_lp_position_address, initialize_lp_position,
+        plasma_amm::{BurnPreview, MintPreview, SwapResult},
+        remove_liquidity, slot_window, swap, transfer_liquidity,
+    },
+};
+
+#[cfg(feature = "mock")]
+mod mock;
+
+#[cfg(feature = "mock")]
+pub use mock::MockRpc;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientError {
+    /// The backend failed to serve the request
+    Rpc(String),
+    AccountNotFound(Pubkey),
+    /// The account exists but is not the expected Plasma account
+    InvalidAccount(Pubkey),
+    State(PlasmaStateError),
+    Signing(String),
+    /// The transaction was sent but rejected
+    TransactionFailed(String),
+}
+
+impl Display for ClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClientError::Rpc(error) => write!(f, "RPC error: {}", error),
+            ClientError::AccountNotFound(key) => write!(f, "Account {} not found", key),
+            ClientError::InvalidAccount(key) => {
+                write!(f, "Account {} is not a valid Plasma account", key)
+            }
+            ClientError::State(error) => write!(f, "{}", error),
+            ClientError::Signing(error) => write!(f, "Failed to sign transaction: {}", error),
+            ClientError::TransactionFailed(error) => write!(f, "Transaction failed: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<PlasmaStateError> for ClientError {
+    fn from(error: PlasmaStateError) -> Self {
+        ClientError::State(error)
+    }
+}
+
+/// Read side of an RPC backend
+pub trait AccountFetcher: Sync {
+    /// Returns one entry per key, `None` for accounts that do not exist
+    fn get_multiple_accounts(
+        &self,
+        keys: &[Pubkey],
+    ) -> impl Future<Output = Result<Vec<Option<Account>>, ClientError>> + Send;
+
+    fn get_slot(&self) -> impl Future<Output = Result<u64, ClientError>> + Send;
+
+    fn get_account(
+        &self,
+        key: &Pubkey,
+    ) -> impl Future<Output = Result<Option<Account>, ClientError>> + Send {
+        async move {
+            Ok(self
+                .get_multiple_accounts(std::slice::from_ref(key))
+                .await?
+                .pop()
+                .flatten())
+        }
+    }
+}
+
+/// Write side of an RPC backend
+pub trait TransactionSender: Sync {
+    fn get_latest_blockhash(&self) -> impl Future<Output = Result<Hash, ClientError>> + Send;
+
+    /// Sends a signed transaction and waits until it is processed
+    fn send_transaction(
+        &self,
+        transaction: &Transaction,
+    ) -> impl Future<Output = Result<Signature, ClientError>> + Send;
+}
+
+pub fn decode_pool(key: &Pubkey, account: &Account) -> Result<PoolAccount, ClientError> {
+    if account.owner != ID {
+        return Err(ClientError::InvalidAccount(*key));
+    }
+    PoolAccount::decode(&account.data).ok_or(ClientError::InvalidAccount(*key))
+}
+
+pub fn decode_lp_position(key: &Pubkey, account: &Account) -> Result<LpPosition, ClientError> {
+    if account.owner != ID {
+        return Err(ClientError::InvalidAccount(*key));
+    }
+    LpPosition::decode(&account.data).ok_or(ClientError::InvalidAccount(*key))
+}
+
+/// Fetches Plasma state, quotes against it, and signs and sends Plasma instructions with `payer`.
+///
+/// The payer is also the trader, LP position owner and token account owner of every instruction.
+pub struct PlasmaClient<B> {
+    backend: B,
+    payer: Keypair,
+}
+
+impl<B: AccountFetcher + TransactionSender> PlasmaClient<B> {
+    pub fn new(backend: B, payer: Keypair) -> Self {
+        Self { backend, payer }
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn payer(&self) -> Pubkey {
+        self.payer.pubkey()
+    }
+
+    pub async fn fetch_pool(&self, pool_key: &Pubkey) -> Result<PoolAccount, ClientError> {
+        let account = self
+            .backend
+            .get_account(pool_key)
+            .await?
+            .ok_or(ClientError::AccountNotFound(*pool_key))?;
+        decode_pool(pool_key, &account)
+    }
+
+    /// Fetches several pools in one request
+    pub async fn fetch_pools(&self, pool_keys: &[Pubkey]) -> Result<Vec<PoolAccount>, ClientError> {
+        let accounts = self.backend.get_multiple_accounts(pool_keys).await?;
+        pool_keys
+            .iter()
+            .zip(accounts)
+            .map(|(key, account)| {
+                decode_pool(key, &account.ok_or(ClientError::AccountNotFound(*key))?)
+            })
+            .collect()
+    }
+
+    /// Returns `None` if `owner` has no LP position in the pool
+    pub async fn fetch_lp_position(
+        &self,
+        pool_key: &Pubkey,
+        owner: &Pubkey,
+    ) -> Result<Option<LpPosition>, ClientError> {
+        let (lp_position_key, _) = get_lp_position_address(&ID, pool_key, owner);
+        self.backend
+            .get_account(&lp_position_key)
+            .await?
+            .map(|account| decode_lp_position(&lp_position_key, &account))
+            .transpose()
+    }
+
+    pub async fn slot_window(&self) -> Result<SlotWindow, ClientError> {
+        Ok(slot_window(self.backend.get_slot().await?))
+    }
+
+    async fn fetch_pool_at_current_window(
+        &self,
+        pool_key: &Pubkey,
+    ) -> Result<(PoolAccount, SlotWindow), ClientError> {
+        let pool = self.fetch_pool(pool_key).await?;
+        Ok((pool, self.slot_window().await?))
+    }
+
+    /// Simulates the swap against the latest pool state at the current slot window. The limits
+    /// in `params` are not checked.
+    pub async fn quote(
+        &self,
+        pool_key: &Pubkey,
+        params: &SwapParams,
+    ) -> Result<SwapResult, ClientError> {
+        let (mut pool, window) = self.fetch_pool_at_current_window(pool_key).await?;
+        Ok(params.execute(&mut pool.amm, window)?)
+    }
+
+    pub async fn preview_add_liquidity(
+        &self,
+        pool_key: &Pubkey,
+        base_amount: u64,
+        quote_amount: u64,
+    ) -> Result<MintPreview, ClientError> {
+        let (pool, window) = self.fetch_pool_at_current_window(pool_key).await?;
+        Ok(pool.amm.preview_mint(window, base_amount, quote_amount)?)
+    }
+
+    pub async fn preview_remove_liquidity(
+        &self,
+        pool_key: &Pubkey,
+        shares: u64,
+    ) -> Result<BurnPreview, ClientError> {
+        let (pool, window) = self.fetch_pool_at_current_window(pool_key).await?;
+        Ok(pool.amm.preview_burn(window, shares)?)
+    }
+
+    /// Signs `instructions` with the payer and `signers`, then sends them in one transaction
+    pub async fn send(
+        &self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<Signature, ClientError> {
+        let recent_blockhash = self.backend.get_latest_blockhash().await?;
+        let mut transaction = Transaction::new_with_payer(instructions, Some(&self.payer()));
+        let signers = [&self.payer].into_iter().chain(signers.iter().copied());
+        transaction
+            .try_sign(&signers.collect::<Vec<_>>(), recent_blockhash)
+            .map_err(|error| ClientError::Signing(error.to_string()))?;
+        self.backend.send_transaction(&transaction).await
+    }
+
+    pub async fn swap(
+        &self,
+        pool_key: &Pubkey,
+        base_account: &Pubkey,
+        quote_account: &Pubkey,
+        params: SwapParams,
+    ) -> Result<Signature, ClientError> {
+        let pool = self.fetch_pool(pool_key).await?;
+        let instruction = swap(
+            pool_key,
+            &self.payer(),
+            &pool.header.base_params.mint_key,
+            &pool.header.quote_params.mint_key,
+            base_account,
+            quote_account,
+            params,
+        );
+        self.send(&[instruction], &[]).await
+    }
+
+    /// Quotes the swap, then sends it with the limit set `slippage_in_bps` away from the quote.
+    /// The limit in `swap_type` is ignored, and `slippage_in_bps` is capped at 10_000.
+    pub async fn swap_with_slippage(
+        &self,
+        pool_key: &Pubkey,
+        base_account: &Pubkey,
+        quote_account: &Pubkey,
+        side: Side,
+        swap_type: SwapType,
+        slippage_in_bps: u64,
+    ) -> Result<Signature, ClientError> {
+        let slippage_in_bps = slippage_in_bps.min(10_000);
+        let (mut pool, window) = self.fetch_pool_at_current_window(pool_key).await?;
+        let params = SwapParams { side, swap_type };
+        let result = params.execute(&mut pool.amm, window)?;
+        let (amount_in, amount_out) = match side {
+            Side::Buy => (
+                result.quote_amount_to_transfer,
+                result.base_amount_to_transfer,
+            ),
+            Side::Sell => (
+                result.base_amount_to_transfer,
+                result.quote_amount_to_transfer,
+            ),
+        };
+        let swap_type = match swap_type {
+            SwapType::ExactIn { amount_in, .. } => SwapType::ExactIn {
+                amount_in,
+                min_amount_out: apply_bps(amount_out, 10_000 - slippage_in_bps),
+            },
+            SwapType::ExactOut { amount_out, .. } => SwapType::ExactOut {
+                amount_out,
+                max_amount_in: apply_bps(amount_in, 10_000 + slippage_in_bps),
+            },
+        };
+        let instruction = swap(
+            pool_key,
+            &self.payer(),
+            &pool.header.base_params.mint_key,
+            &pool.header.quote_params.mint_key,
+            base_account,
+            quote_account,
+            SwapParams { side, swap_type },
+        );
+        self.send(&[instruction], &[]).await
+    }
+
+    pub async fn initialize_lp_position(
+        &self,
+        pool_key: &Pubkey,
+    ) -> Result<Signature, ClientError> {
+        let payer = self.payer();
+        self.send(&[initialize_lp_position(pool_key, &payer, &payer)], &[])
+            .await
+    }
+
+    pub async fn add_liquidity(
+        &self,
+        pool_key: &Pubkey,
+        base_account: &Pubkey,
+        quote_account: &Pubkey,
+        params: AddLiquidityParams,
+    ) -> Result<Signature, ClientError> {
+        let pool = self.fetch_pool(pool_key).await?;
+        let instruction = add_liquidity(
+            pool_key,
+            &self.payer(),
+            &pool.header.base_params.mint_key,
+            base_account,
+            &pool.header.quote_params.mint_key,
+            quote_account,
+            params,
+        );
+        self.send(&[instruction], &[]).await
+    }
+
+    pub async fn remove_liquidity(
+        &self,
+        pool_key: &Pubkey,
+        base_account: &Pubkey,
+        quote_account: &Pubkey,
+        shares: u64,
+    ) -> Result<Signature, ClientError> {
+        let pool = self.fetch_pool(pool_key).await?;
+        let instruction = remove_liquidity(
+            pool_key,
+            &self.payer(),
+            &pool.header.base_params.mint_key,
+            &pool.header.quote_params.mint_key,
+            base_account,
+            quote_account,
+            shares,
+        );
+        self.send(&[instruction], &[]).await
+    }
+
+    /// Moves the payer's whole LP position to `destination`'s position, which must exist
+    pub async fn transfer_liquidity(
+        &self,
+        pool_key: &Pubkey,
+        destination: &Pubkey,
+    ) -> Result<Signature, ClientError> {
+        let instruction = transfer_liquidity(pool_key, &self.payer(), destination);
+        self.send(&[instruction], &[]).await
+    }
+}
+
+fn apply_bps(amount: u64, bps: u64) -> u64 {
+    (amount as u128 * bps as u128 / 10_000).min(u64::MAX as u128) as u64
+}
@@ -0,0 +1,269 @@
+//! Compute unit estimates for Plasma instructions, and `ComputeBudget` instructions priced by a
+//! priority fee policy.
+//!
+//! Estimates come either from [`ComputeUnitTable`], a fixed cost per instruction type, or from
+//! [`simulate_compute_units`], which runs the instructions on the reference processor and charges
+//! each CPI it performs. No costs have been measured against the deployed program, so both take
+//! their costs from the caller. [`ComputeUnitTable::CONSERVATIVE`] charges every instruction the
+//! limit the runtime would apply to it, which is safe but leaves no room for a tight limit.
+
+use solana_account::Account;
+use solana_program::{instruction::Instruction, pubkey::Pubkey, system_program};
+use solana_sdk::compute_budget::{self, ComputeBudgetInstruction};
+
+use crate::plasma::{
+    ID, PlasmaInstruction,
+    processor::{self, ProcessorContext, ProcessorError},
+    reference::ReferenceRuntime,
+};
+
+/// Largest compute unit limit a transaction can request
+pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+/// Limit the runtime applies to an instruction of a program other than a builtin when the
+/// transaction sets no limit
+pub const DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT: u32 = 200_000;
+
+/// Limit the runtime applies to a builtin instruction when the transaction sets no limit
+pub const MAX_BUILTIN_INSTRUCTION_COMPUTE_UNIT_LIMIT: u32 = 3_000;
+
+const MICRO_LAMPORTS_PER_LAMPORT: u64 = 1_000_000;
+
+/// Compute units charged for each instruction type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComputeUnitTable {
+    pub swap: u32,
+    pub add_liquidity: u32,
+    pub remove_liquidity: u32,
+    pub initialize_lp_position: u32,
+    pub initialize_pool: u32,
+    pub transfer_liquidity: u32,
+    /// System and compute budget instructions
+    pub builtin: u32,
+    /// Instructions of other programs, and Plasma instructions that do not decode
+    pub other: u32,
+}
+
+impl ComputeUnitTable {
+    /// The limits the runtime applies to each instruction when the transaction sets none
+    pub const CONSERVATIVE: Self = Self {
+        swap: DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT,
+        add_liquidity: DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT,
+        remove_liquidity: DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT,
+        initialize_lp_position: DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT,
+        initialize_pool: DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT,
+        transfer_liquidity: DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT,
+        builtin: MAX_BUILTIN_INSTRUCTION_COMPUTE_UNIT_LIMIT,
+        other: DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT,
+    };
+
+    /// Estimate for a single instruction
+    pub fn estimate(&self, instruction: &Instruction) -> u32 {
+        match instruction.program_id {
+            ID => match PlasmaInstruction::unpack(&instruction.data) {
+                Some(PlasmaInstruction::Swap(_)) => self.swap,
+                Some(PlasmaInstruction::AddLiquidity(_)) => self.add_liquidity,
+                Some(PlasmaInstruction::RemoveLiquidity { .. }) => self.remove_liquidity,
+                Some(PlasmaInstruction::InitializeLpPosition) => self.initialize_lp_position,
+                Some(PlasmaInstruction::InitializePool(_)) => self.initialize_pool,
+                Some(PlasmaInstruction::TransferLiquidity) => self.transfer_liquidity,
+                None => self.other,
+            },
+            system_program::ID | compute_budget::ID => self.builtin,
+            _ => self.other,
+        }
+    }
+
+    /// Estimate for a transaction made of `instructions` and the two instructions added by
+    /// [`with_compute_budget`], which replace any compute budget instruction of `instructions`
+    pub fn estimate_transaction(&self, instructions: &[Instruction]) -> u32 {
+        instructions
+            .iter()
+            .filter(|instruction| instruction.program_id != compute_budget::ID)
+            .map(|instruction| self.estimate(instruction))
+            .fold(2 * self.builtin, u32::saturating_add)
+            .min(MAX_COMPUTE_UNIT_LIMIT)
+    }
+}
+
+/// Cost model applied by [`simulate_compute_units`]: a fixed cost for the logic of every Plasma
+/// instruction, plus the cost of each CPI it makes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpiCosts {
+    pub instruction: u32,
+    pub token_transfer: u32,
+    pub create_account: u32,
+    /// Account creation followed by the token account initialization
+    pub create_token_account: u32,
+}
+
+/// Charges the CPIs of the processor to `units` while running against the wrapped runtime
+struct Metered<'a> {
+    runtime: &'a mut ReferenceRuntime,
+    costs: &'a CpiCosts,
+    units: u32,
+}
+
+impl ProcessorContext for Metered<'_> {
+    fn slot(&self) -> u64 {
+        self.runtime.slot()
+    }
+
+    fn account(&self, key: &Pubkey) -> Option<Account> {
+        self.runtime.account(key)
+    }
+
+    fn set_data(&mut self, key: &Pubkey, data: &[u8]) -> Result<(), ProcessorError> {
+        self.runtime.set_data(key, data)
+    }
+
+    fn create_account(
+        &mut self,
+        payer: &Pubkey,
+        key: &Pubkey,
+        space: u64,
+        owner: &Pubkey,
+        seeds: &[&[u8]],
+    ) -> Result<(), ProcessorError> {
+        self.units += self.costs.create_account;
+        self.runtime.create_account(payer, key, space, owner, seeds)
+    }
+
+    fn create_token_account(
+        &mut self,
+        payer: &Pubkey,
+        key: &Pubkey,
+        mint: &Pubkey,
+        seeds: &[&[u8]],
+    ) -> Result<(), ProcessorError> {
+        self.units += self.costs.create_token_account;
+        ProcessorContext::create_token_account(self.runtime, payer, key, mint, seeds)
+    }
+
+    fn transfer_tokens(
+        &mut self,
+        source: &Pubkey,
+        destination: &Pubkey,
+        authority: &Pubkey,
+        amount: u64,
+        authority_seeds: Option<&[&[u8]]>,
+    ) -> Result<(), ProcessorError> {
+        self.units += self.costs.token_transfer;
+        self.runtime
+            .transfer_tokens(source, destination, authority, amount, authority_seeds)
+    }
+}
+
+/// Runs `instructions` on a copy of `runtime` and returns the estimate of each instruction:
+/// Plasma instructions are charged according to `costs` for the CPIs they actually make, other
+/// instructions according to `table`. Fails if the transaction would fail.
+pub fn simulate_compute_units(
+    runtime: &ReferenceRuntime,
+    instructions: &[Instruction],
+    signers: &[Pubkey],
+    costs: &CpiCosts,
+    table: &ComputeUnitTable,
+) -> Result<Vec<u32>, ProcessorError> {
+    let mut runtime = runtime.clone();
+    let mut units = Vec::with_capacity(instructions.len());
+    for instruction in instructions {
+        if instruction.program_id != ID {
+            runtime.process_transaction(std::slice::from_ref(instruction), signers)?;
+            units.push(table.estimate(instruction));
+            continue;
+        }
+        if let Some(meta) = instruction
+            .accounts
+            .iter()
+            .find(|meta| meta.is_signer && !signers.contains(&meta.pubkey))
+        {
+            return Err(ProcessorError::MissingRequiredSignature(meta.pubkey));
+        }
+        let mut metered = Metered {
+            runtime: &mut runtime,
+            costs,
+            units: costs.instruction,
+        };
+        processor::process_instruction(
+            &mut metered,
+            &instruction.program_id,
+            &instruction.accounts,
+            &instruction.data,
+        )?;
+        units.push(metered.units);
+    }
+    Ok(units)
+}
+
+/// Chooses the compute unit price of a transaction, in micro-lamports per compute unit, from the
+/// compute unit limit it requests
+pub trait PriorityFeePolicy {
+    fn compute_unit_price(&self, compute_unit_limit: u32) -> u64;
+}
+
+impl<F: Fn(u32) -> u64> PriorityFeePolicy for F {
+    fn compute_unit_price(&self, compute_unit_limit: u32) -> u64 {
+        self(compute_unit_limit)
+    }
+}
+
+/// The same price whatever the limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedPriorityFee {
+    pub micro_lamports_per_compute_unit: u64,
+}
+
+impl PriorityFeePolicy for FixedPriorityFee {
+    fn compute_unit_price(&self, _compute_unit_limit: u32) -> u64 {
+        self.micro_lamports_per_compute_unit
+    }
+}
+
+/// Spends at most `lamports` of priority fee on the whole transaction. The runtime charges the
+/// price times the requested limit, not the consumed units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TotalPriorityFee {
+    pub lamports: u64,
+}
+
+impl PriorityFeePolicy for TotalPriorityFee {
+    fn compute_unit_price(&self, compute_unit_limit: u32) -> u64 {
+        let micro_lamports = self.lamports as u128 * MICRO_LAMPORTS_PER_LAMPORT as u128;
+        (micro_lamports / compute_unit_limit.max(1) as u128).min(u64::MAX as u128) as u64
+    }
+}
+
+/// Priority fee in lamports paid by a transaction requesting `compute_unit_limit` units at
+/// `compute_unit_price` micro-lamports each
+pub fn priority_fee(compute_unit_limit: u32, compute_unit_price: u64) -> u64 {
+    (compute_unit_limit as u128 * compute_unit_price as u128)
+        .div_ceil(MICRO_LAMPORTS_PER_LAMPORT as u128)
+        .min(u64::MAX as u128) as u64
+}
+
+/// Prepends the set-limit and set-price instructions to `instructions`, after removing the
+/// compute budget instructions they already hold, since a transaction may only set each once.
+/// The price instruction is left out when `policy` returns zero.
+pub fn with_compute_budget(
+    instructions: &[Instruction],
+    compute_unit_limit: u32,
+    policy: &impl PriorityFeePolicy,
+) -> Vec<Instruction> {
+    let compute_unit_limit = compute_unit_limit.min(MAX_COMPUTE_UNIT_LIMIT);
+    let mut with_budget = vec![ComputeBudgetInstruction::set_compute_unit_limit(
+        compute_unit_limit,
+    )];
+    let compute_unit_price = policy.compute_unit_price(compute_unit_limit);
+    if compute_unit_price > 0 {
+        with_budget.push(ComputeBudgetInstruction::set_compute_unit_price(
+            compute_unit_price,
+        ));
+    }
+    with_budget.extend(
+        instructions
+            .iter()
+            .filter(|instruction| instruction.program_id != compute_budget::ID)
+            .cloned(),
+    );
+    with_budget
+}
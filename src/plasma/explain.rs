@@ -0,0 +1,377 @@
+//! Human-readable explanations of Plasma instructions, for signing prompts and audit logs.
+//!
+//! The instruction is first checked with [`validate_instruction`], then simulated against the
+//! pool account with the processor's math, so the amounts shown are the ones the program would
+//! transfer at `slot` if the pool does not change before the transaction lands.
+
+use std::fmt::Display;
+
+use solana_program::{instruction::Instruction, pubkey::Pubkey};
+
+use crate::{
+    PoolAccount,
+    plasma::{
+        Side, SwapType, TokenParams,
+        processor::{ProcessorError, execute_swap},
+        slot_window,
+        validation::{Issue, ValidatedInstruction, validate_instruction},
+    },
+};
+
+/// Errors returned by [`explain_instruction`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExplainError {
+    /// The instruction does not match the canonical builder
+    Invalid(Vec<Issue>),
+    /// The program would reject the instruction
+    Simulation(ProcessorError),
+}
+
+impl From<ProcessorError> for ExplainError {
+    fn from(error: ProcessorError) -> Self {
+        ExplainError::Simulation(error)
+    }
+}
+
+impl Display for ExplainError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExplainError::Invalid(issues) => {
+                write!(f, "Invalid instruction: ")?;
+                for (index, issue) in issues.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "{}", issue)?;
+                }
+                Ok(())
+            }
+            ExplainError::Simulation(error) => write!(f, "Simulation failed: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for ExplainError {}
+
+/// Display names of the pool's tokens
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenSymbols {
+    pub base: String,
+    pub quote: String,
+}
+
+impl TokenSymbols {
+    pub fn new(base: &str, quote: &str) -> Self {
+        Self {
+            base: base.to_string(),
+            quote: quote.to_string(),
+        }
+    }
+
+    /// Abbreviated mint addresses, for pools whose tokens have no known symbol
+    pub fn from_mints(pool: &PoolAccount) -> Self {
+        let abbreviate = |mint: &Pubkey| {
+            let mint = mint.to_string();
+            format!("{}…{}", &mint[..4], &mint[mint.len() - 4..])
+        };
+        Self {
+            base: abbreviate(&pool.header.base_params.mint_key),
+            quote: abbreviate(&pool.header.quote_params.mint_key),
+        }
+    }
+}
+
+/// An amount of a pool token in its smallest unit, displayed in whole tokens using the decimals
+/// of the token, e.g. `2,000` or `12.5`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenAmount {
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub decimals: u32,
+}
+
+impl TokenAmount {
+    pub fn new(params: &TokenParams, amount: u64) -> Self {
+        Self {
+            mint: params.mint_key,
+            amount,
+            decimals: params.decimals,
+        }
+    }
+
+    pub fn ui_amount(&self) -> f64 {
+        // Decimals past `i32::MAX` would wrap to a negative exponent, while the amount rounds to
+        // zero from 309 decimals on
+        match i32::try_from(self.decimals) {
+            Ok(decimals) => self.amount as f64 / 10_f64.powi(decimals),
+            Err(_) => 0.0,
+        }
+    }
+}
+
+impl Display for TokenAmount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // From 39 decimals on the scale exceeds `u128` and any amount is a fraction
+        let (whole, fraction) = match 10_u128.checked_pow(self.decimals) {
+            Some(scale) => (self.amount as u128 / scale, self.amount as u128 % scale),
+            None => (0, self.amount as u128),
+        };
+        write!(f, "{}", group_digits(whole))?;
+        if fraction > 0 {
+            let digits = fraction.to_string();
+            let significant = digits.trim_end_matches('0');
+            // The leading zeros are written by the padding, without allocating them
+            let width = self.decimals as usize - (digits.len() - significant.len());
+            write!(f, ".{:0>width$}", significant, width = width)?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats `value` with a comma between groups of three digits
+fn group_digits(value: u128) -> String {
+    let digits = value.to_string();
+    let mut grouped = String::with_capacity(digits.len() * 4 / 3);
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// What an instruction does, with the amounts simulated against the pool
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Swap {
+        side: Side,
+        /// Exact input for `ExactIn` swaps, maximum input for `ExactOut` swaps
+        limit_in: TokenAmount,
+        /// Minimum output for `ExactIn` swaps, exact output for `ExactOut` swaps
+        limit_out: TokenAmount,
+        exact_in: bool,
+        expected_in: TokenAmount,
+        expected_out: TokenAmount,
+        /// LP and protocol fees, in quote
+        fee: TokenAmount,
+    },
+    AddLiquidity {
+        desired_base: TokenAmount,
+        desired_quote: TokenAmount,
+        expected_base: TokenAmount,
+        expected_quote: TokenAmount,
+        expected_lp_shares: u64,
+    },
+    RemoveLiquidity {
+        lp_shares: u64,
+        expected_base: TokenAmount,
+        expected_quote: TokenAmount,
+    },
+    InitializeLpPosition {
+        owner: Pubkey,
+    },
+    /// Moves every LP share of `source_owner` to `destination_lp_position`
+    TransferLiquidity {
+        source_owner: Pubkey,
+        destination_lp_position: Pubkey,
+    },
+    InitializePool {
+        base_mint: Pubkey,
+        quote_mint: Pubkey,
+        lp_fee_in_bps: u64,
+    },
+}
+
+/// Structured and textual explanation of an instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explanation {
+    pub pool: Pubkey,
+    /// The account that signs the instruction
+    pub signer: Pubkey,
+    pub action: Action,
+    pub text: String,
+}
+
+impl Display for Explanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+/// Explains `instruction`, an instruction on the pool described by `pool`, as if it executed at
+/// `slot`.
+///
+/// Pool initialization is explained without simulation, since `pool` is not initialized yet.
+pub fn explain_instruction(
+    instruction: &Instruction,
+    pool: &PoolAccount,
+    slot: u64,
+    symbols: &TokenSymbols,
+) -> Result<Explanation, ExplainError> {
+    let validated = validate_instruction(instruction, Some(pool)).map_err(ExplainError::Invalid)?;
+    let (base_params, quote_params) = (&pool.header.base_params, &pool.header.quote_params);
+    let base = |amount| TokenAmount::new(base_params, amount);
+    let quote = |amount| TokenAmount::new(quote_params, amount);
+    let window = slot_window(slot);
+    let pool_key = validated.pool();
+    let (base_symbol, quote_symbol) = (&symbols.base, &symbols.quote);
+
+    let (action, text) = match validated {
+        ValidatedInstruction::Swap { params, .. } => {
+            let mut amm = pool.amm;
+            let result = execute_swap(&mut amm, window, params)?;
+            let (expected_base, expected_quote) = (
+                base(result.base_amount_to_transfer),
+                quote(result.quote_amount_to_transfer),
+            );
+            let fee = quote(result.fee_in_quote);
+            let (exact_in, limit_in, limit_out) = match params.swap_type {
+                SwapType::ExactIn {
+                    amount_in,
+                    min_amount_out,
+                } => (true, amount_in, min_amount_out),
+                SwapType::ExactOut {
+                    amount_out,
+                    max_amount_in,
+                } => (false, max_amount_in, amount_out),
+            };
+            let (limit_base, limit_quote) = match params.side {
+                Side::Buy => (base(limit_out), quote(limit_in)),
+                Side::Sell => (base(limit_in), quote(limit_out)),
+            };
+            let (base_words, quote_words) = match (params.side, exact_in) {
+                (Side::Buy, true) => ("at least", "exactly"),
+                (Side::Buy, false) => ("exactly", "at most"),
+                (Side::Sell, true) => ("exactly", "at least"),
+                (Side::Sell, false) => ("at most", "exactly"),
+            };
+            let base_text = format!("{} {} {}", base_words, limit_base, base_symbol);
+            let quote_text = format!("{} {} {}", quote_words, limit_quote, quote_symbol);
+            // The simulated amount of the bounded side
+            let expected_text = if exact_in == (params.side == Side::Buy) {
+                format!("{} {}", expected_base, base_symbol)
+            } else {
+                format!("{} {}", expected_quote, quote_symbol)
+            };
+            let text = match params.side {
+                Side::Buy => format!(
+                    "Buy {} for {} from pool {} (≈ {} expected), fee ≈ {} {}",
+                    base_text, quote_text, pool_key, expected_text, fee, quote_symbol
+                ),
+                Side::Sell => format!(
+                    "Sell {} for {} to pool {} (≈ {} expected), fee ≈ {} {}",
+                    base_text, quote_text, pool_key, expected_text, fee, quote_symbol
+                ),
+            };
+            let (expected_in, expected_out, limit_in, limit_out) = match params.side {
+                Side::Buy => (expected_quote, expected_base, limit_quote, limit_base),
+                Side::Sell => (expected_base, expected_quote, limit_base, limit_quote),
+            };
+            (
+                Action::Swap {
+                    side: params.side,
+                    limit_in,
+                    limit_out,
+                    exact_in,
+                    expected_in,
+                    expected_out,
+                    fee,
+                },
+                text,
+            )
+        }
+        ValidatedInstruction::AddLiquidity { params, .. } => {
+            let preview = pool
+                .amm
+                .preview_mint(
+                    window,
+                    params.desired_base_amount_in,
+                    params.desired_quote_amount_in,
+                )
+                .map_err(ProcessorError::from)?;
+            let action = Action::AddLiquidity {
+                desired_base: base(params.desired_base_amount_in),
+                desired_quote: quote(params.desired_quote_amount_in),
+                expected_base: base(preview.base_amount_deposited),
+                expected_quote: quote(preview.quote_amount_deposited),
+                expected_lp_shares: preview.lp_shares,
+            };
+            let text = format!(
+                "Add up to {} {} + {} {} to pool {} ≈ {} LP shares for {} {} + {} {}",
+                base(params.desired_base_amount_in),
+                base_symbol,
+                quote(params.desired_quote_amount_in),
+                quote_symbol,
+                pool_key,
+                group_digits(preview.lp_shares as u128),
+                base(preview.base_amount_deposited),
+                base_symbol,
+                quote(preview.quote_amount_deposited),
+                quote_symbol
+            );
+            (action, text)
+        }
+        ValidatedInstruction::RemoveLiquidity { shares, .. } => {
+            let preview = pool
+                .amm
+                .preview_burn(window, shares)
+                .map_err(ProcessorError::from)?;
+            let action = Action::RemoveLiquidity {
+                lp_shares: shares,
+                expected_base: base(preview.base_amount_withdrawn),
+                expected_quote: quote(preview.quote_amount_withdrawn),
+            };
+            let text = format!(
+                "Remove {} LP shares ≈ {} {} + {} {} from pool {}",
+                group_digits(shares as u128),
+                base(preview.base_amount_withdrawn),
+                base_symbol,
+                quote(preview.quote_amount_withdrawn),
+                quote_symbol,
+                pool_key
+            );
+            (action, text)
+        }
+        ValidatedInstruction::InitializeLpPosition { owner, .. } => (
+            Action::InitializeLpPosition { owner },
+            format!("Open an LP position for {} in pool {}", owner, pool_key),
+        ),
+        ValidatedInstruction::TransferLiquidity {
+            source_owner,
+            destination_lp_position,
+            ..
+        } => (
+            Action::TransferLiquidity {
+                source_owner,
+                destination_lp_position,
+            },
+            format!(
+                "Transfer all LP shares of {} in pool {} to LP position {}",
+                source_owner, pool_key, destination_lp_position
+            ),
+        ),
+        ValidatedInstruction::InitializePool {
+            base_mint,
+            quote_mint,
+            params,
+            ..
+        } => (
+            Action::InitializePool {
+                base_mint,
+                quote_mint,
+                lp_fee_in_bps: params.lp_fee_in_bps,
+            },
+            format!(
+                "Create pool {} trading {} against {}, LP fee {} bps",
+                pool_key, base_mint, quote_mint, params.lp_fee_in_bps
+            ),
+        ),
+    };
+    Ok(Explanation {
+        pool: pool_key,
+        signer: validated.signer(),
+        action,
+        text,
+    })
+}
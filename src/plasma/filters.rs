@@ -0,0 +1,104 @@
+//! `getProgramAccounts` filters for discovering Plasma accounts.
+//!
+//! An RPC node applies every filter of a request, so "pools with either mint" takes one request
+//! per side, see [`pools_by_mint`].
+//!
+//! LP positions only hold share and fee accounting: the pool and the owner are PDA seeds and are
+//! not stored in the account, so they cannot be matched with `memcmp`. Find the positions of an
+//! owner, or of a pool, by deriving their addresses with [`lp_position_keys`] and fetching them.
+
+use solana_program::pubkey::Pubkey;
+
+use crate::plasma::{
+    ID, LP_POSITION_LEN, POOL_DISCRIMINATOR, POOL_LEN, get_lp_position_address,
+    layout::{BASE_MINT_OFFSET, DISCRIMINATOR_OFFSET, QUOTE_MINT_OFFSET},
+};
+
+/// Mirrors the RPC `dataSize` and `memcmp` filters
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccountFilter {
+    DataSize(u64),
+    Memcmp { offset: usize, bytes: Vec<u8> },
+}
+
+impl AccountFilter {
+    pub fn memcmp(offset: usize, bytes: &[u8]) -> Self {
+        AccountFilter::Memcmp {
+            offset,
+            bytes: bytes.to_vec(),
+        }
+    }
+
+    /// Applies the filter to account data the way an RPC node would
+    pub fn matches(&self, data: &[u8]) -> bool {
+        match self {
+            AccountFilter::DataSize(size) => data.len() as u64 == *size,
+            AccountFilter::Memcmp { offset, bytes } => data
+                .get(*offset..)
+                .is_some_and(|data| data.starts_with(bytes)),
+        }
+    }
+}
+
+/// Returns true if `data` passes every filter
+pub fn matches_all(filters: &[AccountFilter], data: &[u8]) -> bool {
+    filters.iter().all(|filter| filter.matches(data))
+}
+
+/// Every pool account
+pub fn pool_filters() -> Vec<AccountFilter> {
+    vec![
+        AccountFilter::DataSize(POOL_LEN),
+        AccountFilter::memcmp(DISCRIMINATOR_OFFSET, &POOL_DISCRIMINATOR),
+    ]
+}
+
+pub fn pools_by_base_mint(base_mint: &Pubkey) -> Vec<AccountFilter> {
+    let mut filters = pool_filters();
+    filters.push(AccountFilter::memcmp(BASE_MINT_OFFSET, base_mint.as_ref()));
+    filters
+}
+
+pub fn pools_by_quote_mint(quote_mint: &Pubkey) -> Vec<AccountFilter> {
+    let mut filters = pool_filters();
+    filters.push(AccountFilter::memcmp(
+        QUOTE_MINT_OFFSET,
+        quote_mint.as_ref(),
+    ));
+    filters
+}
+
+/// Filters for the pools that have `mint` as their base mint and as their quote mint, to be sent
+/// as two requests
+pub fn pools_by_mint(mint: &Pubkey) -> [Vec<AccountFilter>; 2] {
+    [pools_by_base_mint(mint), pools_by_quote_mint(mint)]
+}
+
+/// Pools trading `base_mint` against `quote_mint`
+pub fn pools_by_mints(base_mint: &Pubkey, quote_mint: &Pubkey) -> Vec<AccountFilter> {
+    let mut filters = pools_by_base_mint(base_mint);
+    filters.push(AccountFilter::memcmp(
+        QUOTE_MINT_OFFSET,
+        quote_mint.as_ref(),
+    ));
+    filters
+}
+
+/// Every LP position of the program. Pools and LP positions are the only program accounts, and
+/// their sizes differ.
+pub fn lp_position_filters() -> Vec<AccountFilter> {
+    vec![AccountFilter::DataSize(LP_POSITION_LEN)]
+}
+
+/// LP position addresses of every `(pool, owner)` pair, e.g. an owner's positions across the pools
+/// found with the pool filters, or the positions of known owners in one pool
+pub fn lp_position_keys(pools: &[Pubkey], owners: &[Pubkey]) -> Vec<Pubkey> {
+    pools
+        .iter()
+        .flat_map(|pool| {
+            owners
+                .iter()
+                .map(move |owner| get_lp_position_address(&ID, pool, owner).0)
+        })
+        .collect()
+}
@@ -1,4 +1,4 @@
-use std::{
+use core::{
     fmt::{Debug, Display, Formatter},
     ops::{Add, AddAssign, Mul, Sub},
 };
@@ -36,6 +36,28 @@ impl I80F48 {
         value.floor().to_num()
     }
 
+    /// `floor`, or `None` if the value is negative or does not fit in a u64
+    pub fn checked_floor(&self) -> Option<u64> {
+        let value = FixedI80F48::from_bits(self.inner);
+        value.floor().checked_to_num()
+    }
+
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        let lhs = FixedI80F48::from_bits(self.inner);
+        let rhs = FixedI80F48::from_bits(rhs.inner);
+        lhs.checked_sub(rhs).map(|diff| Self {
+            inner: diff.to_bits(),
+        })
+    }
+
+    pub fn checked_mul(self, rhs: Self) -> Option<Self> {
+        let lhs = FixedI80F48::from_bits(self.inner);
+        let rhs = FixedI80F48::from_bits(rhs.inner);
+        lhs.checked_mul(rhs).map(|product| Self {
+            inner: product.to_bits(),
+        })
+    }
+
     pub fn to_bits(&self) -> i128 {
         self.inner
     }
@@ -54,7 +76,7 @@ impl PartialEq for I80F48 {
 }
 
 impl PartialOrd for I80F48 {
-    fn partial_cmp(&self, rhs: &Self) -> Option<std::cmp::Ordering> {
+    fn partial_cmp(&self, rhs: &Self) -> Option<core::cmp::Ordering> {
         let lhs = FixedI80F48::from_bits(self.inner);
         let rhs = FixedI80F48::from_bits(rhs.inner);
         lhs.partial_cmp(&rhs)
@@ -104,14 +126,14 @@ impl Mul for I80F48 {
 }
 
 impl Display for I80F48 {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         let value = FixedI80F48::from_bits(self.inner);
         write!(f, "{}", value)
     }
 }
 
 impl Debug for I80F48 {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         let value = FixedI80F48::from_bits(self.inner);
         write!(f, "{:?}", value)
     }
@@ -0,0 +1,87 @@
+//! Byte offsets of the fields of the pool and LP position accounts, for `memcmp` filters and
+//! reads of single fields. `PoolAccount` is `Pod`, so these are both its in-memory and on-chain
+//! offsets.
+
+use core::mem::{offset_of, size_of};
+
+use crate::{
+    PoolAccount,
+    plasma::{
+        LP_POSITION_LEN, LpPosition, POOL_LEN, ProtocolFeeRecipient, ProtocolFeeRecipients,
+        plasma_amm,
+    },
+};
+
+pub const DISCRIMINATOR_OFFSET: usize = offset_of!(PoolAccount, header.discriminator);
+pub const SEQUENCE_NUMBER_OFFSET: usize = offset_of!(PoolAccount, header.sequence_number);
+
+pub const BASE_DECIMALS_OFFSET: usize = offset_of!(PoolAccount, header.base_params.decimals);
+pub const BASE_VAULT_BUMP_OFFSET: usize = offset_of!(PoolAccount, header.base_params.vault_bump);
+pub const BASE_MINT_OFFSET: usize = offset_of!(PoolAccount, header.base_params.mint_key);
+pub const BASE_VAULT_OFFSET: usize = offset_of!(PoolAccount, header.base_params.vault_key);
+
+pub const QUOTE_DECIMALS_OFFSET: usize = offset_of!(PoolAccount, header.quote_params.decimals);
+pub const QUOTE_VAULT_BUMP_OFFSET: usize = offset_of!(PoolAccount, header.quote_params.vault_bump);
+pub const QUOTE_MINT_OFFSET: usize = offset_of!(PoolAccount, header.quote_params.mint_key);
+pub const QUOTE_VAULT_OFFSET: usize = offset_of!(PoolAccount, header.quote_params.vault_key);
+
+pub const NUM_FEE_RECIPIENTS: usize = 3;
+pub const FEE_RECIPIENTS_OFFSET: usize = offset_of!(PoolAccount, header.fee_recipients.recipients);
+/// Offsets of the fields of a `ProtocolFeeRecipient`, relative to [`fee_recipient_offset`]
+pub const FEE_RECIPIENT_RECIPIENT_OFFSET: usize = offset_of!(ProtocolFeeRecipient, recipient);
+pub const FEE_RECIPIENT_SHARES_OFFSET: usize = offset_of!(ProtocolFeeRecipient, shares);
+pub const FEE_RECIPIENT_TOTAL_ACCUMULATED_QUOTE_FEES_OFFSET: usize =
+    offset_of!(ProtocolFeeRecipient, total_accumulated_quote_fees);
+pub const FEE_RECIPIENT_COLLECTED_QUOTE_FEES_OFFSET: usize =
+    offset_of!(ProtocolFeeRecipient, collected_quote_fees);
+
+pub const SWAP_SEQUENCE_NUMBER_OFFSET: usize = offset_of!(PoolAccount, header.swap_sequence_number);
+pub const HEADER_PADDING_OFFSET: usize = offset_of!(PoolAccount, header.padding);
+
+pub const AMM_OFFSET: usize = offset_of!(PoolAccount, amm);
+pub const FEE_IN_BPS_OFFSET: usize = offset_of!(PoolAccount, amm.fee_in_bps);
+pub const PROTOCOL_ALLOCATION_IN_PCT_OFFSET: usize =
+    AMM_OFFSET + plasma_amm::PROTOCOL_ALLOCATION_IN_PCT_OFFSET;
+pub const LP_VESTING_WINDOW_OFFSET: usize = offset_of!(PoolAccount, amm.lp_vesting_window);
+pub const REWARD_FACTOR_OFFSET: usize = offset_of!(PoolAccount, amm.reward_factor);
+pub const TOTAL_LP_SHARES_OFFSET: usize = offset_of!(PoolAccount, amm.total_lp_shares);
+pub const SLOT_SNAPSHOT_OFFSET: usize = AMM_OFFSET + plasma_amm::SLOT_SNAPSHOT_OFFSET;
+pub const BASE_RESERVES_SNAPSHOT_OFFSET: usize =
+    offset_of!(PoolAccount, amm.base_reserves_snapshot);
+pub const QUOTE_RESERVES_SNAPSHOT_OFFSET: usize =
+    offset_of!(PoolAccount, amm.quote_reserves_snapshot);
+pub const BASE_RESERVES_OFFSET: usize = offset_of!(PoolAccount, amm.base_reserves);
+pub const QUOTE_RESERVES_OFFSET: usize = offset_of!(PoolAccount, amm.quote_reserves);
+pub const CUMULATIVE_QUOTE_LP_FEES_OFFSET: usize =
+    offset_of!(PoolAccount, amm.cumulative_quote_lp_fees);
+pub const CUMULATIVE_QUOTE_PROTOCOL_FEES_OFFSET: usize =
+    offset_of!(PoolAccount, amm.cumulative_quote_protocol_fees);
+
+/// `LpPosition` is Borsh encoded in field order. `pending_shares_to_vest` is encoded as the slot
+/// window followed by the number of shares.
+pub const LP_REWARD_FACTOR_SNAPSHOT_OFFSET: usize = offset_of!(LpPosition, reward_factor_snapshot);
+pub const LP_SHARES_OFFSET: usize = offset_of!(LpPosition, lp_shares);
+pub const LP_WITHDRAWABLE_LP_SHARES_OFFSET: usize = offset_of!(LpPosition, withdrawable_lp_shares);
+pub const LP_UNCOLLECTED_FEES_OFFSET: usize = offset_of!(LpPosition, uncollected_fees);
+pub const LP_COLLECTED_FEES_OFFSET: usize = offset_of!(LpPosition, collected_fees);
+pub const LP_PENDING_SHARES_TO_VEST_OFFSET: usize = offset_of!(LpPosition, pending_shares_to_vest);
+
+/// Offset of the `index`th protocol fee recipient
+pub const fn fee_recipient_offset(index: usize) -> usize {
+    assert!(index < NUM_FEE_RECIPIENTS);
+    FEE_RECIPIENTS_OFFSET + index * size_of::<ProtocolFeeRecipient>()
+}
+
+const _: () = assert!(size_of::<PoolAccount>() == POOL_LEN as usize);
+const _: () = assert!(size_of::<LpPosition>() == LP_POSITION_LEN as usize);
+// Indexers hard-code the mint offsets, so they must never move
+const _: () = assert!(BASE_MINT_OFFSET == 24);
+const _: () = assert!(QUOTE_MINT_OFFSET == 96);
+// Borsh has no padding, so the fields must be laid out back to back in declaration order
+const _: () = assert!(AMM_OFFSET + size_of::<plasma_amm::Amm>() == POOL_LEN as usize);
+const _: () = assert!(
+    size_of::<[ProtocolFeeRecipient; NUM_FEE_RECIPIENTS]>() + 12 * 8
+        == size_of::<ProtocolFeeRecipients>()
+);
+const _: () = assert!(HEADER_PADDING_OFFSET + 12 * 8 == AMM_OFFSET);
+const _: () = assert!(LP_PENDING_SHARES_TO_VEST_OFFSET + 16 == LP_POSITION_LEN as usize);
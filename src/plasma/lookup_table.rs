@@ -0,0 +1,132 @@
+//! Address lookup tables for Plasma swaps.
+//!
+//! A swap references nine accounts, six of which do not depend on the trader, so routes through
+//! more than one pool quickly overflow a legacy transaction. Put the addresses returned by
+//! [`lookup_table_addresses`] in a lookup table and compile the route with
+//! [`compile_v0_message`].
+//!
+//! The Plasma program id is never included: the runtime requires invoked programs to be static
+//! keys of a v0 message.
+
+use solana_address_lookup_table_interface::{instruction, state::AddressLookupTable};
+use solana_program::{hash::Hash, instruction::Instruction, pubkey::Pubkey};
+use solana_sdk::message::{AddressLookupTableAccount, CompileError, VersionedMessage, v0};
+
+use crate::plasma::{ID, PoolHeader, get_log_authority, spl_token};
+
+/// Addresses extended per instruction by [`lookup_table_instructions`], small enough for the
+/// extend instruction to fit in a transaction with a separate payer
+pub const MAX_ADDRESSES_PER_EXTEND: usize = 20;
+
+/// Accounts referenced by every swap, whatever the pool
+pub fn shared_lookup_table_addresses() -> Vec<Pubkey> {
+    vec![get_log_authority(&ID), spl_token::ID]
+}
+
+/// Accounts a swap references for the pool `pool_key`: the pool and its vaults
+pub fn pool_lookup_table_addresses(pool_key: &Pubkey, header: &PoolHeader) -> Vec<Pubkey> {
+    vec![
+        *pool_key,
+        header.base_params.vault_key,
+        header.quote_params.vault_key,
+    ]
+}
+
+/// Contents of a lookup table for swaps through `pools`: the shared addresses followed by the
+/// addresses of each pool
+pub fn lookup_table_addresses(pools: &[(Pubkey, &PoolHeader)]) -> Vec<Pubkey> {
+    let mut addresses = shared_lookup_table_addresses();
+    for (pool_key, header) in pools {
+        addresses.extend(pool_lookup_table_addresses(pool_key, header));
+    }
+    addresses
+}
+
+/// Instructions appending `addresses` to an existing lookup table, one per
+/// [`MAX_ADDRESSES_PER_EXTEND`] addresses. Send each one in its own transaction.
+pub fn extend_lookup_table_instructions(
+    lookup_table: &Pubkey,
+    authority: &Pubkey,
+    payer: &Pubkey,
+    addresses: &[Pubkey],
+) -> Vec<Instruction> {
+    addresses
+        .chunks(MAX_ADDRESSES_PER_EXTEND)
+        .map(|chunk| {
+            instruction::extend_lookup_table(
+                *lookup_table,
+                *authority,
+                Some(*payer),
+                chunk.to_vec(),
+            )
+        })
+        .collect()
+}
+
+/// Creates a lookup table holding `addresses`. Returns the table address and the create
+/// instruction followed by the extend instructions.
+///
+/// `recent_slot` must be a recent finalized slot, it seeds the table address. The create
+/// instruction can share a transaction with the first extend instruction. Tables can only be used
+/// from the slot after they were last extended.
+pub fn lookup_table_instructions(
+    authority: &Pubkey,
+    payer: &Pubkey,
+    recent_slot: u64,
+    addresses: &[Pubkey],
+) -> (Pubkey, Vec<Instruction>) {
+    let (create, lookup_table) = instruction::create_lookup_table(*authority, *payer, recent_slot);
+    let mut instructions = vec![create];
+    instructions.extend(extend_lookup_table_instructions(
+        &lookup_table,
+        authority,
+        payer,
+        addresses,
+    ));
+    (lookup_table, instructions)
+}
+
+/// Decodes a fetched lookup table account, or returns `None` if `data` is not a lookup table
+pub fn decode_lookup_table(key: &Pubkey, data: &[u8]) -> Option<AddressLookupTableAccount> {
+    let table = AddressLookupTable::deserialize(data).ok()?;
+    Some(AddressLookupTableAccount {
+        key: *key,
+        addresses: table.addresses.to_vec(),
+    })
+}
+
+/// Accounts of `instructions` that are not in any of `lookup_tables` and could be added to one.
+/// Signers and invoked programs are left out, they are always static keys.
+pub fn missing_lookup_table_addresses(
+    instructions: &[Instruction],
+    lookup_tables: &[AddressLookupTableAccount],
+) -> Vec<Pubkey> {
+    let mut missing = vec![];
+    for ix in instructions {
+        for meta in ix.accounts.iter() {
+            if meta.is_signer
+                || instructions.iter().any(|ix| ix.program_id == meta.pubkey)
+                || missing.contains(&meta.pubkey)
+                || lookup_tables
+                    .iter()
+                    .any(|table| table.addresses.contains(&meta.pubkey))
+            {
+                continue;
+            }
+            missing.push(meta.pubkey);
+        }
+    }
+    missing
+}
+
+/// Compiles `instructions`, e.g. one swap or the swaps of a multi-pool route, into a v0 message
+/// that loads the non-signer accounts found in `lookup_tables` by index
+pub fn compile_v0_message(
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    lookup_tables: &[AddressLookupTableAccount],
+    recent_blockhash: Hash,
+) -> Result<VersionedMessage, CompileError> {
+    v0::Message::try_compile(payer, instructions, lookup_tables, recent_blockhash)
+        .map(VersionedMessage::V0)
+}
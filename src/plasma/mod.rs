@@ -1,12 +1,66 @@
+#[cfg(feature = "account-json")]
+pub mod account_json;
+#[cfg(feature = "client")]
+pub mod aggregator;
+#[cfg(feature = "client")]
+pub mod analytics;
+#[cfg(feature = "client")]
+pub mod backtest;
+#[cfg(feature = "client")]
+pub mod client;
+#[cfg(feature = "client")]
+pub mod compute_budget;
+#[cfg(feature = "client")]
+pub mod explain;
+#[cfg(feature = "client")]
+pub mod filters;
 pub mod fixed;
+#[cfg(feature = "state")]
+pub mod layout;
+#[cfg(feature = "client")]
+pub mod lookup_table;
+#[cfg(feature = "client")]
+pub mod oracle;
 pub mod plasma_amm;
 pub mod plasma_error;
+#[cfg(feature = "client")]
 pub mod plasma_utils;
+#[cfg(feature = "client")]
+pub mod processor;
+#[cfg(feature = "program-test")]
+pub mod program_test;
+#[cfg(feature = "client")]
+pub mod reference;
+#[cfg(feature = "client")]
+pub mod replay;
+#[cfg(feature = "state")]
+pub mod state;
+#[cfg(feature = "client")]
+pub mod token;
+#[cfg(feature = "client")]
+pub mod validation;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 pub type SlotWindow = u64;
 
+pub const POOL_LEN: u64 = 624;
+pub const LP_POSITION_LEN: u64 = 64;
+pub const POOL_DISCRIMINATOR: [u8; 8] = [116, 210, 187, 119, 196, 196, 52, 137];
+
+/// Number of slots in a leader slot window. Pool snapshots are refreshed at most once per window.
+pub const SLOTS_PER_WINDOW: u64 = 4;
+
+/// Converts a cluster slot into the `SlotWindow` passed to the `Amm`
+pub fn slot_window(slot: u64) -> SlotWindow {
+    slot / SLOTS_PER_WINDOW
+}
+
 pub use fixed::I80F48;
 pub use plasma_error::*;
+#[cfg(feature = "client")]
 pub use plasma_utils::*;
+#[cfg(feature = "state")]
+pub use state::*;
 
 /// Private trait for safely downcasting between types
 pub(crate) trait Downcast<To> {
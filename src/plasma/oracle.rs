@@ -0,0 +1,296 @@
+use std::{
+    collections::BTreeMap,
+    ops::Bound::{Excluded, Unbounded},
+};
+
+use crate::PoolAccount;
+
+/// Configuration for [`PriceOracle`]
+#[derive(Debug, Clone, Copy)]
+pub struct OracleConfig {
+    /// Observations older than this many slots behind the newest one are pruned
+    pub max_age_in_slots: u64,
+    /// Reject observations whose spot price deviates from the median of the preceding
+    /// observations by more than this many basis points
+    pub max_deviation_in_bps: Option<u64>,
+    /// Number of preceding observations used for the outlier median
+    pub outlier_lookback: usize,
+}
+
+impl Default for OracleConfig {
+    fn default() -> Self {
+        Self {
+            max_age_in_slots: 9_000,
+            max_deviation_in_bps: None,
+            outlier_lookback: 16,
+        }
+    }
+}
+
+/// Prices are quoted in quote atoms per base atom
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OracleObservation {
+    pub slot: u64,
+    pub unix_timestamp: i64,
+    pub sequence_number: u64,
+    pub swap_sequence_number: u64,
+    /// Price implied by `base_reserves` and `quote_reserves`
+    pub spot_price: f64,
+    /// Price implied by `base_reserves_snapshot` and `quote_reserves_snapshot`
+    pub snapshot_price: f64,
+}
+
+impl OracleObservation {
+    pub fn from_pool(slot: u64, unix_timestamp: i64, pool: &PoolAccount) -> Option<Self> {
+        let amm = &pool.amm;
+        if amm.base_reserves == 0 || amm.base_reserves_snapshot == 0 {
+            return None;
+        }
+        Some(Self {
+            slot,
+            unix_timestamp,
+            sequence_number: pool.header.sequence_number,
+            swap_sequence_number: pool.header.swap_sequence_number,
+            spot_price: amm.quote_reserves as f64 / amm.base_reserves as f64,
+            snapshot_price: amm.quote_reserves_snapshot as f64 / amm.base_reserves_snapshot as f64,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IngestOutcome {
+    Accepted,
+    /// An observation with the same sequence number was already ingested
+    Duplicate,
+    /// The slot or swap sequence number contradicts the order implied by the sequence number
+    Inconsistent,
+    /// The spot price was rejected by the outlier filter
+    Outlier,
+    /// The pool has no reserves to price
+    EmptyPool,
+    /// The observation is older than `max_age_in_slots`
+    Expired,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OracleError {
+    /// No observation was ingested at or before the end of the averaging window
+    NoObservations,
+    /// The averaging window is negative
+    NegativeWindow(i64),
+}
+
+impl std::fmt::Display for OracleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OracleError::NoObservations => {
+                write!(f, "No observation precedes the end of the window")
+            }
+            OracleError::NegativeWindow(window) => {
+                write!(f, "Averaging window of {} is negative", window)
+            }
+        }
+    }
+}
+
+impl std::error::Error for OracleError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AveragePrices {
+    pub spot_price: f64,
+    pub snapshot_price: f64,
+}
+
+impl AveragePrices {
+    /// Converts atom prices into UI prices (quote tokens per base token)
+    pub fn to_ui(&self, base_decimals: u32, quote_decimals: u32) -> Self {
+        let scale = 10_f64.powi(base_decimals as i32 - quote_decimals as i32);
+        Self {
+            spot_price: self.spot_price * scale,
+            snapshot_price: self.snapshot_price * scale,
+        }
+    }
+}
+
+/// Accumulates `PoolAccount` snapshots of a single pool and produces time- and slot-weighted
+/// average prices.
+///
+/// Observations are ordered by the pool's `sequence_number`, which increases on every state
+/// change, so snapshots may be ingested out of order and refetches of unchanged state are
+/// deduplicated.
+#[derive(Debug, Clone, Default)]
+pub struct PriceOracle {
+    config: OracleConfig,
+    observations: BTreeMap<u64, OracleObservation>,
+}
+
+impl PriceOracle {
+    pub fn new(config: OracleConfig) -> Self {
+        Self {
+            config,
+            observations: BTreeMap::new(),
+        }
+    }
+
+    pub fn observations(&self) -> impl Iterator<Item = &OracleObservation> {
+        self.observations.values()
+    }
+
+    pub fn latest(&self) -> Option<&OracleObservation> {
+        self.observations.values().next_back()
+    }
+
+    pub fn ingest(&mut self, slot: u64, unix_timestamp: i64, pool: &PoolAccount) -> IngestOutcome {
+        match OracleObservation::from_pool(slot, unix_timestamp, pool) {
+            Some(observation) => self.ingest_observation(observation),
+            None => IngestOutcome::EmptyPool,
+        }
+    }
+
+    pub fn ingest_observation(&mut self, observation: OracleObservation) -> IngestOutcome {
+        let sequence_number = observation.sequence_number;
+        if self.observations.contains_key(&sequence_number) {
+            return IngestOutcome::Duplicate;
+        }
+        let max_age_in_slots = self.config.max_age_in_slots;
+        if self
+            .latest()
+            .is_some_and(|latest| observation.slot.saturating_add(max_age_in_slots) < latest.slot)
+        {
+            return IngestOutcome::Expired;
+        }
+
+        let previous = self
+            .observations
+            .range(..sequence_number)
+            .next_back()
+            .map(|(_, observation)| observation);
+        let next = self
+            .observations
+            .range((Excluded(sequence_number), Unbounded))
+            .next()
+            .map(|(_, observation)| observation);
+        let consistent_with = |earlier: &OracleObservation, later: &OracleObservation| {
+            earlier.slot <= later.slot
+                && earlier.unix_timestamp <= later.unix_timestamp
+                && earlier.swap_sequence_number <= later.swap_sequence_number
+        };
+        if previous.is_some_and(|previous| !consistent_with(previous, &observation))
+            || next.is_some_and(|next| !consistent_with(&observation, next))
+        {
+            return IngestOutcome::Inconsistent;
+        }
+
+        if let Some(max_deviation_in_bps) = self.config.max_deviation_in_bps {
+            let mut recent_prices = self
+                .observations
+                .range(..sequence_number)
+                .rev()
+                .take(self.config.outlier_lookback)
+                .map(|(_, observation)| observation.spot_price)
+                .collect::<Vec<_>>();
+            if !recent_prices.is_empty() {
+                recent_prices.sort_by(f64::total_cmp);
+                let median = recent_prices[recent_prices.len() / 2];
+                // Compared without dividing by the median, so that any price deviates from a
+                // median of zero
+                let deviation = (observation.spot_price - median).abs() * 10_000.0;
+                if deviation > median * max_deviation_in_bps as f64 {
+                    return IngestOutcome::Outlier;
+                }
+            }
+        }
+
+        self.observations.insert(sequence_number, observation);
+        self.prune();
+        IngestOutcome::Accepted
+    }
+
+    fn prune(&mut self) {
+        let Some(latest_slot) = self.latest().map(|latest| latest.slot) else {
+            return;
+        };
+        let min_slot = latest_slot.saturating_sub(self.config.max_age_in_slots);
+        self.observations
+            .retain(|_, observation| observation.slot >= min_slot);
+    }
+
+    /// Average prices over the `window_in_slots` slots ending at `current_slot`, weighting each
+    /// observation by the number of slots it was the latest known state. The latest observation
+    /// is weighted up to `current_slot`, so a refetch of unchanged state need not be ingested.
+    pub fn slot_weighted_average(
+        &self,
+        current_slot: u64,
+        window_in_slots: u64,
+    ) -> Result<AveragePrices, OracleError> {
+        self.weighted_average(
+            current_slot as i128,
+            window_in_slots as i128,
+            |observation| observation.slot as i128,
+        )
+    }
+
+    /// Average prices over the `window_in_seconds` seconds ending at `unix_timestamp`,
+    /// weighting each observation by how long it was the latest known state. The latest
+    /// observation is weighted up to `unix_timestamp`.
+    pub fn time_weighted_average(
+        &self,
+        unix_timestamp: i64,
+        window_in_seconds: i64,
+    ) -> Result<AveragePrices, OracleError> {
+        if window_in_seconds < 0 {
+            return Err(OracleError::NegativeWindow(window_in_seconds));
+        }
+        self.weighted_average(
+            unix_timestamp as i128,
+            window_in_seconds as i128,
+            |observation| observation.unix_timestamp as i128,
+        )
+    }
+
+    fn weighted_average(
+        &self,
+        end: i128,
+        window: i128,
+        clock: impl Fn(&OracleObservation) -> i128,
+    ) -> Result<AveragePrices, OracleError> {
+        let start = end - window;
+        let observations = self
+            .observations
+            .values()
+            .take_while(|observation| clock(observation) <= end)
+            .collect::<Vec<_>>();
+        let Some(latest) = observations.last() else {
+            return Err(OracleError::NoObservations);
+        };
+
+        let mut weighted_spot = 0.0;
+        let mut weighted_snapshot = 0.0;
+        let mut total_weight = 0.0;
+        for (index, current) in observations.iter().enumerate() {
+            // The current observation holds until the next one is observed, and the latest
+            // one until the end of the window
+            let from = clock(current).max(start);
+            let to = observations.get(index + 1).map_or(end, |next| clock(next));
+            if to <= from {
+                continue;
+            }
+            let weight = (to - from) as f64;
+            weighted_spot += current.spot_price * weight;
+            weighted_snapshot += current.snapshot_price * weight;
+            total_weight += weight;
+        }
+
+        if total_weight == 0.0 {
+            // The window is empty, so the latest price known at its end is used
+            return Ok(AveragePrices {
+                spot_price: latest.spot_price,
+                snapshot_price: latest.snapshot_price,
+            });
+        }
+        Ok(AveragePrices {
+            spot_price: weighted_spot / total_weight,
+            snapshot_price: weighted_snapshot / total_weight,
+        })
+    }
+}
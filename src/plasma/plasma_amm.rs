@@ -7,7 +7,8 @@ pub const BPS_BASE: u128 = 10000;
 
 use super::SlotWindow;
 
-#[derive(Debug, Clone, Copy, BorshDeserialize, BorshSerialize)]
+#[cfg_attr(feature = "wasm", wasm_bindgen::prelude::wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshDeserialize, BorshSerialize)]
 pub struct SwapResult {
     pub side: Side,
     pub base_amount_to_transfer: u64,
@@ -34,6 +35,26 @@ impl SwapResult {
     }
 }
 
+#[cfg_attr(feature = "wasm", wasm_bindgen::prelude::wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshDeserialize, BorshSerialize)]
+pub struct MintPreview {
+    pub base_amount_deposited: u64,
+    pub quote_amount_deposited: u64,
+    /// Portion of the desired base amount that would not be pulled into the pool
+    pub base_amount_refunded: u64,
+    /// Portion of the desired quote amount that would not be pulled into the pool
+    pub quote_amount_refunded: u64,
+    pub lp_shares: u64,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen::prelude::wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshDeserialize, BorshSerialize)]
+pub struct BurnPreview {
+    pub base_amount_withdrawn: u64,
+    pub quote_amount_withdrawn: u64,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen::prelude::wasm_bindgen)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, BorshDeserialize, BorshSerialize)]
 pub enum Side {
     Buy,
@@ -63,6 +84,13 @@ pub struct Amm {
     pub cumulative_quote_protocol_fees: u64,
 }
 
+/// Offsets of the private `Amm` fields, re-exported with the public ones by [`super::layout`]
+#[cfg(feature = "state")]
+pub(crate) const PROTOCOL_ALLOCATION_IN_PCT_OFFSET: usize =
+    core::mem::offset_of!(Amm, protocol_allocation_in_pct);
+#[cfg(feature = "state")]
+pub(crate) const SLOT_SNAPSHOT_OFFSET: usize = core::mem::offset_of!(Amm, slot_snapshot);
+
 impl Amm {
     pub fn new(
         fee_in_bps: u32,
@@ -101,7 +129,7 @@ impl Amm {
         slot: SlotWindow,
         quote_in: u64,
     ) -> Result<SwapResult, PlasmaStateError> {
-        let mut pool_clone = self.clone();
+        let mut pool_clone = *self;
         pool_clone.buy_exact_in(slot, quote_in)
     }
 
@@ -110,9 +138,118 @@ impl Amm {
         slot: SlotWindow,
         base_in: u64,
     ) -> Result<SwapResult, PlasmaStateError> {
-        let mut pool_clone = self.clone();
+        let mut pool_clone = *self;
         pool_clone.sell_exact_in(slot, base_in)
     }
+
+    /// Simulates `mint` without modifying the pool.
+    ///
+    /// If the pool is empty, the initial LP shares are derived with [`Amm::initial_lp_shares`].
+    pub fn preview_mint(
+        &self,
+        slot: SlotWindow,
+        base_amount_desired: u64,
+        quote_amount_desired: u64,
+    ) -> Result<MintPreview, PlasmaStateError> {
+        let initial_lp_shares = if self.total_lp_shares == 0 {
+            Some(Self::initial_lp_shares(
+                base_amount_desired,
+                quote_amount_desired,
+            ))
+        } else {
+            None
+        };
+        let mut pool_clone = *self;
+        let (base_amount_deposited, quote_amount_deposited, lp_shares) = pool_clone.mint(
+            slot,
+            base_amount_desired,
+            quote_amount_desired,
+            initial_lp_shares,
+        )?;
+        Ok(MintPreview {
+            base_amount_deposited,
+            quote_amount_deposited,
+            base_amount_refunded: base_amount_desired
+                .checked_sub(base_amount_deposited)
+                .ok_or(PlasmaStateError::Underflow)?,
+            quote_amount_refunded: quote_amount_desired
+                .checked_sub(quote_amount_deposited)
+                .ok_or(PlasmaStateError::Underflow)?,
+            lp_shares,
+        })
+    }
+
+    /// Simulates `burn` without modifying the pool.
+    pub fn preview_burn(
+        &self,
+        slot: SlotWindow,
+        lp_shares: u64,
+    ) -> Result<BurnPreview, PlasmaStateError> {
+        if self.total_lp_shares == 0 {
+            return Err(PlasmaStateError::UninitializedPool);
+        }
+        if lp_shares > self.total_lp_shares {
+            return Err(PlasmaStateError::TooManyShares);
+        }
+        let mut pool_clone = *self;
+        let (base_amount_withdrawn, quote_amount_withdrawn) = pool_clone.burn(slot, lp_shares)?;
+        Ok(BurnPreview {
+            base_amount_withdrawn,
+            quote_amount_withdrawn,
+        })
+    }
+}
+
+impl Amm {
+    /// Returns the smallest number of LP shares whose `burn` withdraws at least `base_amount`.
+    pub fn lp_shares_to_withdraw_base(&self, base_amount: u64) -> Result<u64, PlasmaStateError> {
+        self.lp_shares_to_withdraw(base_amount, self.base_reserves)
+    }
+
+    /// Returns the smallest number of LP shares whose `burn` withdraws at least `quote_amount`.
+    pub fn lp_shares_to_withdraw_quote(&self, quote_amount: u64) -> Result<u64, PlasmaStateError> {
+        self.lp_shares_to_withdraw(quote_amount, self.quote_reserves)
+    }
+
+    fn lp_shares_to_withdraw(&self, amount: u64, reserves: u64) -> Result<u64, PlasmaStateError> {
+        if self.total_lp_shares == 0 {
+            return Err(PlasmaStateError::UninitializedPool);
+        }
+        if amount > reserves {
+            return Err(PlasmaStateError::WithdrawalExceedsReserves { amount, reserves });
+        }
+        // `burn` withdraws floor(reserves * shares / total_shares)
+        let lp_shares =
+            (amount.upcast() * self.total_lp_shares.upcast()).div_ceil(reserves.upcast());
+        lp_shares.downcast()
+    }
+
+    /// Returns the smallest `(base_amount, quote_amount)` deposit for which `mint` issues at least
+    /// `lp_shares` shares.
+    ///
+    /// The quote amount is `deposit_amount_quote(base_amount)`, so passing both values to `mint`
+    /// deposits them in full with nothing refunded.
+    pub fn deposit_for_lp_shares(&self, lp_shares: u64) -> Result<(u64, u64), PlasmaStateError> {
+        if self.total_lp_shares == 0 {
+            return Err(PlasmaStateError::UninitializedPool);
+        }
+        let total_shares = self.total_lp_shares.upcast();
+        let base_reserves = self.base_reserves.upcast();
+        let quote_reserves = self.quote_reserves.upcast();
+        let lp_shares = lp_shares.upcast();
+
+        // `mint` issues min(quote * total_shares / quote_reserves, base * total_shares / base_reserves)
+        // shares, so both terms have to reach `lp_shares` after rounding down
+        let quote_amount_min = (lp_shares * quote_reserves).div_ceil(total_shares);
+        let base_amount = quote_amount_min
+            .checked_mul(base_reserves)
+            .ok_or(PlasmaStateError::Overflow)?
+            .div_ceil(quote_reserves)
+            .max((lp_shares * base_reserves).div_ceil(total_shares))
+            .downcast()?;
+        let quote_amount = self.deposit_amount_quote(base_amount).downcast()?;
+        Ok((base_amount, quote_amount))
+    }
 }
 
 impl Amm {
@@ -120,6 +257,10 @@ impl Amm {
         self.slot_snapshot
     }
 
+    pub fn protocol_allocation_in_pct(&self) -> u32 {
+        self.protocol_allocation_in_pct
+    }
+
     pub fn deposit_amount_quote(&self, amount_base: u64) -> u128 {
         amount_base.upcast() * self.quote_reserves.upcast() / self.base_reserves.upcast()
     }
@@ -187,7 +328,7 @@ impl Amm {
 
         match side {
             Side::Buy => {
-                let ask = if quote_snapshot * base_reserves > base_snapshot * quote_reserves {
+                if quote_snapshot * base_reserves > base_snapshot * quote_reserves {
                     let size_in_quote = (quote_snapshot * base_reserves
                         - base_snapshot * quote_reserves)
                         / (2 * base_snapshot);
@@ -198,11 +339,10 @@ impl Amm {
                     }
                 } else {
                     LimitOrderConfiguration::new_default()
-                };
-                ask
+                }
             }
             Side::Sell => {
-                let bid = if base_snapshot * quote_reserves > quote_snapshot * base_reserves {
+                if base_snapshot * quote_reserves > quote_snapshot * base_reserves {
                     let size_in_base = (base_snapshot * quote_reserves
                         - quote_snapshot * base_reserves)
                         / (2 * quote_snapshot);
@@ -213,8 +353,7 @@ impl Amm {
                     }
                 } else {
                     LimitOrderConfiguration::new_default()
-                };
-                bid
+                }
             }
         }
     }
@@ -265,8 +404,7 @@ impl Amm {
         let base_reserves = self.base_reserves.upcast();
         let quote_reserves = self.quote_reserves.upcast();
         let k = (base_reserves * quote_reserves).saturating_sub(1);
-        let base_out = base_reserves - (k / (quote_reserves + quote_in)).saturating_add(1);
-        base_out
+        base_reserves - (k / (quote_reserves + quote_in)).saturating_add(1)
     }
 
     pub fn get_quote_in_from_base_out(&self, base_out: u128) -> Result<u128, PlasmaStateError> {
@@ -287,8 +425,7 @@ impl Amm {
         let base_reserves = self.base_reserves.upcast();
         let quote_reserves = self.quote_reserves.upcast();
         let k = (base_reserves * quote_reserves).saturating_sub(1);
-        let quote_out = quote_reserves - (k / (base_reserves + base_in)).saturating_add(1);
-        quote_out
+        quote_reserves - (k / (base_reserves + base_in)).saturating_add(1)
     }
 
     pub fn get_base_in_from_quote_out(&self, quote_out: u128) -> Result<u128, PlasmaStateError> {
@@ -309,7 +446,8 @@ impl Amm {
 impl Amm {
     fn get_fee_splits(&self, total_fees: u64) -> (u64, u64) {
         // This will round down so LPs get any remainders
-        let protocol_fees = (total_fees * self.protocol_allocation_in_pct as u64) / 100;
+        let protocol_fees =
+            (total_fees.upcast() * self.protocol_allocation_in_pct.upcast() / 100) as u64;
         let lp_fees = total_fees - protocol_fees;
         (lp_fees, protocol_fees)
     }
@@ -322,7 +460,7 @@ impl Amm {
         // x * 10000 / (10000 - fee) is approximately equivalent to x * (1 - fee / 10000)
         let numerator = amount * BPS_BASE;
         let denominator = BPS_BASE - self.fee_in_bps.upcast();
-        return numerator / denominator;
+        numerator / denominator
     }
 }
 
@@ -339,6 +477,19 @@ impl Amm {
     }
 }
 
+impl Amm {
+    /// Returns the number of LP shares that must be passed as `initial_lp_shares` on the first
+    /// deposit into an empty pool.
+    ///
+    /// `mint` only accepts the first deposit if `lp_shares^2 <= base * quote < (lp_shares + 1)^2`,
+    /// which is exactly the integer square root of the initial `k`.
+    pub fn initial_lp_shares(base_amount: u64, quote_amount: u64) -> u64 {
+        let initial_k = base_amount.upcast() * quote_amount.upcast();
+        // sqrt(u64::MAX * u64::MAX) < 2^64, so the root always fits in a u64
+        initial_k.isqrt() as u64
+    }
+}
+
 impl Amm {
     pub fn mint(
         &mut self,
@@ -357,9 +508,9 @@ impl Amm {
             let initial_k = base_amount_desired.upcast() * quote_amount_desired.upcast();
             let lp_shares_squared = lp_shares * lp_shares;
 
-            // Check that lp_shares^2 <= initial_k < (lp_shares + 1)^2
-            if lp_shares_squared <= initial_k && lp_shares_squared + (lp_shares * 2) + 1 > initial_k
-            {
+            // Check that lp_shares^2 <= initial_k < (lp_shares + 1)^2. (lp_shares + 1)^2 exceeds
+            // u128 for lp_shares = u64::MAX, so the upper bound is checked by division.
+            if lp_shares_squared <= initial_k && initial_k / (lp_shares + 1) < lp_shares + 1 {
                 // On initial deposit, set up the pool snapshot
                 self.base_reserves_snapshot = base_amount_desired;
                 self.quote_reserves_snapshot = quote_amount_desired;
@@ -490,8 +641,14 @@ impl Amm {
                 (lp_fees + protocol_fees) as u128,
             ));
         }
-        self.cumulative_quote_lp_fees += lp_fees;
-        self.cumulative_quote_protocol_fees += protocol_fees;
+        self.cumulative_quote_lp_fees = self
+            .cumulative_quote_lp_fees
+            .checked_add(lp_fees)
+            .ok_or(PlasmaStateError::Overflow)?;
+        self.cumulative_quote_protocol_fees = self
+            .cumulative_quote_protocol_fees
+            .checked_add(protocol_fees)
+            .ok_or(PlasmaStateError::Overflow)?;
         self.reward_factor += I80F48::from_fraction(lp_fees, self.total_lp_shares);
         Ok(())
     }
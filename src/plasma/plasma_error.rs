@@ -1,4 +1,4 @@
-use std::fmt::Display;
+use core::fmt::Display;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PlasmaStateError {
@@ -21,10 +21,14 @@ pub enum PlasmaStateError {
     SwapExactOutTooLarge,
     SwapExactInTooLarge,
     SwapOutputGreaterThanOrEqualToReserves(u128, u128),
+    WithdrawalExceedsReserves {
+        amount: u64,
+        reserves: u64,
+    },
 }
 
 impl Display for PlasmaStateError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             PlasmaStateError::InvariantViolation(k_start, k_end) => {
                 write!(
@@ -74,6 +78,15 @@ impl Display for PlasmaStateError {
                     input, reserves
                 )
             }
+            PlasmaStateError::WithdrawalExceedsReserves { amount, reserves } => {
+                write!(
+                    f,
+                    "Cannot withdraw {} from reserves of {}",
+                    amount, reserves
+                )
+            }
         }
     }
 }
+
+impl core::error::Error for PlasmaStateError {}
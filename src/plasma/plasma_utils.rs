@@ -1,11 +1,19 @@
 use borsh::{BorshDeserialize, BorshSerialize};
-use bytemuck::{Pod, Zeroable};
+use solana_account::Account;
 use solana_program::{
     declare_id,
     instruction::{AccountMeta, Instruction},
     pubkey::Pubkey,
+    rent::Rent,
     system_program,
 };
+use solana_system_interface::instruction as system_instruction;
+
+use crate::plasma::{
+    LpPosition, POOL_LEN, PlasmaStateError, SlotWindow,
+    plasma_amm::{Amm, SwapResult},
+    slot_window,
+};
 
 declare_id!("srAMMzfVHVAtgSJc8iH6CfKzuWuUTzLHVCE81QU1rgi");
 
@@ -16,9 +24,6 @@ const INITIALIZE_LP_POSITION_DISCRIMINATOR: u8 = 5;
 const INITIALIZE_POOL_DISCRIMINATOR: u8 = 6;
 const TRANSFER_LIQUIDITY_DISCRIMINATOR: u8 = 9;
 
-pub const POOL_LEN: u64 = 624;
-pub const POOL_DISCRIMINATOR: [u8; 8] = [116, 210, 187, 119, 196, 196, 52, 137];
-
 pub mod spl_token {
     solana_program::declare_id!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
 }
@@ -42,10 +47,7 @@ pub struct ProtocolFeeRecipientParams {
 }
 
 pub fn get_vault_address(plasma_program_id: &Pubkey, pool: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
-    Pubkey::find_program_address(
-        &[b"vault", pool.as_ref(), mint.as_ref()],
-        &plasma_program_id,
-    )
+    Pubkey::find_program_address(&[b"vault", pool.as_ref(), mint.as_ref()], plasma_program_id)
 }
 
 pub fn get_lp_position_address(
@@ -55,7 +57,7 @@ pub fn get_lp_position_address(
 ) -> (Pubkey, u8) {
     Pubkey::find_program_address(
         &[b"lp_position", pool.as_ref(), trader.as_ref()],
-        &plasma_program_id,
+        plasma_program_id,
     )
 }
 
@@ -63,6 +65,8 @@ pub fn get_log_authority(plasma_program_id: &Pubkey) -> Pubkey {
     Pubkey::find_program_address(&[b"log"], plasma_program_id).0
 }
 
+/// The pool account must already be allocated with `POOL_LEN` bytes and owned by the program, see
+/// [`create_pool`].
 pub fn initialize_pool(
     pool_key: &Pubkey,
     pool_creator: &Pubkey,
@@ -221,15 +225,291 @@ pub fn remove_liquidity(
     }
 }
 
-#[derive(Debug, Copy, Clone, BorshDeserialize, BorshSerialize)]
-#[repr(C)]
-pub struct LpPosition {
-    reward_factor_snapshot: i128,
+/// Builds the full instruction sequence for launching a pool in one transaction:
+/// allocate the pool account, `initialize_pool`, `initialize_lp_position` for the creator and
+/// the first `add_liquidity`.
+///
+/// The initial LP shares are derived with [`Amm::initial_lp_shares`] so the first deposit is
+/// accepted as is. Both `pool_creator` and `pool_key` must sign the transaction.
+///
+/// The pool account is funded at `Rent::default()`; use
+/// [`initialize_pool_with_liquidity_with_rent`] on clusters with a different rent.
+#[allow(clippy::too_many_arguments)]
+pub fn initialize_pool_with_liquidity(
+    pool_key: &Pubkey,
+    pool_creator: &Pubkey,
+    base_mint: &Pubkey,
+    base_account_key: &Pubkey,
+    quote_mint: &Pubkey,
+    quote_account_key: &Pubkey,
+    params: InitializePoolParams,
+    base_amount: u64,
+    quote_amount: u64,
+) -> Vec<Instruction> {
+    initialize_pool_with_liquidity_with_rent(
+        pool_key,
+        pool_creator,
+        base_mint,
+        base_account_key,
+        quote_mint,
+        quote_account_key,
+        params,
+        base_amount,
+        quote_amount,
+        &Rent::default(),
+    )
+}
+
+/// [`initialize_pool_with_liquidity`] with the pool account funded to be rent-exempt at `rent`
+#[allow(clippy::too_many_arguments)]
+pub fn initialize_pool_with_liquidity_with_rent(
+    pool_key: &Pubkey,
+    pool_creator: &Pubkey,
+    base_mint: &Pubkey,
+    base_account_key: &Pubkey,
+    quote_mint: &Pubkey,
+    quote_account_key: &Pubkey,
+    params: InitializePoolParams,
+    base_amount: u64,
+    quote_amount: u64,
+    rent: &Rent,
+) -> Vec<Instruction> {
+    vec![
+        create_pool_account(pool_key, pool_creator, rent),
+        initialize_pool(pool_key, pool_creator, base_mint, quote_mint, params),
+        initialize_lp_position(pool_key, pool_creator, pool_creator),
+        add_liquidity(
+            pool_key,
+            pool_creator,
+            base_mint,
+            base_account_key,
+            quote_mint,
+            quote_account_key,
+            AddLiquidityParams {
+                desired_base_amount_in: base_amount,
+                desired_quote_amount_in: quote_amount,
+                initial_lp_shares: Some(Amm::initial_lp_shares(base_amount, quote_amount)),
+            },
+        ),
+    ]
+}
+
+/// Allocates a rent-exempt, program-owned pool account. `pool_key` must sign.
+pub fn create_pool_account(pool_key: &Pubkey, pool_creator: &Pubkey, rent: &Rent) -> Instruction {
+    system_instruction::create_account(
+        pool_creator,
+        pool_key,
+        rent.minimum_balance(POOL_LEN as usize),
+        POOL_LEN,
+        &ID,
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CreatePoolError {
+    /// The pool key already holds lamports or data, so `create_account` would fail
+    PoolAccountInUse(Pubkey),
+    /// The pool key is also the creator or one of the mints
+    PoolKeyReused(Pubkey),
+}
+
+impl std::fmt::Display for CreatePoolError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CreatePoolError::PoolAccountInUse(key) => {
+                write!(f, "Pool account {} already exists", key)
+            }
+            CreatePoolError::PoolKeyReused(key) => {
+                write!(f, "Pool key {} is also used as the creator or a mint", key)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CreatePoolError {}
+
+/// Instructions that create and initialize a pool, and the accounts that must sign them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolCreation {
+    pub instructions: Vec<Instruction>,
+    /// The pool creator, who also pays the fees and the rent, followed by the pool key
+    pub signers: Vec<Pubkey>,
+    /// Lamports moved from the creator into the pool account
+    pub pool_lamports: u64,
+}
+
+/// Builds `create_account` for the pool with rent-exempt lamports for `POOL_LEN` at `rent`,
+/// followed by `initialize_pool`.
+///
+/// `existing_pool_account` is the current state of `pool_key`, if any, so a keypair that was
+/// already used is rejected before the transaction is sent.
+pub fn create_pool(
+    pool_key: &Pubkey,
+    pool_creator: &Pubkey,
+    base_mint: &Pubkey,
+    quote_mint: &Pubkey,
+    params: InitializePoolParams,
+    rent: &Rent,
+    existing_pool_account: Option<&Account>,
+) -> Result<PoolCreation, CreatePoolError> {
+    if [pool_creator, base_mint, quote_mint].contains(&pool_key) {
+        return Err(CreatePoolError::PoolKeyReused(*pool_key));
+    }
+    if existing_pool_account.is_some_and(|account| {
+        account.lamports > 0 || !account.data.is_empty() || account.owner != system_program::ID
+    }) {
+        return Err(CreatePoolError::PoolAccountInUse(*pool_key));
+    }
+    Ok(PoolCreation {
+        instructions: vec![
+            create_pool_account(pool_key, pool_creator, rent),
+            initialize_pool(pool_key, pool_creator, base_mint, quote_mint, params),
+        ],
+        signers: vec![*pool_creator, *pool_key],
+        pool_lamports: rent.minimum_balance(POOL_LEN as usize),
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferLiquidityError {
+    /// The source and the destination are the same owner
+    SameOwner(Pubkey),
+    /// The source owner has no LP position in the pool
+    SourceNotFound(Pubkey),
+    /// The account at an LP position address is not an LP position
+    InvalidLpPosition(Pubkey),
+    /// The destination LP position address holds lamports but no account, so the system program
+    /// refuses to create the position there
+    DestinationFunded(Pubkey),
+    /// The source LP position holds no shares
+    NoShares(Pubkey),
+    /// Shares deposited in `deposit_window` are still vesting, which the program rejects
+    SharesStillVesting {
+        deposit_window: SlotWindow,
+        shares: u64,
+    },
+}
+
+impl std::fmt::Display for TransferLiquidityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransferLiquidityError::SameOwner(owner) => {
+                write!(f, "Cannot transfer liquidity from {} to itself", owner)
+            }
+            TransferLiquidityError::SourceNotFound(key) => {
+                write!(f, "Source LP position {} does not exist", key)
+            }
+            TransferLiquidityError::InvalidLpPosition(key) => {
+                write!(f, "Account {} is not an LP position", key)
+            }
+            TransferLiquidityError::DestinationFunded(key) => {
+                write!(
+                    f,
+                    "Destination LP position {} holds lamports and cannot be created",
+                    key
+                )
+            }
+            TransferLiquidityError::NoShares(key) => {
+                write!(f, "LP position {} holds no shares", key)
+            }
+            TransferLiquidityError::SharesStillVesting {
+                deposit_window,
+                shares,
+            } => write!(
+                f,
+                "{} shares deposited in slot window {} are still vesting",
+                shares, deposit_window
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TransferLiquidityError {}
+
+/// Instructions that move every LP share of a position to another owner
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiquidityTransfer {
+    pub instructions: Vec<Instruction>,
+    /// Set when the destination position is created first, with the source owner paying the rent
+    pub initializes_destination: bool,
     pub lp_shares: u64,
+    /// Shares of `lp_shares` that can be withdrawn, including the ones vested by the transfer
     pub withdrawable_lp_shares: u64,
-    uncollected_fees: u64,
-    collected_fees: u64,
-    pub pending_shares_to_vest: (u64, u64),
+}
+
+fn decode_lp_position(
+    key: &Pubkey,
+    account: &Account,
+) -> Result<LpPosition, TransferLiquidityError> {
+    if account.owner != ID {
+        return Err(TransferLiquidityError::InvalidLpPosition(*key));
+    }
+    LpPosition::decode(&account.data).ok_or(TransferLiquidityError::InvalidLpPosition(*key))
+}
+
+/// Builds `transfer_liquidity` from `src` to `dst`, preceded by `initialize_lp_position` when the
+/// destination position does not exist yet, so liquidity can be sent to fresh wallets.
+///
+/// `source_lp_position` and `destination_lp_position` are the fetched accounts at the LP
+/// position addresses of `src` and `dst`, if any, and `amm` the state of the pool at `slot`.
+/// Transfers the program would reject, or that would move nothing, are refused.
+pub fn transfer_liquidity_checked(
+    pool_key: &Pubkey,
+    src: &Pubkey,
+    dst: &Pubkey,
+    amm: &Amm,
+    slot: u64,
+    source_lp_position: Option<&Account>,
+    destination_lp_position: Option<&Account>,
+) -> Result<LiquidityTransfer, TransferLiquidityError> {
+    if src == dst {
+        return Err(TransferLiquidityError::SameOwner(*src));
+    }
+    let (src_lp_position_key, _) = get_lp_position_address(&ID, pool_key, src);
+    let (dst_lp_position_key, _) = get_lp_position_address(&ID, pool_key, dst);
+    let source = decode_lp_position(
+        &src_lp_position_key,
+        source_lp_position.ok_or(TransferLiquidityError::SourceNotFound(src_lp_position_key))?,
+    )?;
+    let (deposit_window, pending_shares) = source.pending_shares_to_vest;
+    if pending_shares > 0
+        && slot_window(slot) < deposit_window.saturating_add(amm.lp_vesting_window)
+    {
+        return Err(TransferLiquidityError::SharesStillVesting {
+            deposit_window,
+            shares: pending_shares,
+        });
+    }
+    if source.lp_shares == 0 {
+        return Err(TransferLiquidityError::NoShares(src_lp_position_key));
+    }
+
+    let initializes_destination = match destination_lp_position {
+        Some(account) if account.owner == system_program::ID && account.data.is_empty() => {
+            if account.lamports > 0 {
+                return Err(TransferLiquidityError::DestinationFunded(
+                    dst_lp_position_key,
+                ));
+            }
+            true
+        }
+        Some(account) => {
+            decode_lp_position(&dst_lp_position_key, account)?;
+            false
+        }
+        None => true,
+    };
+    let mut instructions = vec![];
+    if initializes_destination {
+        instructions.push(initialize_lp_position(pool_key, src, dst));
+    }
+    instructions.push(transfer_liquidity(pool_key, src, dst));
+    Ok(LiquidityTransfer {
+        instructions,
+        initializes_destination,
+        lp_shares: source.lp_shares,
+        withdrawable_lp_shares: source.withdrawable_lp_shares.saturating_add(pending_shares),
+    })
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, BorshDeserialize, BorshSerialize)]
@@ -239,18 +519,40 @@ pub enum Side {
 }
 
 #[repr(C)]
-#[derive(Clone, Copy, Debug, BorshDeserialize, BorshSerialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, BorshDeserialize, BorshSerialize)]
 pub struct SwapParams {
     pub side: Side,
     pub swap_type: SwapType,
 }
 
-#[derive(Clone, Copy, Debug, BorshDeserialize, BorshSerialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, BorshDeserialize, BorshSerialize)]
 pub enum SwapType {
     ExactIn { amount_in: u64, min_amount_out: u64 },
     ExactOut { amount_out: u64, max_amount_in: u64 },
 }
 
+impl SwapParams {
+    /// Runs the swap against `amm`. The slippage limit is not checked.
+    pub fn execute(
+        &self,
+        amm: &mut Amm,
+        window: SlotWindow,
+    ) -> Result<SwapResult, PlasmaStateError> {
+        match (self.side, self.swap_type) {
+            (Side::Buy, SwapType::ExactIn { amount_in, .. }) => amm.buy_exact_in(window, amount_in),
+            (Side::Buy, SwapType::ExactOut { amount_out, .. }) => {
+                amm.buy_exact_out(window, amount_out)
+            }
+            (Side::Sell, SwapType::ExactIn { amount_in, .. }) => {
+                amm.sell_exact_in(window, amount_in)
+            }
+            (Side::Sell, SwapType::ExactOut { amount_out, .. }) => {
+                amm.sell_exact_out(window, amount_out)
+            }
+        }
+    }
+}
+
 pub fn swap(
     pool_key: &Pubkey,
     trader: &Pubkey,
@@ -286,46 +588,39 @@ pub fn swap(
     }
 }
 
-#[derive(Debug, Copy, Clone, Zeroable, Pod, BorshDeserialize, BorshSerialize)]
-#[repr(C)]
-pub struct PoolHeader {
-    pub discriminator: [u8; 8],
-    pub sequence_number: u64,
-    pub base_params: TokenParams,
-    pub quote_params: TokenParams,
-    pub fee_recipients: ProtocolFeeRecipients,
-    pub swap_sequence_number: u64,
-    pub padding: [u64; 12],
-}
-
-#[derive(Debug, Copy, Clone, Zeroable, Pod, BorshDeserialize, BorshSerialize)]
-#[repr(C)]
-pub struct TokenParams {
-    /// Number of decimals for the token (e.g. 9 for SOL, 6 for USDC).
-    pub decimals: u32,
-
-    /// Bump used for generating the PDA for the pool's token vault.
-    pub vault_bump: u32,
-
-    /// Pubkey of the token mint.
-    pub mint_key: Pubkey,
-
-    /// Pubkey of the token vault.
-    pub vault_key: Pubkey,
-}
-
-#[derive(Debug, Default, Copy, Clone, Zeroable, Pod, BorshDeserialize, BorshSerialize)]
-#[repr(C)]
-pub struct ProtocolFeeRecipient {
-    pub recipient: Pubkey,
-    pub shares: u64,
-    pub total_accumulated_quote_fees: u64,
-    pub collected_quote_fees: u64,
+/// Instruction data of a Plasma instruction, decoded from the layout produced by the builders
+/// in this module
+#[derive(Debug, Clone, Copy)]
+pub enum PlasmaInstruction {
+    Swap(SwapParams),
+    AddLiquidity(AddLiquidityParams),
+    RemoveLiquidity { shares: u64 },
+    InitializeLpPosition,
+    InitializePool(InitializePoolParams),
+    TransferLiquidity,
 }
 
-#[derive(Debug, Default, Copy, Clone, Zeroable, Pod, BorshDeserialize, BorshSerialize)]
-#[repr(C)]
-pub struct ProtocolFeeRecipients {
-    pub recipients: [ProtocolFeeRecipient; 3],
-    _padding: [u64; 12],
+impl PlasmaInstruction {
+    /// Returns `None` if the discriminator is unknown or the payload does not decode exactly
+    pub fn unpack(data: &[u8]) -> Option<Self> {
+        let (discriminator, payload) = data.split_first()?;
+        let instruction = match *discriminator {
+            SWAP_DISCRIMINATOR => Self::Swap(SwapParams::try_from_slice(payload).ok()?),
+            ADD_LIQUIDITY_DISCRIMINATOR => {
+                Self::AddLiquidity(AddLiquidityParams::try_from_slice(payload).ok()?)
+            }
+            REMOVE_LIQUIDITY_DISCRIMINATOR => Self::RemoveLiquidity {
+                shares: u64::try_from_slice(payload).ok()?,
+            },
+            INITIALIZE_LP_POSITION_DISCRIMINATOR if payload.is_empty() => {
+                Self::InitializeLpPosition
+            }
+            INITIALIZE_POOL_DISCRIMINATOR => {
+                Self::InitializePool(InitializePoolParams::try_from_slice(payload).ok()?)
+            }
+            TRANSFER_LIQUIDITY_DISCRIMINATOR if payload.is_empty() => Self::TransferLiquidity,
+            _ => return None,
+        };
+        Some(instruction)
+    }
 }
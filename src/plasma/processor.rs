@@ -0,0 +1,766 @@
+use std::fmt::Display;
+
+use borsh::BorshSerialize;
+use bytemuck::Zeroable;
+use solana_account::Account;
+use solana_program::{
+    instruction::AccountMeta, program_error::ProgramError, pubkey::Pubkey, system_program,
+};
+
+use crate::{
+    PoolAccount,
+    plasma::{
+        InitializePoolParams, LP_POSITION_LEN, LpPosition, POOL_DISCRIMINATOR, POOL_LEN,
+        PlasmaInstruction, PlasmaStateError, PoolHeader, ProtocolFeeRecipients, SLOTS_PER_WINDOW,
+        Side, SlotWindow, SwapParams, SwapType, TokenParams,
+        plasma_amm::{Amm, BPS_BASE, SwapResult},
+        slot_window, spl_token,
+        token::{Mint, TokenAccount},
+    },
+};
+
+/// Errors returned by the Plasma processor model
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProcessorError {
+    State(PlasmaStateError),
+    InvalidInstructionData,
+    NotEnoughAccountKeys,
+    IncorrectProgramId(Pubkey),
+    MissingRequiredSignature(Pubkey),
+    AccountNotWritable(Pubkey),
+    InvalidSeeds(Pubkey),
+    AccountNotFound(Pubkey),
+    AccountAlreadyInitialized(Pubkey),
+    InvalidAccountData(Pubkey),
+    IllegalOwner(Pubkey),
+    InsufficientFunds(Pubkey),
+    SlippageExceeded {
+        limit: u64,
+        actual: u64,
+    },
+    /// Error raised by the runtime or a cross-program invocation
+    Program(ProgramError),
+}
+
+impl From<PlasmaStateError> for ProcessorError {
+    fn from(error: PlasmaStateError) -> Self {
+        ProcessorError::State(error)
+    }
+}
+
+impl From<ProgramError> for ProcessorError {
+    fn from(error: ProgramError) -> Self {
+        ProcessorError::Program(error)
+    }
+}
+
+impl Display for ProcessorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProcessorError::State(error) => write!(f, "{}", error),
+            ProcessorError::InvalidInstructionData => write!(f, "Invalid instruction data"),
+            ProcessorError::NotEnoughAccountKeys => write!(f, "Not enough account keys"),
+            ProcessorError::IncorrectProgramId(key) => write!(f, "Incorrect program id {}", key),
+            ProcessorError::MissingRequiredSignature(key) => {
+                write!(f, "Missing required signature for {}", key)
+            }
+            ProcessorError::AccountNotWritable(key) => write!(f, "Account {} is not writable", key),
+            ProcessorError::InvalidSeeds(key) => {
+                write!(f, "Account {} does not match the expected PDA", key)
+            }
+            ProcessorError::AccountNotFound(key) => write!(f, "Account {} not found", key),
+            ProcessorError::AccountAlreadyInitialized(key) => {
+                write!(f, "Account {} is already initialized", key)
+            }
+            ProcessorError::InvalidAccountData(key) => {
+                write!(f, "Account {} has invalid data", key)
+            }
+            ProcessorError::IllegalOwner(key) => write!(f, "Account {} has an illegal owner", key),
+            ProcessorError::InsufficientFunds(key) => {
+                write!(f, "Account {} has insufficient funds", key)
+            }
+            ProcessorError::SlippageExceeded { limit, actual } => {
+                write!(f, "Slippage exceeded: limit {} but got {}", limit, actual)
+            }
+            ProcessorError::Program(error) => write!(f, "Program error: {}", error),
+        }
+    }
+}
+
+/// Account storage and side effects that the processor runs against.
+///
+/// Implemented by the in-memory [`crate::plasma::reference::ReferenceRuntime`], and by runtime
+/// adapters that perform the same effects through CPIs.
+pub trait ProcessorContext {
+    /// Current cluster slot
+    fn slot(&self) -> u64;
+
+    fn account(&self, key: &Pubkey) -> Option<Account>;
+
+    /// Overwrites the data of an account owned by the executing program
+    fn set_data(&mut self, key: &Pubkey, data: &[u8]) -> Result<(), ProcessorError>;
+
+    /// Creates a rent-exempt account of `space` bytes owned by `owner`, funded by `payer`.
+    /// `seeds` are the signer seeds of `key`, which is always a PDA of the executing program.
+    fn create_account(
+        &mut self,
+        payer: &Pubkey,
+        key: &Pubkey,
+        space: u64,
+        owner: &Pubkey,
+        seeds: &[&[u8]],
+    ) -> Result<(), ProcessorError>;
+
+    /// Creates a token account for `mint` at the PDA `key` whose authority is itself
+    fn create_token_account(
+        &mut self,
+        payer: &Pubkey,
+        key: &Pubkey,
+        mint: &Pubkey,
+        seeds: &[&[u8]],
+    ) -> Result<(), ProcessorError>;
+
+    /// Moves tokens between two token accounts. `authority_seeds` is set when the authority is
+    /// a PDA of the executing program.
+    fn transfer_tokens(
+        &mut self,
+        source: &Pubkey,
+        destination: &Pubkey,
+        authority: &Pubkey,
+        amount: u64,
+        authority_seeds: Option<&[&[u8]]>,
+    ) -> Result<(), ProcessorError>;
+}
+
+/// Executes a Plasma instruction against `ctx`.
+///
+/// `accounts` carries the signer and writable flags of the transaction, which are checked
+/// against the layout produced by the builders in `plasma_utils`.
+pub fn process_instruction(
+    ctx: &mut impl ProcessorContext,
+    program_id: &Pubkey,
+    accounts: &[AccountMeta],
+    data: &[u8],
+) -> Result<(), ProcessorError> {
+    let instruction =
+        PlasmaInstruction::unpack(data).ok_or(ProcessorError::InvalidInstructionData)?;
+    let accounts = Accounts::new(program_id, accounts)?;
+    match instruction {
+        PlasmaInstruction::InitializePool(params) => {
+            process_initialize_pool(ctx, program_id, &accounts, params)
+        }
+        PlasmaInstruction::InitializeLpPosition => {
+            process_initialize_lp_position(ctx, program_id, &accounts)
+        }
+        PlasmaInstruction::AddLiquidity(params) => process_add_liquidity(
+            ctx,
+            program_id,
+            &accounts,
+            params.desired_base_amount_in,
+            params.desired_quote_amount_in,
+            params.initial_lp_shares,
+        ),
+        PlasmaInstruction::RemoveLiquidity { shares } => {
+            process_remove_liquidity(ctx, program_id, &accounts, shares)
+        }
+        PlasmaInstruction::TransferLiquidity => {
+            process_transfer_liquidity(ctx, program_id, &accounts)
+        }
+        PlasmaInstruction::Swap(params) => process_swap(ctx, program_id, &accounts, params),
+    }
+}
+
+struct Accounts<'a> {
+    metas: &'a [AccountMeta],
+}
+
+impl<'a> Accounts<'a> {
+    /// Every instruction starts with the program itself and the log authority
+    fn new(program_id: &Pubkey, metas: &'a [AccountMeta]) -> Result<Self, ProcessorError> {
+        let accounts = Self { metas };
+        let program = accounts.get(0)?;
+        if program.pubkey != *program_id {
+            return Err(ProcessorError::IncorrectProgramId(program.pubkey));
+        }
+        let log_authority = accounts.get(1)?;
+        if log_authority.pubkey != Pubkey::find_program_address(&[b"log"], program_id).0 {
+            return Err(ProcessorError::InvalidSeeds(log_authority.pubkey));
+        }
+        Ok(accounts)
+    }
+
+    fn get(&self, index: usize) -> Result<&'a AccountMeta, ProcessorError> {
+        self.metas
+            .get(index)
+            .ok_or(ProcessorError::NotEnoughAccountKeys)
+    }
+
+    fn key(&self, index: usize) -> Result<Pubkey, ProcessorError> {
+        Ok(self.get(index)?.pubkey)
+    }
+
+    fn signer(&self, index: usize) -> Result<Pubkey, ProcessorError> {
+        let meta = self.get(index)?;
+        if !meta.is_signer {
+            return Err(ProcessorError::MissingRequiredSignature(meta.pubkey));
+        }
+        Ok(meta.pubkey)
+    }
+
+    fn writable(&self, index: usize) -> Result<Pubkey, ProcessorError> {
+        let meta = self.get(index)?;
+        if !meta.is_writable {
+            return Err(ProcessorError::AccountNotWritable(meta.pubkey));
+        }
+        Ok(meta.pubkey)
+    }
+
+    fn program(&self, index: usize, expected: &Pubkey) -> Result<(), ProcessorError> {
+        let key = self.key(index)?;
+        if key != *expected {
+            return Err(ProcessorError::IncorrectProgramId(key));
+        }
+        Ok(())
+    }
+}
+
+fn load_account(
+    ctx: &impl ProcessorContext,
+    key: &Pubkey,
+    owner: &Pubkey,
+) -> Result<Account, ProcessorError> {
+    let account = ctx
+        .account(key)
+        .ok_or(ProcessorError::AccountNotFound(*key))?;
+    if account.owner != *owner {
+        return Err(ProcessorError::IllegalOwner(*key));
+    }
+    Ok(account)
+}
+
+pub(crate) fn load_pool(
+    ctx: &impl ProcessorContext,
+    program_id: &Pubkey,
+    key: &Pubkey,
+) -> Result<PoolAccount, ProcessorError> {
+    let account = load_account(ctx, key, program_id)?;
+    PoolAccount::decode(&account.data).ok_or(ProcessorError::InvalidAccountData(*key))
+}
+
+fn load_lp_position(
+    ctx: &impl ProcessorContext,
+    program_id: &Pubkey,
+    key: &Pubkey,
+) -> Result<LpPosition, ProcessorError> {
+    let account = load_account(ctx, key, program_id)?;
+    LpPosition::decode(&account.data).ok_or(ProcessorError::InvalidAccountData(*key))
+}
+
+fn load_token_account(
+    ctx: &impl ProcessorContext,
+    key: &Pubkey,
+    mint: &Pubkey,
+) -> Result<TokenAccount, ProcessorError> {
+    let account = load_account(ctx, key, &spl_token::ID)?;
+    let token_account =
+        TokenAccount::unpack(&account.data).ok_or(ProcessorError::InvalidAccountData(*key))?;
+    if token_account.mint != *mint {
+        return Err(ProcessorError::InvalidAccountData(*key));
+    }
+    Ok(token_account)
+}
+
+fn store<T: BorshSerialize>(
+    ctx: &mut impl ProcessorContext,
+    key: &Pubkey,
+    value: &T,
+) -> Result<(), ProcessorError> {
+    let mut data = vec![];
+    value
+        .serialize(&mut data)
+        .map_err(|_| ProcessorError::InvalidAccountData(*key))?;
+    ctx.set_data(key, &data)
+}
+
+/// Checks that the vault accounts at `base_index` and `base_index + 1` are the pool's vaults
+fn check_vaults(
+    accounts: &Accounts,
+    header: &PoolHeader,
+    base_index: usize,
+) -> Result<(), ProcessorError> {
+    let base_vault = accounts.writable(base_index)?;
+    if base_vault != header.base_params.vault_key {
+        return Err(ProcessorError::InvalidSeeds(base_vault));
+    }
+    let quote_vault = accounts.writable(base_index + 1)?;
+    if quote_vault != header.quote_params.vault_key {
+        return Err(ProcessorError::InvalidSeeds(quote_vault));
+    }
+    Ok(())
+}
+
+/// Moves newly vested shares into `withdrawable_lp_shares`.
+///
+/// `pending_shares_to_vest` holds the slot window of the last deposit and the shares it minted.
+pub(crate) fn vest_lp_shares(position: &mut LpPosition, amm: &Amm, window: SlotWindow) {
+    let (deposit_window, shares) = position.pending_shares_to_vest;
+    if shares > 0 && window >= deposit_window.saturating_add(amm.lp_vesting_window) {
+        position.withdrawable_lp_shares += shares;
+        position.pending_shares_to_vest = (0, 0);
+    }
+}
+
+/// Accrues fees earned since the position's reward factor snapshot
+pub(crate) fn settle_lp_fees(position: &mut LpPosition, amm: &Amm) -> Result<(), PlasmaStateError> {
+    position.uncollected_fees = position
+        .pending_fees(amm)
+        .and_then(|fees| position.uncollected_fees.checked_add(fees))
+        .ok_or(PlasmaStateError::Overflow)?;
+    position.reward_factor_snapshot = amm.reward_factor.to_bits();
+    Ok(())
+}
+
+fn process_initialize_pool(
+    ctx: &mut impl ProcessorContext,
+    program_id: &Pubkey,
+    accounts: &Accounts,
+    params: InitializePoolParams,
+) -> Result<(), ProcessorError> {
+    let pool_key = accounts.writable(2)?;
+    let pool_creator = accounts.signer(3)?;
+    accounts.writable(3)?;
+    let base_mint = accounts.key(4)?;
+    let quote_mint = accounts.key(5)?;
+    let base_vault = accounts.writable(6)?;
+    let quote_vault = accounts.writable(7)?;
+    accounts.program(8, &system_program::ID)?;
+    accounts.program(9, &spl_token::ID)?;
+
+    let pool_account = load_account(ctx, &pool_key, program_id)?;
+    if pool_account.data.len() != POOL_LEN as usize {
+        return Err(ProcessorError::InvalidAccountData(pool_key));
+    }
+    if pool_account.data.iter().any(|byte| *byte != 0) {
+        return Err(ProcessorError::AccountAlreadyInitialized(pool_key));
+    }
+    if base_mint == quote_mint {
+        return Err(ProcessorError::InvalidAccountData(quote_mint));
+    }
+    if params.lp_fee_in_bps >= BPS_BASE as u64 || params.protocol_fee_allocation_in_pct > 100 {
+        return Err(PlasmaStateError::UnexpectedArgument.into());
+    }
+
+    let mut token_params = [TokenParams::zeroed(); 2];
+    for ((mint_key, vault_key), token_params) in
+        [(base_mint, base_vault), (quote_mint, quote_vault)]
+            .into_iter()
+            .zip(token_params.iter_mut())
+    {
+        let mint_account = load_account(ctx, &mint_key, &spl_token::ID)?;
+        let mint =
+            Mint::unpack(&mint_account.data).ok_or(ProcessorError::InvalidAccountData(mint_key))?;
+        let (expected_vault, vault_bump) = Pubkey::find_program_address(
+            &[b"vault", pool_key.as_ref(), mint_key.as_ref()],
+            program_id,
+        );
+        if vault_key != expected_vault {
+            return Err(ProcessorError::InvalidSeeds(vault_key));
+        }
+        ctx.create_token_account(
+            &pool_creator,
+            &vault_key,
+            &mint_key,
+            &[
+                b"vault",
+                pool_key.as_ref(),
+                mint_key.as_ref(),
+                &[vault_bump],
+            ],
+        )?;
+        *token_params = TokenParams {
+            decimals: mint.decimals as u32,
+            vault_bump: vault_bump as u32,
+            mint_key,
+            vault_key,
+        };
+    }
+
+    let mut fee_recipients = ProtocolFeeRecipients::default();
+    for (recipient, recipient_params) in fee_recipients
+        .recipients
+        .iter_mut()
+        .zip(params.fee_recipients_params.iter())
+    {
+        recipient.recipient = recipient_params.recipient;
+        recipient.shares = recipient_params.shares;
+    }
+
+    let window = slot_window(ctx.slot());
+    let pool = PoolAccount {
+        header: PoolHeader {
+            discriminator: POOL_DISCRIMINATOR,
+            sequence_number: 0,
+            base_params: token_params[0],
+            quote_params: token_params[1],
+            fee_recipients,
+            swap_sequence_number: 0,
+            padding: [0; 12],
+        },
+        amm: Amm::new(
+            params.lp_fee_in_bps as u32,
+            params.protocol_fee_allocation_in_pct as u32,
+            params.num_slots_to_vest_lp_shares.unwrap_or(0) / SLOTS_PER_WINDOW,
+            window,
+        ),
+    };
+    store(ctx, &pool_key, &pool)
+}
+
+fn process_initialize_lp_position(
+    ctx: &mut impl ProcessorContext,
+    program_id: &Pubkey,
+    accounts: &Accounts,
+) -> Result<(), ProcessorError> {
+    let pool_key = accounts.writable(2)?;
+    let payer = accounts.signer(3)?;
+    accounts.writable(3)?;
+    let owner = accounts.key(4)?;
+    let lp_position_key = accounts.writable(5)?;
+    accounts.program(6, &system_program::ID)?;
+
+    let mut pool = load_pool(ctx, program_id, &pool_key)?;
+    let (expected_lp_position, bump) = Pubkey::find_program_address(
+        &[b"lp_position", pool_key.as_ref(), owner.as_ref()],
+        program_id,
+    );
+    if lp_position_key != expected_lp_position {
+        return Err(ProcessorError::InvalidSeeds(lp_position_key));
+    }
+    if ctx
+        .account(&lp_position_key)
+        .is_some_and(|account| account.owner != system_program::ID || !account.data.is_empty())
+    {
+        return Err(ProcessorError::AccountAlreadyInitialized(lp_position_key));
+    }
+
+    ctx.create_account(
+        &payer,
+        &lp_position_key,
+        LP_POSITION_LEN,
+        program_id,
+        &[b"lp_position", pool_key.as_ref(), owner.as_ref(), &[bump]],
+    )?;
+    let lp_position = LpPosition {
+        reward_factor_snapshot: pool.amm.reward_factor.to_bits(),
+        lp_shares: 0,
+        withdrawable_lp_shares: 0,
+        uncollected_fees: 0,
+        collected_fees: 0,
+        pending_shares_to_vest: (0, 0),
+    };
+    store(ctx, &lp_position_key, &lp_position)?;
+
+    pool.header.sequence_number += 1;
+    store(ctx, &pool_key, &pool)
+}
+
+/// Resolves the trader's LP position for add and remove liquidity
+fn load_trader_lp_position(
+    ctx: &impl ProcessorContext,
+    program_id: &Pubkey,
+    accounts: &Accounts,
+    pool_key: &Pubkey,
+    trader: &Pubkey,
+) -> Result<(Pubkey, LpPosition), ProcessorError> {
+    let lp_position_key = accounts.writable(4)?;
+    let (expected_lp_position, _) = Pubkey::find_program_address(
+        &[b"lp_position", pool_key.as_ref(), trader.as_ref()],
+        program_id,
+    );
+    if lp_position_key != expected_lp_position {
+        return Err(ProcessorError::InvalidSeeds(lp_position_key));
+    }
+    Ok((
+        lp_position_key,
+        load_lp_position(ctx, program_id, &lp_position_key)?,
+    ))
+}
+
+fn process_add_liquidity(
+    ctx: &mut impl ProcessorContext,
+    program_id: &Pubkey,
+    accounts: &Accounts,
+    base_amount_desired: u64,
+    quote_amount_desired: u64,
+    initial_lp_shares: Option<u64>,
+) -> Result<(), ProcessorError> {
+    let pool_key = accounts.writable(2)?;
+    let trader = accounts.signer(3)?;
+    let base_account = accounts.writable(5)?;
+    let quote_account = accounts.writable(6)?;
+    accounts.program(9, &spl_token::ID)?;
+
+    let mut pool = load_pool(ctx, program_id, &pool_key)?;
+    check_vaults(accounts, &pool.header, 7)?;
+    let (lp_position_key, mut lp_position) =
+        load_trader_lp_position(ctx, program_id, accounts, &pool_key, &trader)?;
+    load_token_account(ctx, &base_account, &pool.header.base_params.mint_key)?;
+    load_token_account(ctx, &quote_account, &pool.header.quote_params.mint_key)?;
+
+    let window = slot_window(ctx.slot());
+    vest_lp_shares(&mut lp_position, &pool.amm, window);
+    if lp_position.pending_shares_to_vest.1 > 0 {
+        return Err(PlasmaStateError::VestingPeriodNotOver.into());
+    }
+    settle_lp_fees(&mut lp_position, &pool.amm)?;
+
+    let (base_amount_deposited, quote_amount_deposited, lp_shares) = pool.amm.mint(
+        window,
+        base_amount_desired,
+        quote_amount_desired,
+        initial_lp_shares,
+    )?;
+    ctx.transfer_tokens(
+        &base_account,
+        &pool.header.base_params.vault_key,
+        &trader,
+        base_amount_deposited,
+        None,
+    )?;
+    ctx.transfer_tokens(
+        &quote_account,
+        &pool.header.quote_params.vault_key,
+        &trader,
+        quote_amount_deposited,
+        None,
+    )?;
+
+    lp_position.lp_shares += lp_shares;
+    if pool.amm.lp_vesting_window == 0 {
+        lp_position.withdrawable_lp_shares += lp_shares;
+    } else {
+        lp_position.pending_shares_to_vest = (window, lp_shares);
+    }
+    pool.header.sequence_number += 1;
+    store(ctx, &lp_position_key, &lp_position)?;
+    store(ctx, &pool_key, &pool)
+}
+
+/// Signer seeds of a pool vault
+fn vault_seeds<'a>(
+    pool_key: &'a Pubkey,
+    token_params: &'a TokenParams,
+    bump: &'a [u8; 1],
+) -> [&'a [u8]; 4] {
+    [
+        b"vault",
+        pool_key.as_ref(),
+        token_params.mint_key.as_ref(),
+        bump,
+    ]
+}
+
+fn process_remove_liquidity(
+    ctx: &mut impl ProcessorContext,
+    program_id: &Pubkey,
+    accounts: &Accounts,
+    shares: u64,
+) -> Result<(), ProcessorError> {
+    let pool_key = accounts.writable(2)?;
+    let trader = accounts.signer(3)?;
+    let base_account = accounts.writable(5)?;
+    let quote_account = accounts.writable(6)?;
+    accounts.program(9, &spl_token::ID)?;
+
+    let mut pool = load_pool(ctx, program_id, &pool_key)?;
+    check_vaults(accounts, &pool.header, 7)?;
+    let (lp_position_key, mut lp_position) =
+        load_trader_lp_position(ctx, program_id, accounts, &pool_key, &trader)?;
+    load_token_account(ctx, &base_account, &pool.header.base_params.mint_key)?;
+    load_token_account(ctx, &quote_account, &pool.header.quote_params.mint_key)?;
+
+    let window = slot_window(ctx.slot());
+    vest_lp_shares(&mut lp_position, &pool.amm, window);
+    if shares > lp_position.withdrawable_lp_shares {
+        return Err(PlasmaStateError::TooManyShares.into());
+    }
+    settle_lp_fees(&mut lp_position, &pool.amm)?;
+
+    let (base_amount_withdrawn, quote_amount_withdrawn) = pool.amm.burn(window, shares)?;
+    let base_params = pool.header.base_params;
+    let quote_params = pool.header.quote_params;
+    let base_bump = [base_params.vault_bump as u8];
+    let quote_bump = [quote_params.vault_bump as u8];
+    ctx.transfer_tokens(
+        &base_params.vault_key,
+        &base_account,
+        &base_params.vault_key,
+        base_amount_withdrawn,
+        Some(&vault_seeds(&pool_key, &base_params, &base_bump)),
+    )?;
+    ctx.transfer_tokens(
+        &quote_params.vault_key,
+        &quote_account,
+        &quote_params.vault_key,
+        quote_amount_withdrawn,
+        Some(&vault_seeds(&pool_key, &quote_params, &quote_bump)),
+    )?;
+
+    lp_position.lp_shares -= shares;
+    lp_position.withdrawable_lp_shares -= shares;
+    pool.header.sequence_number += 1;
+    store(ctx, &lp_position_key, &lp_position)?;
+    store(ctx, &pool_key, &pool)
+}
+
+fn process_transfer_liquidity(
+    ctx: &mut impl ProcessorContext,
+    program_id: &Pubkey,
+    accounts: &Accounts,
+) -> Result<(), ProcessorError> {
+    let pool_key = accounts.writable(2)?;
+    let src = accounts.signer(3)?;
+    let src_lp_position_key = accounts.writable(4)?;
+    let dst_lp_position_key = accounts.writable(5)?;
+
+    let mut pool = load_pool(ctx, program_id, &pool_key)?;
+    let (expected_src_lp_position, _) = Pubkey::find_program_address(
+        &[b"lp_position", pool_key.as_ref(), src.as_ref()],
+        program_id,
+    );
+    if src_lp_position_key != expected_src_lp_position {
+        return Err(ProcessorError::InvalidSeeds(src_lp_position_key));
+    }
+    if dst_lp_position_key == src_lp_position_key {
+        return Err(ProcessorError::InvalidAccountData(dst_lp_position_key));
+    }
+    let mut src_lp_position = load_lp_position(ctx, program_id, &src_lp_position_key)?;
+    let mut dst_lp_position = load_lp_position(ctx, program_id, &dst_lp_position_key)?;
+
+    let window = slot_window(ctx.slot());
+    vest_lp_shares(&mut src_lp_position, &pool.amm, window);
+    vest_lp_shares(&mut dst_lp_position, &pool.amm, window);
+    if src_lp_position.pending_shares_to_vest.1 > 0 {
+        return Err(PlasmaStateError::VestingPeriodNotOver.into());
+    }
+    settle_lp_fees(&mut src_lp_position, &pool.amm)?;
+    settle_lp_fees(&mut dst_lp_position, &pool.amm)?;
+
+    let lp_shares = src_lp_position.lp_shares;
+    let withdrawable_lp_shares = src_lp_position.withdrawable_lp_shares;
+    dst_lp_position.lp_shares += lp_shares;
+    dst_lp_position.withdrawable_lp_shares += withdrawable_lp_shares;
+    src_lp_position.lp_shares = 0;
+    src_lp_position.withdrawable_lp_shares = 0;
+
+    pool.header.sequence_number += 1;
+    store(ctx, &src_lp_position_key, &src_lp_position)?;
+    store(ctx, &dst_lp_position_key, &dst_lp_position)?;
+    store(ctx, &pool_key, &pool)
+}
+
+/// Runs a swap against `amm` and enforces the slippage limit of `params`
+pub(crate) fn execute_swap(
+    amm: &mut Amm,
+    window: SlotWindow,
+    params: SwapParams,
+) -> Result<SwapResult, ProcessorError> {
+    let result = params.execute(amm, window)?;
+    let (amount_in, amount_out) = match params.side {
+        Side::Buy => (
+            result.quote_amount_to_transfer,
+            result.base_amount_to_transfer,
+        ),
+        Side::Sell => (
+            result.base_amount_to_transfer,
+            result.quote_amount_to_transfer,
+        ),
+    };
+    match params.swap_type {
+        SwapType::ExactIn { min_amount_out, .. } => {
+            check_min_amount_out(min_amount_out, amount_out)?
+        }
+        SwapType::ExactOut { max_amount_in, .. } => check_max_amount_in(max_amount_in, amount_in)?,
+    }
+    Ok(result)
+}
+
+fn check_min_amount_out(limit: u64, actual: u64) -> Result<(), ProcessorError> {
+    if actual < limit {
+        return Err(ProcessorError::SlippageExceeded { limit, actual });
+    }
+    Ok(())
+}
+
+fn check_max_amount_in(limit: u64, actual: u64) -> Result<(), ProcessorError> {
+    if actual > limit {
+        return Err(ProcessorError::SlippageExceeded { limit, actual });
+    }
+    Ok(())
+}
+
+fn process_swap(
+    ctx: &mut impl ProcessorContext,
+    program_id: &Pubkey,
+    accounts: &Accounts,
+    params: SwapParams,
+) -> Result<(), ProcessorError> {
+    let pool_key = accounts.writable(2)?;
+    let trader = accounts.signer(3)?;
+    let base_account = accounts.writable(4)?;
+    let quote_account = accounts.writable(5)?;
+    accounts.program(8, &spl_token::ID)?;
+
+    let mut pool = load_pool(ctx, program_id, &pool_key)?;
+    check_vaults(accounts, &pool.header, 6)?;
+    load_token_account(ctx, &base_account, &pool.header.base_params.mint_key)?;
+    load_token_account(ctx, &quote_account, &pool.header.quote_params.mint_key)?;
+
+    let window = slot_window(ctx.slot());
+    let result = execute_swap(&mut pool.amm, window, params)?;
+
+    let base_params = pool.header.base_params;
+    let quote_params = pool.header.quote_params;
+    let base_bump = [base_params.vault_bump as u8];
+    let quote_bump = [quote_params.vault_bump as u8];
+    let base_vault_seeds = vault_seeds(&pool_key, &base_params, &base_bump);
+    let quote_vault_seeds = vault_seeds(&pool_key, &quote_params, &quote_bump);
+    match params.side {
+        Side::Buy => {
+            ctx.transfer_tokens(
+                &quote_account,
+                &quote_params.vault_key,
+                &trader,
+                result.quote_amount_to_transfer,
+                None,
+            )?;
+            ctx.transfer_tokens(
+                &base_params.vault_key,
+                &base_account,
+                &base_params.vault_key,
+                result.base_amount_to_transfer,
+                Some(&base_vault_seeds),
+            )?;
+        }
+        Side::Sell => {
+            ctx.transfer_tokens(
+                &base_account,
+                &base_params.vault_key,
+                &trader,
+                result.base_amount_to_transfer,
+                None,
+            )?;
+            ctx.transfer_tokens(
+                &quote_params.vault_key,
+                &quote_account,
+                &quote_params.vault_key,
+                result.quote_amount_to_transfer,
+                Some(&quote_vault_seeds),
+            )?;
+        }
+    }
+
+    pool.header.sequence_number += 1;
+    pool.header.swap_sequence_number += 1;
+    store(ctx, &pool_key, &pool)
+}
@@ -0,0 +1,409 @@
+use borsh::BorshSerialize;
+use solana_account::Account;
+use solana_program::{
+    account_info::AccountInfo,
+    clock::Clock,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    msg,
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    sysvar::Sysvar,
+};
+use solana_program_test::{ProgramTest, processor};
+use solana_system_interface::instruction as system_instruction;
+
+use crate::{
+    PoolAccount,
+    plasma::{
+        ID, InitializePoolParams, LpPosition, POOL_DISCRIMINATOR, PlasmaStateError, PoolHeader,
+        ProtocolFeeRecipients, SLOTS_PER_WINDOW, TokenParams, get_lp_position_address,
+        get_vault_address,
+        plasma_amm::{Amm, BPS_BASE},
+        processor::{self, ProcessorContext, ProcessorError},
+        spl_token,
+        token::{Mint, TOKEN_ACCOUNT_LEN, TokenAccount},
+    },
+};
+
+const SPL_TOKEN_TRANSFER: u8 = 3;
+const SPL_TOKEN_INITIALIZE_ACCOUNT_3: u8 = 18;
+
+/// Native entrypoint for the Plasma instruction set.
+///
+/// Runs the same processor as [`crate::plasma::reference::ReferenceRuntime`], performing
+/// account creation and token transfers through CPIs to the system and SPL token programs.
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let metas = accounts
+        .iter()
+        .map(|info| AccountMeta {
+            pubkey: *info.key,
+            is_signer: info.is_signer,
+            is_writable: info.is_writable,
+        })
+        .collect::<Vec<_>>();
+    let mut ctx = NativeContext {
+        slot: Clock::get()?.slot,
+        accounts,
+    };
+    processor::process_instruction(&mut ctx, program_id, &metas, data).map_err(|error| {
+        msg!("{}", error);
+        error.into()
+    })
+}
+
+struct NativeContext<'a, 'info> {
+    slot: u64,
+    accounts: &'a [AccountInfo<'info>],
+}
+
+impl<'info> NativeContext<'_, 'info> {
+    fn info(&self, key: &Pubkey) -> Result<&AccountInfo<'info>, ProcessorError> {
+        self.accounts
+            .iter()
+            .find(|info| info.key == key)
+            .ok_or(ProcessorError::AccountNotFound(*key))
+    }
+
+    fn invoke(&self, instruction: &Instruction, seeds: &[&[&[u8]]]) -> Result<(), ProcessorError> {
+        invoke_signed(instruction, self.accounts, seeds).map_err(ProcessorError::from)
+    }
+}
+
+impl ProcessorContext for NativeContext<'_, '_> {
+    fn slot(&self) -> u64 {
+        self.slot
+    }
+
+    fn account(&self, key: &Pubkey) -> Option<Account> {
+        let info = self.info(key).ok()?;
+        Some(Account {
+            lamports: info.lamports(),
+            data: info.try_borrow_data().ok()?.to_vec(),
+            owner: *info.owner,
+            executable: info.executable,
+            rent_epoch: info.rent_epoch,
+        })
+    }
+
+    fn set_data(&mut self, key: &Pubkey, data: &[u8]) -> Result<(), ProcessorError> {
+        let info = self.info(key)?;
+        if *info.owner != ID {
+            return Err(ProcessorError::IllegalOwner(*key));
+        }
+        let mut account_data = info.try_borrow_mut_data()?;
+        if account_data.len() != data.len() {
+            return Err(ProcessorError::InvalidAccountData(*key));
+        }
+        account_data.copy_from_slice(data);
+        Ok(())
+    }
+
+    fn create_account(
+        &mut self,
+        payer: &Pubkey,
+        key: &Pubkey,
+        space: u64,
+        owner: &Pubkey,
+        seeds: &[&[u8]],
+    ) -> Result<(), ProcessorError> {
+        let lamports = Rent::get()?.minimum_balance(space as usize);
+        self.invoke(
+            &system_instruction::create_account(payer, key, lamports, space, owner),
+            &[seeds],
+        )
+    }
+
+    fn create_token_account(
+        &mut self,
+        payer: &Pubkey,
+        key: &Pubkey,
+        mint: &Pubkey,
+        seeds: &[&[u8]],
+    ) -> Result<(), ProcessorError> {
+        self.create_account(payer, key, TOKEN_ACCOUNT_LEN as u64, &spl_token::ID, seeds)?;
+        let mut data = vec![SPL_TOKEN_INITIALIZE_ACCOUNT_3];
+        data.extend_from_slice(key.as_ref());
+        self.invoke(
+            &Instruction {
+                program_id: spl_token::ID,
+                accounts: vec![
+                    AccountMeta::new(*key, false),
+                    AccountMeta::new_readonly(*mint, false),
+                ],
+                data,
+            },
+            &[],
+        )
+    }
+
+    fn transfer_tokens(
+        &mut self,
+        source: &Pubkey,
+        destination: &Pubkey,
+        authority: &Pubkey,
+        amount: u64,
+        authority_seeds: Option<&[&[u8]]>,
+    ) -> Result<(), ProcessorError> {
+        let mut data = vec![SPL_TOKEN_TRANSFER];
+        data.extend_from_slice(&amount.to_le_bytes());
+        let instruction = Instruction {
+            program_id: spl_token::ID,
+            accounts: vec![
+                AccountMeta::new(*source, false),
+                AccountMeta::new(*destination, false),
+                AccountMeta::new_readonly(*authority, true),
+            ],
+            data,
+        };
+        match authority_seeds {
+            Some(seeds) => self.invoke(&instruction, &[seeds]),
+            None => self.invoke(&instruction, &[]),
+        }
+    }
+}
+
+/// A `ProgramTest` with the native Plasma processor registered under [`ID`]
+pub fn plasma_program_test() -> ProgramTest {
+    let mut program_test = ProgramTest::default();
+    program_test.prefer_bpf(false);
+    program_test.add_program("plasma", ID, processor!(process_instruction));
+    program_test
+}
+
+fn add_spl_token_account(program_test: &mut ProgramTest, key: Pubkey, data: Vec<u8>) {
+    program_test.add_account(
+        key,
+        Account {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner: spl_token::ID,
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+}
+
+/// Adds a mint with no mint or freeze authority. The supply is not tracked.
+pub fn add_mint(program_test: &mut ProgramTest, decimals: u8) -> Pubkey {
+    let key = Pubkey::new_unique();
+    let mint = Mint {
+        mint_authority: None,
+        supply: 0,
+        decimals,
+    };
+    add_spl_token_account(program_test, key, mint.pack());
+    key
+}
+
+/// Adds a token account for `mint` owned by `owner` holding `amount`
+pub fn add_token_account(
+    program_test: &mut ProgramTest,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    amount: u64,
+) -> Pubkey {
+    let key = Pubkey::new_unique();
+    add_token_account_at(program_test, key, mint, owner, amount);
+    key
+}
+
+fn add_token_account_at(
+    program_test: &mut ProgramTest,
+    key: Pubkey,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    amount: u64,
+) {
+    let token_account = TokenAccount {
+        mint: *mint,
+        owner: *owner,
+        amount,
+    };
+    add_spl_token_account(program_test, key, token_account.pack());
+}
+
+/// Adds the vault of `pool_key` for `mint` holding `amount`, returning its address and bump
+pub fn add_vault(
+    program_test: &mut ProgramTest,
+    pool_key: &Pubkey,
+    mint: &Pubkey,
+    amount: u64,
+) -> (Pubkey, u8) {
+    let (vault_key, vault_bump) = get_vault_address(&ID, pool_key, mint);
+    add_token_account_at(program_test, vault_key, mint, &vault_key, amount);
+    (vault_key, vault_bump)
+}
+
+/// Keys of a pool written directly into the test genesis by [`add_seeded_pool`]
+#[derive(Debug, Clone, Copy)]
+pub struct SeededPool {
+    pub pool_key: Pubkey,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub base_vault: Pubkey,
+    pub quote_vault: Pubkey,
+    /// Owner of the LP position holding the initial shares
+    pub lp_owner: Pubkey,
+    pub lp_shares: u64,
+}
+
+/// Adds new base and quote mints, a pool holding `base_amount` and `quote_amount` in funded
+/// vaults, and a fully vested LP position of `lp_owner` for the initial shares.
+///
+/// The state matches a pool launched with `initialize_pool_with_liquidity` in slot window 0,
+/// without spending a transaction on it.
+#[allow(clippy::too_many_arguments)]
+pub fn add_seeded_pool(
+    program_test: &mut ProgramTest,
+    lp_owner: &Pubkey,
+    base_decimals: u8,
+    quote_decimals: u8,
+    params: InitializePoolParams,
+    base_amount: u64,
+    quote_amount: u64,
+) -> Result<SeededPool, PlasmaStateError> {
+    if params.lp_fee_in_bps >= BPS_BASE as u64 || params.protocol_fee_allocation_in_pct > 100 {
+        return Err(PlasmaStateError::UnexpectedArgument);
+    }
+    let pool_key = Pubkey::new_unique();
+    let base_mint = add_mint(program_test, base_decimals);
+    let quote_mint = add_mint(program_test, quote_decimals);
+
+    let mut amm = Amm::new(
+        params.lp_fee_in_bps as u32,
+        params.protocol_fee_allocation_in_pct as u32,
+        params.num_slots_to_vest_lp_shares.unwrap_or(0) / SLOTS_PER_WINDOW,
+        0,
+    );
+    let (base_amount_deposited, quote_amount_deposited, lp_shares) = amm.mint(
+        0,
+        base_amount,
+        quote_amount,
+        Some(Amm::initial_lp_shares(base_amount, quote_amount)),
+    )?;
+    let (base_vault, base_vault_bump) =
+        add_vault(program_test, &pool_key, &base_mint, base_amount_deposited);
+    let (quote_vault, quote_vault_bump) =
+        add_vault(program_test, &pool_key, &quote_mint, quote_amount_deposited);
+
+    let mut fee_recipients = ProtocolFeeRecipients::default();
+    for (recipient, recipient_params) in fee_recipients
+        .recipients
+        .iter_mut()
+        .zip(params.fee_recipients_params.iter())
+    {
+        recipient.recipient = recipient_params.recipient;
+        recipient.shares = recipient_params.shares;
+    }
+    let pool = PoolAccount {
+        header: PoolHeader {
+            discriminator: POOL_DISCRIMINATOR,
+            sequence_number: 0,
+            base_params: TokenParams {
+                decimals: base_decimals as u32,
+                vault_bump: base_vault_bump as u32,
+                mint_key: base_mint,
+                vault_key: base_vault,
+            },
+            quote_params: TokenParams {
+                decimals: quote_decimals as u32,
+                vault_bump: quote_vault_bump as u32,
+                mint_key: quote_mint,
+                vault_key: quote_vault,
+            },
+            fee_recipients,
+            swap_sequence_number: 0,
+            padding: [0; 12],
+        },
+        amm,
+    };
+    let lp_position = LpPosition {
+        reward_factor_snapshot: pool.amm.reward_factor.to_bits(),
+        lp_shares,
+        withdrawable_lp_shares: lp_shares,
+        uncollected_fees: 0,
+        collected_fees: 0,
+        pending_shares_to_vest: (0, 0),
+    };
+    add_program_account(program_test, pool_key, &pool);
+    let (lp_position_key, _) = get_lp_position_address(&ID, &pool_key, lp_owner);
+    add_program_account(program_test, lp_position_key, &lp_position);
+
+    Ok(SeededPool {
+        pool_key,
+        base_mint,
+        quote_mint,
+        base_vault,
+        quote_vault,
+        lp_owner: *lp_owner,
+        lp_shares,
+    })
+}
+
+fn add_program_account<T: BorshSerialize>(program_test: &mut ProgramTest, key: Pubkey, value: &T) {
+    let data = borsh::to_vec(value).expect("Plasma accounts always serialize");
+    program_test.add_account(
+        key,
+        Account {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner: ID,
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+}
+
+impl From<ProcessorError> for ProgramError {
+    fn from(error: ProcessorError) -> Self {
+        match error {
+            ProcessorError::State(error) => ProgramError::Custom(state_error_code(&error)),
+            ProcessorError::InvalidInstructionData => ProgramError::InvalidInstructionData,
+            ProcessorError::NotEnoughAccountKeys => ProgramError::NotEnoughAccountKeys,
+            ProcessorError::IncorrectProgramId(_) => ProgramError::IncorrectProgramId,
+            ProcessorError::MissingRequiredSignature(_) => ProgramError::MissingRequiredSignature,
+            ProcessorError::AccountNotWritable(_) => ProgramError::InvalidArgument,
+            ProcessorError::InvalidSeeds(_) => ProgramError::InvalidSeeds,
+            ProcessorError::AccountNotFound(_) => ProgramError::NotEnoughAccountKeys,
+            ProcessorError::AccountAlreadyInitialized(_) => ProgramError::AccountAlreadyInitialized,
+            ProcessorError::InvalidAccountData(_) => ProgramError::InvalidAccountData,
+            ProcessorError::IllegalOwner(_) => ProgramError::IllegalOwner,
+            ProcessorError::InsufficientFunds(_) => ProgramError::InsufficientFunds,
+            ProcessorError::SlippageExceeded { .. } => ProgramError::Custom(SLIPPAGE_EXCEEDED_CODE),
+            ProcessorError::Program(error) => error,
+        }
+    }
+}
+
+/// Custom error code returned when a swap violates its slippage limit
+pub const SLIPPAGE_EXCEEDED_CODE: u32 = 100;
+
+/// Custom error code returned for a [`PlasmaStateError`], in declaration order
+pub fn state_error_code(error: &PlasmaStateError) -> u32 {
+    match error {
+        PlasmaStateError::InvariantViolation(..) => 0,
+        PlasmaStateError::MismatchedFees(..) => 1,
+        PlasmaStateError::UninitializedPool => 2,
+        PlasmaStateError::SwapAmountMismatch => 3,
+        PlasmaStateError::Overflow => 4,
+        PlasmaStateError::Underflow => 5,
+        PlasmaStateError::UnexpectedArgument => 6,
+        PlasmaStateError::MissingExpectedArgument => 7,
+        PlasmaStateError::BelowMinimumLpSharesRequired => 8,
+        PlasmaStateError::BelowMinimumWithdrawaRequired { .. } => 9,
+        PlasmaStateError::VestingPeriodNotOver => 10,
+        PlasmaStateError::IncorrectProtocolFeeRecipient => 11,
+        PlasmaStateError::TooManyShares => 12,
+        PlasmaStateError::SwapExactOutTooLarge => 13,
+        PlasmaStateError::SwapExactInTooLarge => 14,
+        PlasmaStateError::SwapOutputGreaterThanOrEqualToReserves(..) => 15,
+        PlasmaStateError::WithdrawalExceedsReserves { .. } => 16,
+    }
+}
@@ -0,0 +1,343 @@
+use std::collections::HashMap;
+
+use solana_account::Account;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_program,
+};
+use solana_sdk::compute_budget;
+use solana_system_interface::instruction::SystemInstruction;
+
+use crate::{
+    PoolAccount,
+    plasma::{
+        ID, LpPosition, get_lp_position_address,
+        processor::{self, ProcessorContext, ProcessorError},
+        spl_token,
+        token::{Mint, TokenAccount},
+    },
+};
+
+/// An in-memory model of a cluster running the Plasma program.
+///
+/// Executes the instructions produced by the builders in `plasma_utils` with the same `Amm`
+/// math as the program, modelling SPL token balances, PDA checks and signer checks, so client
+/// flows can be tested without a validator. System program `create_account` and `transfer`
+/// are supported as well, since they precede pool initialization, and `ComputeBudget`
+/// instructions are accepted and ignored.
+#[derive(Debug, Clone, Default)]
+pub struct ReferenceRuntime {
+    accounts: HashMap<Pubkey, Account>,
+    slot: u64,
+    rent: Rent,
+}
+
+impl ReferenceRuntime {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn slot(&self) -> u64 {
+        self.slot
+    }
+
+    pub fn set_slot(&mut self, slot: u64) {
+        self.slot = slot;
+    }
+
+    pub fn advance_slots(&mut self, slots: u64) {
+        self.slot += slots;
+    }
+
+    pub fn rent(&self) -> &Rent {
+        &self.rent
+    }
+
+    pub fn get_account(&self, key: &Pubkey) -> Option<&Account> {
+        self.accounts.get(key)
+    }
+
+    pub fn set_account(&mut self, key: Pubkey, account: Account) {
+        self.accounts.insert(key, account);
+    }
+
+    pub fn airdrop(&mut self, key: &Pubkey, lamports: u64) {
+        self.accounts
+            .entry(*key)
+            .or_insert_with(|| Account::new(0, 0, &system_program::ID))
+            .lamports += lamports;
+    }
+
+    pub fn create_mint(&mut self, key: Pubkey, decimals: u8) {
+        let mint = Mint {
+            mint_authority: None,
+            supply: 0,
+            decimals,
+        };
+        self.set_spl_token_account(key, mint.pack());
+    }
+
+    pub fn create_token_account(&mut self, key: Pubkey, mint: Pubkey, owner: Pubkey, amount: u64) {
+        let token_account = TokenAccount {
+            mint,
+            owner,
+            amount,
+        };
+        self.set_spl_token_account(key, token_account.pack());
+    }
+
+    fn set_spl_token_account(&mut self, key: Pubkey, data: Vec<u8>) {
+        let account = Account {
+            lamports: self.rent.minimum_balance(data.len()),
+            data,
+            owner: spl_token::ID,
+            executable: false,
+            rent_epoch: 0,
+        };
+        self.accounts.insert(key, account);
+    }
+
+    pub fn token_balance(&self, key: &Pubkey) -> Option<u64> {
+        let account = self.accounts.get(key)?;
+        TokenAccount::unpack(&account.data).map(|token_account| token_account.amount)
+    }
+
+    pub fn pool(&self, key: &Pubkey) -> Option<PoolAccount> {
+        processor::load_pool(self, &ID, key).ok()
+    }
+
+    pub fn lp_position(&self, pool_key: &Pubkey, owner: &Pubkey) -> Option<LpPosition> {
+        let (lp_position_key, _) = get_lp_position_address(&ID, pool_key, owner);
+        let account = self.accounts.get(&lp_position_key)?;
+        if account.owner != ID {
+            return None;
+        }
+        LpPosition::decode(&account.data)
+    }
+
+    /// Executes `instructions` atomically: if any instruction fails, no account is modified.
+    ///
+    /// Every account flagged as a signer must be in `signers`.
+    pub fn process_transaction(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[Pubkey],
+    ) -> Result<(), ProcessorError> {
+        let accounts = self.accounts.clone();
+        let result = instructions
+            .iter()
+            .try_for_each(|instruction| self.process_instruction(instruction, signers));
+        if result.is_err() {
+            self.accounts = accounts;
+        }
+        result
+    }
+
+    fn process_instruction(
+        &mut self,
+        instruction: &Instruction,
+        signers: &[Pubkey],
+    ) -> Result<(), ProcessorError> {
+        if let Some(meta) = instruction
+            .accounts
+            .iter()
+            .find(|meta| meta.is_signer && !signers.contains(&meta.pubkey))
+        {
+            return Err(ProcessorError::MissingRequiredSignature(meta.pubkey));
+        }
+        match instruction.program_id {
+            ID => processor::process_instruction(
+                self,
+                &instruction.program_id,
+                &instruction.accounts,
+                &instruction.data,
+            ),
+            system_program::ID => self.process_system_instruction(instruction),
+            // Compute is not metered, so budget requests have no effect
+            compute_budget::ID => Ok(()),
+            program_id => Err(ProcessorError::IncorrectProgramId(program_id)),
+        }
+    }
+
+    fn process_system_instruction(
+        &mut self,
+        instruction: &Instruction,
+    ) -> Result<(), ProcessorError> {
+        let system_instruction: SystemInstruction = bincode::deserialize(&instruction.data)
+            .map_err(|_| ProcessorError::InvalidInstructionData)?;
+        let signer = |index: usize| -> Result<Pubkey, ProcessorError> {
+            let meta: &AccountMeta = instruction
+                .accounts
+                .get(index)
+                .ok_or(ProcessorError::NotEnoughAccountKeys)?;
+            if !meta.is_signer {
+                return Err(ProcessorError::MissingRequiredSignature(meta.pubkey));
+            }
+            Ok(meta.pubkey)
+        };
+        match system_instruction {
+            SystemInstruction::CreateAccount {
+                lamports,
+                space,
+                owner,
+            } => {
+                let from = signer(0)?;
+                let to = signer(1)?;
+                if self.accounts.get(&to).is_some_and(|account| {
+                    account.owner != system_program::ID || !account.data.is_empty()
+                }) {
+                    return Err(ProcessorError::AccountAlreadyInitialized(to));
+                }
+                self.debit(&from, lamports)?;
+                let account = self.credit(&to, lamports)?;
+                account.data = vec![0; space as usize];
+                account.owner = owner;
+                Ok(())
+            }
+            SystemInstruction::Transfer { lamports } => {
+                let from = signer(0)?;
+                let to = instruction
+                    .accounts
+                    .get(1)
+                    .ok_or(ProcessorError::NotEnoughAccountKeys)?
+                    .pubkey;
+                self.debit(&from, lamports)?;
+                self.credit(&to, lamports)?;
+                Ok(())
+            }
+            _ => Err(ProcessorError::InvalidInstructionData),
+        }
+    }
+
+    fn debit(&mut self, key: &Pubkey, lamports: u64) -> Result<(), ProcessorError> {
+        let account = self
+            .accounts
+            .get_mut(key)
+            .ok_or(ProcessorError::AccountNotFound(*key))?;
+        account.lamports = account
+            .lamports
+            .checked_sub(lamports)
+            .ok_or(ProcessorError::InsufficientFunds(*key))?;
+        Ok(())
+    }
+
+    fn credit(&mut self, key: &Pubkey, lamports: u64) -> Result<&mut Account, ProcessorError> {
+        let account = self
+            .accounts
+            .entry(*key)
+            .or_insert_with(|| Account::new(0, 0, &system_program::ID));
+        account.lamports = account
+            .lamports
+            .checked_add(lamports)
+            .ok_or(ProcessorError::Program(ProgramError::ArithmeticOverflow))?;
+        Ok(account)
+    }
+
+    fn load_token_account(&mut self, key: &Pubkey) -> Result<TokenAccount, ProcessorError> {
+        let account = self
+            .accounts
+            .get(key)
+            .ok_or(ProcessorError::AccountNotFound(*key))?;
+        if account.owner != spl_token::ID {
+            return Err(ProcessorError::IllegalOwner(*key));
+        }
+        TokenAccount::unpack(&account.data).ok_or(ProcessorError::InvalidAccountData(*key))
+    }
+}
+
+impl ProcessorContext for ReferenceRuntime {
+    fn slot(&self) -> u64 {
+        self.slot
+    }
+
+    fn account(&self, key: &Pubkey) -> Option<Account> {
+        self.accounts.get(key).cloned()
+    }
+
+    fn set_data(&mut self, key: &Pubkey, data: &[u8]) -> Result<(), ProcessorError> {
+        let account = self
+            .accounts
+            .get_mut(key)
+            .ok_or(ProcessorError::AccountNotFound(*key))?;
+        if account.owner != ID {
+            return Err(ProcessorError::IllegalOwner(*key));
+        }
+        if account.data.len() != data.len() {
+            return Err(ProcessorError::InvalidAccountData(*key));
+        }
+        account.data.copy_from_slice(data);
+        Ok(())
+    }
+
+    fn create_account(
+        &mut self,
+        payer: &Pubkey,
+        key: &Pubkey,
+        space: u64,
+        owner: &Pubkey,
+        _seeds: &[&[u8]],
+    ) -> Result<(), ProcessorError> {
+        let lamports = self.rent.minimum_balance(space as usize);
+        self.debit(payer, lamports)?;
+        let account = self.credit(key, lamports)?;
+        account.data = vec![0; space as usize];
+        account.owner = *owner;
+        Ok(())
+    }
+
+    fn create_token_account(
+        &mut self,
+        payer: &Pubkey,
+        key: &Pubkey,
+        mint: &Pubkey,
+        _seeds: &[&[u8]],
+    ) -> Result<(), ProcessorError> {
+        if self.accounts.contains_key(key) {
+            return Err(ProcessorError::AccountAlreadyInitialized(*key));
+        }
+        let token_account = TokenAccount {
+            mint: *mint,
+            owner: *key,
+            amount: 0,
+        };
+        let data = token_account.pack();
+        self.debit(payer, self.rent.minimum_balance(data.len()))?;
+        self.set_spl_token_account(*key, data);
+        Ok(())
+    }
+
+    fn transfer_tokens(
+        &mut self,
+        source: &Pubkey,
+        destination: &Pubkey,
+        authority: &Pubkey,
+        amount: u64,
+        _authority_seeds: Option<&[&[u8]]>,
+    ) -> Result<(), ProcessorError> {
+        let mut source_account = self.load_token_account(source)?;
+        let mut destination_account = self.load_token_account(destination)?;
+        if source_account.owner != *authority {
+            return Err(ProcessorError::IllegalOwner(*source));
+        }
+        if source_account.mint != destination_account.mint {
+            return Err(ProcessorError::InvalidAccountData(*destination));
+        }
+        if source == destination {
+            return Ok(());
+        }
+        source_account.amount = source_account
+            .amount
+            .checked_sub(amount)
+            .ok_or(ProcessorError::InsufficientFunds(*source))?;
+        destination_account.amount = destination_account
+            .amount
+            .checked_add(amount)
+            .ok_or(ProcessorError::Program(ProgramError::ArithmeticOverflow))?;
+        self.set_spl_token_account(*source, source_account.pack());
+        self.set_spl_token_account(*destination, destination_account.pack());
+        Ok(())
+    }
+}
@@ -0,0 +1,253 @@
+use std::fmt::Display;
+
+use crate::{
+    PoolAccount,
+    plasma::{
+        InitializePoolParams, PlasmaInstruction, PlasmaStateError, SLOTS_PER_WINDOW,
+        plasma_amm::Amm, slot_window,
+    },
+};
+
+/// Where a replay first disagreed with the instructions or the snapshots
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DivergenceKind {
+    /// `initialize_pool` was replayed on a pool that already exists
+    UnexpectedInitializePool,
+    /// The replayed `Amm` rejected an instruction that succeeded on-chain
+    Rejected(PlasmaStateError),
+    /// The replayed state differs from the snapshot taken after the instruction
+    SnapshotMismatch {
+        field: &'static str,
+        replayed: i128,
+        snapshot: i128,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// Index of the step whose instruction or snapshot disagreed with the replay
+    pub step_index: usize,
+    pub kind: DivergenceKind,
+}
+
+impl Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "At step {}", self.step_index)?;
+        match &self.kind {
+            DivergenceKind::UnexpectedInitializePool => {
+                write!(f, ": initialize_pool on a pool that already exists")
+            }
+            DivergenceKind::Rejected(error) => {
+                write!(f, ": replay rejected the instruction: {}", error)
+            }
+            DivergenceKind::SnapshotMismatch {
+                field,
+                replayed,
+                snapshot,
+            } => write!(
+                f,
+                ": {} is {} in the replay but {} in the snapshot",
+                field, replayed, snapshot
+            ),
+        }
+    }
+}
+
+/// A Plasma instruction of the replayed pool that succeeded on-chain
+#[derive(Debug, Clone, Copy)]
+pub struct ReplayStep {
+    /// Slot of the transaction that executed the instruction
+    pub slot: u64,
+    /// Instruction data, decoded with [`PlasmaInstruction::unpack`]
+    pub instruction: PlasmaInstruction,
+    /// The pool account after the transaction, if one was captured. When a transaction holds
+    /// several instructions of the pool, attach it to the last of them.
+    pub snapshot: Option<PoolAccount>,
+}
+
+/// Rebuilds the `Amm` of a single pool by re-running the SDK math on the instructions that
+/// succeeded on-chain, in execution order.
+///
+/// The replay only relies on the instruction layout and the pool account layout, so checking it
+/// against account snapshots shows where the SDK's `Amm` and the deployed program drift apart.
+#[derive(Debug, Clone, Copy)]
+pub struct PoolReplay {
+    amm: Amm,
+}
+
+impl PoolReplay {
+    /// Starts from the state `initialize_pool` creates at `slot`
+    pub fn new(params: &InitializePoolParams, slot: u64) -> Self {
+        Self {
+            amm: Amm::new(
+                params.lp_fee_in_bps as u32,
+                params.protocol_fee_allocation_in_pct as u32,
+                params.num_slots_to_vest_lp_shares.unwrap_or(0) / SLOTS_PER_WINDOW,
+                slot_window(slot),
+            ),
+        }
+    }
+
+    /// Starts from a captured pool account, to replay the instructions that followed it
+    pub fn from_snapshot(snapshot: &PoolAccount) -> Self {
+        Self { amm: snapshot.amm }
+    }
+
+    pub fn amm(&self) -> &Amm {
+        &self.amm
+    }
+
+    /// Applies the next instruction of the pool, executed at `slot`. On error the replay is
+    /// left unchanged.
+    pub fn apply(
+        &mut self,
+        slot: u64,
+        instruction: &PlasmaInstruction,
+    ) -> Result<(), DivergenceKind> {
+        let mut amm = self.amm;
+        let window = slot_window(slot);
+        match *instruction {
+            PlasmaInstruction::InitializePool(_) => {
+                return Err(DivergenceKind::UnexpectedInitializePool);
+            }
+            PlasmaInstruction::InitializeLpPosition | PlasmaInstruction::TransferLiquidity => {}
+            PlasmaInstruction::AddLiquidity(params) => {
+                amm.mint(
+                    window,
+                    params.desired_base_amount_in,
+                    params.desired_quote_amount_in,
+                    params.initial_lp_shares,
+                )
+                .map_err(DivergenceKind::Rejected)?;
+            }
+            PlasmaInstruction::RemoveLiquidity { shares } => {
+                amm.burn(window, shares).map_err(DivergenceKind::Rejected)?;
+            }
+            PlasmaInstruction::Swap(params) => {
+                params
+                    .execute(&mut amm, window)
+                    .map_err(DivergenceKind::Rejected)?;
+            }
+        }
+        self.amm = amm;
+        Ok(())
+    }
+
+    /// Compares the replayed state with a snapshot of the pool taken at the same point
+    pub fn check_snapshot(&self, snapshot: &PoolAccount) -> Result<(), DivergenceKind> {
+        let replayed = &self.amm;
+        let amm = &snapshot.amm;
+        let fields: [(&'static str, i128, i128); 12] = [
+            (
+                "fee_in_bps",
+                replayed.fee_in_bps as i128,
+                amm.fee_in_bps as i128,
+            ),
+            (
+                "protocol_allocation_in_pct",
+                replayed.protocol_allocation_in_pct() as i128,
+                amm.protocol_allocation_in_pct() as i128,
+            ),
+            (
+                "lp_vesting_window",
+                replayed.lp_vesting_window as i128,
+                amm.lp_vesting_window as i128,
+            ),
+            (
+                "reward_factor",
+                replayed.reward_factor.to_bits(),
+                amm.reward_factor.to_bits(),
+            ),
+            (
+                "total_lp_shares",
+                replayed.total_lp_shares as i128,
+                amm.total_lp_shares as i128,
+            ),
+            (
+                "slot_snapshot",
+                replayed.get_slot() as i128,
+                amm.get_slot() as i128,
+            ),
+            (
+                "base_reserves_snapshot",
+                replayed.base_reserves_snapshot as i128,
+                amm.base_reserves_snapshot as i128,
+            ),
+            (
+                "quote_reserves_snapshot",
+                replayed.quote_reserves_snapshot as i128,
+                amm.quote_reserves_snapshot as i128,
+            ),
+            (
+                "base_reserves",
+                replayed.base_reserves as i128,
+                amm.base_reserves as i128,
+            ),
+            (
+                "quote_reserves",
+                replayed.quote_reserves as i128,
+                amm.quote_reserves as i128,
+            ),
+            (
+                "cumulative_quote_lp_fees",
+                replayed.cumulative_quote_lp_fees as i128,
+                amm.cumulative_quote_lp_fees as i128,
+            ),
+            (
+                "cumulative_quote_protocol_fees",
+                replayed.cumulative_quote_protocol_fees as i128,
+                amm.cumulative_quote_protocol_fees as i128,
+            ),
+        ];
+        match fields
+            .into_iter()
+            .find(|(_, replayed, snapshot)| replayed != snapshot)
+        {
+            Some((field, replayed, snapshot)) => Err(DivergenceKind::SnapshotMismatch {
+                field,
+                replayed,
+                snapshot,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Outcome of [`verify_replay`]
+#[derive(Debug, Clone)]
+pub struct ReplayReport {
+    /// The state after the last step that was applied
+    pub replay: PoolReplay,
+    pub steps_applied: usize,
+    pub snapshots_checked: usize,
+    /// `None` if every instruction and every snapshot agreed with the replay
+    pub first_divergence: Option<Divergence>,
+}
+
+/// Applies `steps` on top of `replay`, checking the state against the snapshot of every step
+/// that has one, and stops at the first divergence
+pub fn verify_replay(mut replay: PoolReplay, steps: &[ReplayStep]) -> ReplayReport {
+    let mut steps_applied = 0;
+    let mut snapshots_checked = 0;
+    let mut first_divergence = None;
+    for (step_index, step) in steps.iter().enumerate() {
+        let checked = replay.apply(step.slot, &step.instruction).and_then(|()| {
+            steps_applied += 1;
+            step.snapshot.as_ref().map_or(Ok(()), |snapshot| {
+                snapshots_checked += 1;
+                replay.check_snapshot(snapshot)
+            })
+        });
+        if let Err(kind) = checked {
+            first_divergence = Some(Divergence { step_index, kind });
+            break;
+        }
+    }
+
+    ReplayReport {
+        replay,
+        steps_applied,
+        snapshots_checked,
+        first_divergence,
+    }
+}
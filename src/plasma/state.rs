@@ -0,0 +1,100 @@
+//! Layouts of the pool and LP position accounts. They only depend on `Pubkey`, so on-chain
+//! programs can read Plasma accounts without the Solana SDK.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use bytemuck::{Pod, Zeroable};
+use solana_pubkey::Pubkey;
+
+use crate::plasma::{LP_POSITION_LEN, fixed::I80F48, plasma_amm::Amm};
+
+#[derive(Debug, Copy, Clone, Zeroable, Pod, BorshDeserialize, BorshSerialize)]
+#[repr(C)]
+pub struct PoolHeader {
+    pub discriminator: [u8; 8],
+    pub sequence_number: u64,
+    pub base_params: TokenParams,
+    pub quote_params: TokenParams,
+    pub fee_recipients: ProtocolFeeRecipients,
+    pub swap_sequence_number: u64,
+    pub padding: [u64; 12],
+}
+
+#[derive(Debug, Copy, Clone, Zeroable, Pod, BorshDeserialize, BorshSerialize)]
+#[repr(C)]
+pub struct TokenParams {
+    /// Number of decimals for the token (e.g. 9 for SOL, 6 for USDC).
+    pub decimals: u32,
+
+    /// Bump used for generating the PDA for the pool's token vault.
+    pub vault_bump: u32,
+
+    /// Pubkey of the token mint.
+    pub mint_key: Pubkey,
+
+    /// Pubkey of the token vault.
+    pub vault_key: Pubkey,
+}
+
+#[derive(Debug, Default, Copy, Clone, Zeroable, Pod, BorshDeserialize, BorshSerialize)]
+#[repr(C)]
+pub struct ProtocolFeeRecipient {
+    pub recipient: Pubkey,
+    pub shares: u64,
+    pub total_accumulated_quote_fees: u64,
+    pub collected_quote_fees: u64,
+}
+
+#[derive(Debug, Default, Copy, Clone, Zeroable, Pod, BorshDeserialize, BorshSerialize)]
+#[repr(C)]
+pub struct ProtocolFeeRecipients {
+    pub recipients: [ProtocolFeeRecipient; 3],
+    _padding: [u64; 12],
+}
+
+#[derive(Debug, Copy, Clone, BorshDeserialize, BorshSerialize)]
+#[repr(C)]
+pub struct LpPosition {
+    pub(crate) reward_factor_snapshot: i128,
+    pub lp_shares: u64,
+    pub withdrawable_lp_shares: u64,
+    pub(crate) uncollected_fees: u64,
+    pub(crate) collected_fees: u64,
+    pub pending_shares_to_vest: (u64, u64),
+}
+
+impl LpPosition {
+    /// Decodes the data of an LP position account, or `None` if it has the wrong length. The owner
+    /// is checked by the caller.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() != LP_POSITION_LEN as usize {
+            return None;
+        }
+        Self::try_from_slice(data).ok()
+    }
+
+    pub fn reward_factor_snapshot(&self) -> I80F48 {
+        I80F48::from_bits(self.reward_factor_snapshot)
+    }
+
+    pub fn uncollected_fees(&self) -> u64 {
+        self.uncollected_fees
+    }
+
+    pub fn collected_fees(&self) -> u64 {
+        self.collected_fees
+    }
+
+    /// Quote fees accrued since the position's reward factor snapshot that have not yet been
+    /// moved into `uncollected_fees`, or `None` if they overflow
+    pub fn pending_fees(&self, amm: &Amm) -> Option<u64> {
+        let reward_factor_delta = amm
+            .reward_factor
+            .checked_sub(self.reward_factor_snapshot())?;
+        if reward_factor_delta <= I80F48::ZERO {
+            return Some(0);
+        }
+        reward_factor_delta
+            .checked_mul(I80F48::from_num(self.lp_shares))?
+            .checked_floor()
+    }
+}
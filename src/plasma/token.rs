@@ -0,0 +1,78 @@
+use solana_program::pubkey::Pubkey;
+
+pub const MINT_LEN: usize = 82;
+pub const TOKEN_ACCOUNT_LEN: usize = 165;
+
+const TOKEN_ACCOUNT_STATE_OFFSET: usize = 108;
+const TOKEN_ACCOUNT_STATE_INITIALIZED: u8 = 1;
+const MINT_DECIMALS_OFFSET: usize = 44;
+const MINT_IS_INITIALIZED_OFFSET: usize = 45;
+
+/// The fields of an SPL token account that Plasma cares about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenAccount {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+}
+
+impl TokenAccount {
+    /// Packs the account into the SPL token account layout with no delegate or close authority
+    pub fn pack(&self) -> Vec<u8> {
+        let mut data = vec![0; TOKEN_ACCOUNT_LEN];
+        data[0..32].copy_from_slice(self.mint.as_ref());
+        data[32..64].copy_from_slice(self.owner.as_ref());
+        data[64..72].copy_from_slice(&self.amount.to_le_bytes());
+        data[TOKEN_ACCOUNT_STATE_OFFSET] = TOKEN_ACCOUNT_STATE_INITIALIZED;
+        data
+    }
+
+    pub fn unpack(data: &[u8]) -> Option<Self> {
+        if data.len() != TOKEN_ACCOUNT_LEN || data[TOKEN_ACCOUNT_STATE_OFFSET] == 0 {
+            return None;
+        }
+        Some(Self {
+            mint: Pubkey::try_from(&data[0..32]).ok()?,
+            owner: Pubkey::try_from(&data[32..64]).ok()?,
+            amount: u64::from_le_bytes(data[64..72].try_into().ok()?),
+        })
+    }
+}
+
+/// The fields of an SPL mint that Plasma cares about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mint {
+    pub mint_authority: Option<Pubkey>,
+    pub supply: u64,
+    pub decimals: u8,
+}
+
+impl Mint {
+    /// Packs the mint into the SPL mint layout with no freeze authority
+    pub fn pack(&self) -> Vec<u8> {
+        let mut data = vec![0; MINT_LEN];
+        if let Some(mint_authority) = self.mint_authority {
+            data[0..4].copy_from_slice(&1_u32.to_le_bytes());
+            data[4..36].copy_from_slice(mint_authority.as_ref());
+        }
+        data[36..44].copy_from_slice(&self.supply.to_le_bytes());
+        data[MINT_DECIMALS_OFFSET] = self.decimals;
+        data[MINT_IS_INITIALIZED_OFFSET] = 1;
+        data
+    }
+
+    pub fn unpack(data: &[u8]) -> Option<Self> {
+        if data.len() != MINT_LEN || data[MINT_IS_INITIALIZED_OFFSET] == 0 {
+            return None;
+        }
+        let mint_authority = match u32::from_le_bytes(data[0..4].try_into().ok()?) {
+            0 => None,
+            _ => Some(Pubkey::try_from(&data[4..36]).ok()?),
+        };
+        Some(Self {
+            mint_authority,
+            supply: u64::from_le_bytes(data[36..44].try_into().ok()?),
+            decimals: data[MINT_DECIMALS_OFFSET],
+        })
+    }
+}
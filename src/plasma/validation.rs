@@ -0,0 +1,416 @@
+//! Checks that an instruction addressed to the Plasma program is exactly what the builders in
+//! `plasma_utils` would produce for the keys it declares, so it can be signed without trusting
+//! whoever built it.
+//!
+//! Swaps and liquidity instructions do not carry the mints, so their vaults can only be checked
+//! against the mints of the pool account. The owner of the destination of a liquidity transfer
+//! is not part of the instruction either, so that LP position is reported as is.
+
+use std::fmt::Display;
+
+use solana_program::{instruction::Instruction, pubkey::Pubkey};
+
+use crate::{
+    PoolAccount,
+    plasma::{
+        AddLiquidityParams, ID, InitializePoolParams, PlasmaInstruction, SwapParams, add_liquidity,
+        get_vault_address, initialize_lp_position, initialize_pool, remove_liquidity, swap,
+        transfer_liquidity,
+    },
+};
+
+/// A reason not to sign an instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Issue {
+    /// The instruction targets another program
+    NotPlasmaInstruction(Pubkey),
+    /// The discriminator is unknown or the data does not decode
+    InvalidData,
+    AccountCount {
+        expected: usize,
+        actual: usize,
+    },
+    /// A fixed account, such as the program, the log authority or the token program, is wrong
+    UnexpectedAccount {
+        index: usize,
+        expected: Pubkey,
+        actual: Pubkey,
+    },
+    InvalidVault {
+        index: usize,
+        expected: Pubkey,
+        actual: Pubkey,
+    },
+    InvalidLpPosition {
+        index: usize,
+        expected: Pubkey,
+        actual: Pubkey,
+    },
+    SignerMismatch {
+        index: usize,
+        pubkey: Pubkey,
+        expected: bool,
+    },
+    WritableMismatch {
+        index: usize,
+        pubkey: Pubkey,
+        expected: bool,
+    },
+    /// The vaults of this instruction can only be checked with the pool account
+    MissingPoolAccount,
+    /// The vaults recorded in the pool account are not derived from the declared pool
+    PoolMismatch(Pubkey),
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Issue::NotPlasmaInstruction(program_id) => {
+                write!(f, "Instruction targets program {}", program_id)
+            }
+            Issue::InvalidData => write!(f, "Invalid instruction data"),
+            Issue::AccountCount { expected, actual } => {
+                write!(f, "Expected {} accounts but got {}", expected, actual)
+            }
+            Issue::UnexpectedAccount {
+                index,
+                expected,
+                actual,
+            } => write!(f, "Account {} is {} instead of {}", index, actual, expected),
+            Issue::InvalidVault {
+                index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Vault at account {} is {} instead of {}",
+                index, actual, expected
+            ),
+            Issue::InvalidLpPosition {
+                index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "LP position at account {} is {} instead of {}",
+                index, actual, expected
+            ),
+            Issue::SignerMismatch {
+                index,
+                pubkey,
+                expected,
+            } => write!(
+                f,
+                "Account {} ({}) should {}be a signer",
+                index,
+                pubkey,
+                if *expected { "" } else { "not " }
+            ),
+            Issue::WritableMismatch {
+                index,
+                pubkey,
+                expected,
+            } => write!(
+                f,
+                "Account {} ({}) should {}be writable",
+                index,
+                pubkey,
+                if *expected { "" } else { "not " }
+            ),
+            Issue::MissingPoolAccount => write!(f, "The pool account is required to check vaults"),
+            Issue::PoolMismatch(pool) => {
+                write!(f, "The pool account does not belong to pool {}", pool)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Issue {}
+
+/// A well formed Plasma instruction and the keys it acts on
+#[derive(Debug, Clone, Copy)]
+pub enum ValidatedInstruction {
+    Swap {
+        pool: Pubkey,
+        trader: Pubkey,
+        base_account: Pubkey,
+        quote_account: Pubkey,
+        params: SwapParams,
+    },
+    AddLiquidity {
+        pool: Pubkey,
+        trader: Pubkey,
+        base_account: Pubkey,
+        quote_account: Pubkey,
+        params: AddLiquidityParams,
+    },
+    RemoveLiquidity {
+        pool: Pubkey,
+        trader: Pubkey,
+        base_account: Pubkey,
+        quote_account: Pubkey,
+        shares: u64,
+    },
+    InitializeLpPosition {
+        pool: Pubkey,
+        payer: Pubkey,
+        owner: Pubkey,
+    },
+    InitializePool {
+        pool: Pubkey,
+        creator: Pubkey,
+        base_mint: Pubkey,
+        quote_mint: Pubkey,
+        params: InitializePoolParams,
+    },
+    TransferLiquidity {
+        pool: Pubkey,
+        source_owner: Pubkey,
+        destination_lp_position: Pubkey,
+    },
+}
+
+impl ValidatedInstruction {
+    pub fn pool(&self) -> Pubkey {
+        match self {
+            ValidatedInstruction::Swap { pool, .. }
+            | ValidatedInstruction::AddLiquidity { pool, .. }
+            | ValidatedInstruction::RemoveLiquidity { pool, .. }
+            | ValidatedInstruction::InitializeLpPosition { pool, .. }
+            | ValidatedInstruction::InitializePool { pool, .. }
+            | ValidatedInstruction::TransferLiquidity { pool, .. } => *pool,
+        }
+    }
+
+    /// The account that has to sign the instruction
+    pub fn signer(&self) -> Pubkey {
+        match self {
+            ValidatedInstruction::Swap { trader, .. }
+            | ValidatedInstruction::AddLiquidity { trader, .. }
+            | ValidatedInstruction::RemoveLiquidity { trader, .. } => *trader,
+            ValidatedInstruction::InitializeLpPosition { payer, .. } => *payer,
+            ValidatedInstruction::InitializePool { creator, .. } => *creator,
+            ValidatedInstruction::TransferLiquidity { source_owner, .. } => *source_owner,
+        }
+    }
+}
+
+/// Number of accounts of each instruction, as produced by its builder
+const fn account_count(instruction: &PlasmaInstruction) -> usize {
+    match instruction {
+        PlasmaInstruction::Swap(_) => 9,
+        PlasmaInstruction::AddLiquidity(_) | PlasmaInstruction::RemoveLiquidity { .. } => 10,
+        PlasmaInstruction::InitializeLpPosition => 7,
+        PlasmaInstruction::InitializePool(_) => 10,
+        PlasmaInstruction::TransferLiquidity => 6,
+    }
+}
+
+/// Validates `instruction` against the canonical builder. `pool` is the account of the pool the
+/// instruction declares, required to check the vaults of swaps and liquidity instructions.
+///
+/// Returns every issue found, or the decoded instruction if there are none.
+pub fn validate_instruction(
+    instruction: &Instruction,
+    pool: Option<&PoolAccount>,
+) -> Result<ValidatedInstruction, Vec<Issue>> {
+    if instruction.program_id != ID {
+        return Err(vec![Issue::NotPlasmaInstruction(instruction.program_id)]);
+    }
+    let decoded = PlasmaInstruction::unpack(&instruction.data).ok_or(vec![Issue::InvalidData])?;
+    let expected = account_count(&decoded);
+    if instruction.accounts.len() != expected {
+        return Err(vec![Issue::AccountCount {
+            expected,
+            actual: instruction.accounts.len(),
+        }]);
+    }
+    let key = |index: usize| instruction.accounts[index].pubkey;
+    let pool_key = key(2);
+
+    let mut issues = vec![];
+    // Mints of the pool, from the pool account unless the instruction declares them
+    let mints = match decoded {
+        PlasmaInstruction::InitializePool(_) => Some((key(4), key(5))),
+        PlasmaInstruction::Swap(_)
+        | PlasmaInstruction::AddLiquidity(_)
+        | PlasmaInstruction::RemoveLiquidity { .. } => match pool {
+            Some(pool) => {
+                let (base, quote) = (&pool.header.base_params, &pool.header.quote_params);
+                if get_vault_address(&ID, &pool_key, &base.mint_key).0 != base.vault_key
+                    || get_vault_address(&ID, &pool_key, &quote.mint_key).0 != quote.vault_key
+                {
+                    issues.push(Issue::PoolMismatch(pool_key));
+                }
+                Some((base.mint_key, quote.mint_key))
+            }
+            None => {
+                issues.push(Issue::MissingPoolAccount);
+                None
+            }
+        },
+        _ => None,
+    };
+    let (base_mint, quote_mint) = mints.unwrap_or_default();
+
+    // Rebuild the instruction from the declared keys. `unchecked` are the accounts whose keys
+    // cannot be derived, `vaults` and `lp_positions` the derived ones.
+    let (canonical, validated, unchecked, vaults, lp_positions): (
+        _,
+        _,
+        &[usize],
+        &[usize],
+        &[usize],
+    ) = match decoded {
+        PlasmaInstruction::Swap(params) => (
+            swap(
+                &pool_key,
+                &key(3),
+                &base_mint,
+                &quote_mint,
+                &key(4),
+                &key(5),
+                params,
+            ),
+            ValidatedInstruction::Swap {
+                pool: pool_key,
+                trader: key(3),
+                base_account: key(4),
+                quote_account: key(5),
+                params,
+            },
+            &[],
+            &[6, 7],
+            &[],
+        ),
+        PlasmaInstruction::AddLiquidity(params) => (
+            add_liquidity(
+                &pool_key,
+                &key(3),
+                &base_mint,
+                &key(5),
+                &quote_mint,
+                &key(6),
+                params,
+            ),
+            ValidatedInstruction::AddLiquidity {
+                pool: pool_key,
+                trader: key(3),
+                base_account: key(5),
+                quote_account: key(6),
+                params,
+            },
+            &[],
+            &[7, 8],
+            &[4],
+        ),
+        PlasmaInstruction::RemoveLiquidity { shares } => (
+            remove_liquidity(
+                &pool_key,
+                &key(3),
+                &base_mint,
+                &quote_mint,
+                &key(5),
+                &key(6),
+                shares,
+            ),
+            ValidatedInstruction::RemoveLiquidity {
+                pool: pool_key,
+                trader: key(3),
+                base_account: key(5),
+                quote_account: key(6),
+                shares,
+            },
+            &[],
+            &[7, 8],
+            &[4],
+        ),
+        PlasmaInstruction::InitializeLpPosition => (
+            initialize_lp_position(&pool_key, &key(3), &key(4)),
+            ValidatedInstruction::InitializeLpPosition {
+                pool: pool_key,
+                payer: key(3),
+                owner: key(4),
+            },
+            &[],
+            &[],
+            &[5],
+        ),
+        PlasmaInstruction::InitializePool(params) => (
+            initialize_pool(&pool_key, &key(3), &base_mint, &quote_mint, params),
+            ValidatedInstruction::InitializePool {
+                pool: pool_key,
+                creator: key(3),
+                base_mint,
+                quote_mint,
+                params,
+            },
+            &[],
+            &[6, 7],
+            &[],
+        ),
+        PlasmaInstruction::TransferLiquidity => (
+            transfer_liquidity(&pool_key, &key(3), &Pubkey::default()),
+            ValidatedInstruction::TransferLiquidity {
+                pool: pool_key,
+                source_owner: key(3),
+                destination_lp_position: key(5),
+            },
+            &[5],
+            &[],
+            &[4],
+        ),
+    };
+
+    for (index, (expected, actual)) in canonical
+        .accounts
+        .iter()
+        .zip(instruction.accounts.iter())
+        .enumerate()
+    {
+        let derivable =
+            !unchecked.contains(&index) && (mints.is_some() || !vaults.contains(&index));
+        if derivable && expected.pubkey != actual.pubkey {
+            let (expected, actual) = (expected.pubkey, actual.pubkey);
+            issues.push(if vaults.contains(&index) {
+                Issue::InvalidVault {
+                    index,
+                    expected,
+                    actual,
+                }
+            } else if lp_positions.contains(&index) {
+                Issue::InvalidLpPosition {
+                    index,
+                    expected,
+                    actual,
+                }
+            } else {
+                Issue::UnexpectedAccount {
+                    index,
+                    expected,
+                    actual,
+                }
+            });
+        }
+        if expected.is_signer != actual.is_signer {
+            issues.push(Issue::SignerMismatch {
+                index,
+                pubkey: actual.pubkey,
+                expected: expected.is_signer,
+            });
+        }
+        if expected.is_writable != actual.is_writable {
+            issues.push(Issue::WritableMismatch {
+                index,
+                pubkey: actual.pubkey,
+                expected: expected.is_writable,
+            });
+        }
+    }
+
+    if issues.is_empty() {
+        Ok(validated)
+    } else {
+        Err(issues)
+    }
+}
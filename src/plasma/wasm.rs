@@ -0,0 +1,126 @@
+//! `wasm-bindgen` bindings to the `Amm` math, so browsers quote with the code the program runs.
+//!
+//! Slots are cluster slots, converted with [`slot_window`] as the program does. Amounts are in the
+//! smallest unit of each token and cross into JavaScript as `bigint`.
+
+use core::{fmt::Display, mem::size_of};
+
+use wasm_bindgen::prelude::*;
+
+use crate::plasma::{
+    POOL_DISCRIMINATOR, POOL_LEN,
+    plasma_amm::{Amm, BurnPreview, MintPreview, Side, SwapResult},
+    slot_window,
+};
+
+/// Offset of the `Amm` in the pool account data, which ends with it
+const AMM_OFFSET: usize = POOL_LEN as usize - size_of::<Amm>();
+
+#[cfg(feature = "state")]
+const _: () = assert!(AMM_OFFSET == crate::plasma::layout::AMM_OFFSET);
+
+/// Errors returned by [`decode_amm`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The data is not `POOL_LEN` bytes long
+    InvalidLength(usize),
+    InvalidDiscriminator,
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            DecodeError::InvalidLength(len) => {
+                write!(f, "Expected {} bytes of pool data, got {}", POOL_LEN, len)
+            }
+            DecodeError::InvalidDiscriminator => write!(f, "Data is not a pool account"),
+        }
+    }
+}
+
+impl core::error::Error for DecodeError {}
+
+/// Reads the `Amm` from the data of a pool account
+pub fn decode_amm(data: &[u8]) -> Result<Amm, DecodeError> {
+    if data.len() != POOL_LEN as usize {
+        return Err(DecodeError::InvalidLength(data.len()));
+    }
+    if data[..POOL_DISCRIMINATOR.len()] != POOL_DISCRIMINATOR {
+        return Err(DecodeError::InvalidDiscriminator);
+    }
+    Ok(bytemuck::pod_read_unaligned(&data[AMM_OFFSET..]))
+}
+
+/// A decoded pool, exported to JavaScript
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy)]
+pub struct Pool {
+    amm: Amm,
+}
+
+#[wasm_bindgen]
+impl Pool {
+    /// Decodes the data of a pool account
+    pub fn decode(data: &[u8]) -> Result<Pool, JsError> {
+        Ok(Pool {
+            amm: decode_amm(data)?,
+        })
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn fee_in_bps(&self) -> u32 {
+        self.amm.fee_in_bps
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn base_reserves(&self) -> u64 {
+        self.amm.base_reserves
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn quote_reserves(&self) -> u64 {
+        self.amm.quote_reserves
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn total_lp_shares(&self) -> u64 {
+        self.amm.total_lp_shares
+    }
+
+    /// Simulates a swap at `slot`. `amount` is the input of exact in swaps and the output of
+    /// exact out swaps.
+    pub fn quote(
+        &self,
+        slot: u64,
+        side: Side,
+        exact_in: bool,
+        amount: u64,
+    ) -> Result<SwapResult, JsError> {
+        let mut amm = self.amm;
+        let window = slot_window(slot);
+        let result = match (side, exact_in) {
+            (Side::Buy, true) => amm.buy_exact_in(window, amount),
+            (Side::Buy, false) => amm.buy_exact_out(window, amount),
+            (Side::Sell, true) => amm.sell_exact_in(window, amount),
+            (Side::Sell, false) => amm.sell_exact_out(window, amount),
+        }?;
+        Ok(result)
+    }
+
+    /// Simulates adding liquidity at `slot`
+    pub fn preview_mint(
+        &self,
+        slot: u64,
+        base_amount_desired: u64,
+        quote_amount_desired: u64,
+    ) -> Result<MintPreview, JsError> {
+        Ok(self
+            .amm
+            .preview_mint(slot_window(slot), base_amount_desired, quote_amount_desired)?)
+    }
+
+    /// Simulates removing `lp_shares` at `slot`
+    pub fn preview_burn(&self, slot: u64, lp_shares: u64) -> Result<BurnPreview, JsError> {
+        Ok(self.amm.preview_burn(slot_window(slot), lp_shares)?)
+    }
+}
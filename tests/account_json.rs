@@ -0,0 +1,218 @@
+#![cfg(feature = "account-json")]
+
+use std::path::{Path, PathBuf};
+
+use plasma_sdk::plasma::{
+    ID, InitializePoolParams, Side, SwapParams, SwapType,
+    account_json::{AccountDump, AccountJsonError, decode_pool, parse_account_json},
+    get_lp_position_address, get_vault_address,
+    reference::ReferenceRuntime,
+    swap,
+};
+use solana_account::Account;
+use solana_account_decoder::{UiAccountEncoding, encode_ui_account};
+use solana_program::pubkey::Pubkey;
+
+mod common;
+
+use common::{BASE_AMOUNT, Fixture, QUOTE_AMOUNT, Wallet};
+
+/// A pool whose creator holds an LP position and a trader with token accounts to dump
+struct Snapshot {
+    fixture: Fixture,
+    trader: Wallet,
+}
+
+impl Snapshot {
+    fn new() -> Self {
+        let mut fixture = Fixture::new(InitializePoolParams {
+            lp_fee_in_bps: 30,
+            protocol_fee_allocation_in_pct: 20,
+            fee_recipients_params: Default::default(),
+            num_slots_to_vest_lp_shares: None,
+        });
+        let trader = fixture.wallet(BASE_AMOUNT, QUOTE_AMOUNT);
+        Self { fixture, trader }
+    }
+
+    fn keys(&self) -> Vec<Pubkey> {
+        let fixture = &self.fixture;
+        vec![
+            fixture.pool_key,
+            get_vault_address(&ID, &fixture.pool_key, &fixture.base_mint).0,
+            get_vault_address(&ID, &fixture.pool_key, &fixture.quote_mint).0,
+            get_lp_position_address(&ID, &fixture.pool_key, &fixture.creator.key).0,
+            fixture.base_mint,
+            fixture.quote_mint,
+            self.trader.base_account,
+            self.trader.quote_account,
+        ]
+    }
+
+    fn account(&self, key: &Pubkey) -> Account {
+        self.fixture.runtime.get_account(key).unwrap().clone()
+    }
+
+    /// Writes every account the way `solana account <key> --output json` would
+    fn write_dir(&self, name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("plasma-account-json-{}", name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for key in self.keys() {
+            write_json(
+                &dir.join(format!("{}.json", key)),
+                &key,
+                &self.account(&key),
+            );
+        }
+        dir
+    }
+}
+
+fn account_json(key: &Pubkey, account: &Account, encoding: UiAccountEncoding) -> String {
+    let account = encode_ui_account(key, account, encoding, None, None);
+    serde_json::json!({ "pubkey": key.to_string(), "account": account }).to_string()
+}
+
+fn write_json(path: &Path, key: &Pubkey, account: &Account) {
+    std::fs::write(path, account_json(key, account, UiAccountEncoding::Base64)).unwrap();
+}
+
+#[test]
+fn loaded_dump_matches_the_runtime_and_can_be_replayed() {
+    let snapshot = Snapshot::new();
+    let dir = snapshot.write_dir("replay");
+    // Files that are not JSON are skipped
+    std::fs::write(dir.join("README"), "not an account").unwrap();
+    let dump = AccountDump::load(ID, &dir).unwrap();
+
+    assert_eq!(dump.accounts().len(), snapshot.keys().len());
+    assert_eq!(dump.pools().len(), 1);
+    assert_eq!(dump.lp_positions().len(), 1);
+    let Snapshot { fixture, trader } = &snapshot;
+    let pool = dump.pool(&fixture.pool_key).unwrap();
+    assert_eq!(
+        borsh::to_vec(pool).unwrap(),
+        snapshot.account(&fixture.pool_key).data
+    );
+    let lp_position = dump
+        .lp_position(&fixture.pool_key, &fixture.creator.key)
+        .unwrap();
+    let lp_position_key = get_lp_position_address(&ID, &fixture.pool_key, &fixture.creator.key).0;
+    assert_eq!(
+        borsh::to_vec(lp_position).unwrap(),
+        snapshot.account(&lp_position_key).data
+    );
+
+    // A runtime rebuilt from the dump behaves like the original one
+    let mut runtime = ReferenceRuntime::new();
+    runtime.set_slot(fixture.runtime.slot());
+    for (key, account) in dump.accounts() {
+        runtime.set_account(*key, account.clone());
+    }
+    let instruction = swap(
+        &fixture.pool_key,
+        &trader.key,
+        &fixture.base_mint,
+        &fixture.quote_mint,
+        &trader.base_account,
+        &trader.quote_account,
+        SwapParams {
+            side: Side::Buy,
+            swap_type: SwapType::ExactIn {
+                amount_in: 1_000_000_000,
+                min_amount_out: 0,
+            },
+        },
+    );
+    let mut original = fixture.runtime.clone();
+    original
+        .process_transaction(std::slice::from_ref(&instruction), &[trader.key])
+        .unwrap();
+    runtime
+        .process_transaction(&[instruction], &[trader.key])
+        .unwrap();
+    assert_eq!(
+        runtime.get_account(&fixture.pool_key),
+        original.get_account(&fixture.pool_key)
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn parse_account_json_accepts_every_binary_encoding() {
+    let snapshot = Snapshot::new();
+    let (pool_key, base_mint) = (snapshot.fixture.pool_key, snapshot.fixture.base_mint);
+    let account = snapshot.account(&pool_key);
+    for encoding in [UiAccountEncoding::Base64, UiAccountEncoding::Base64Zstd] {
+        let json = account_json(&pool_key, &account, encoding);
+        assert_eq!(
+            parse_account_json(&json).unwrap(),
+            (pool_key, account.clone())
+        );
+    }
+    // Small accounts can also be dumped as base58
+    let mint = snapshot.account(&base_mint);
+    let json = account_json(&base_mint, &mint, UiAccountEncoding::Base58);
+    assert_eq!(parse_account_json(&json).unwrap(), (base_mint, mint));
+
+    assert!(matches!(
+        parse_account_json("{}"),
+        Err(AccountJsonError::InvalidJson { path: None, .. })
+    ));
+}
+
+#[test]
+fn invalid_accounts_are_rejected() {
+    let snapshot = Snapshot::new();
+    let pool_key = snapshot.fixture.pool_key;
+    let pool = snapshot.account(&pool_key);
+    assert!(decode_pool(&ID, &pool_key, &pool).is_ok());
+
+    let other_program = Pubkey::new_unique();
+    assert_eq!(
+        decode_pool(&other_program, &pool_key, &pool).err(),
+        Some(AccountJsonError::WrongOwner {
+            pubkey: pool_key,
+            owner: ID
+        })
+    );
+
+    let mut bad_discriminator = pool.clone();
+    bad_discriminator.data[0] ^= 1;
+    assert_eq!(
+        decode_pool(&ID, &pool_key, &bad_discriminator).err(),
+        Some(AccountJsonError::InvalidPool(pool_key))
+    );
+
+    // The same pool data stored under another address has vaults derived from the wrong pool
+    let copied_key = Pubkey::new_unique();
+    let mut dump = AccountDump::new(ID);
+    assert!(matches!(
+        dump.insert(copied_key, pool.clone()),
+        Err(AccountJsonError::InvalidVault { pool, .. }) if pool == copied_key
+    ));
+
+    let mut unknown = pool.clone();
+    unknown.data.truncate(100);
+    assert_eq!(
+        dump.insert(pool_key, unknown),
+        Err(AccountJsonError::UnknownAccount(pool_key))
+    );
+
+    dump.insert(pool_key, pool.clone()).unwrap();
+    assert_eq!(
+        dump.insert(pool_key, pool),
+        Err(AccountJsonError::DuplicateAccount(pool_key))
+    );
+
+    let dir = snapshot.write_dir("invalid");
+    let broken = dir.join("broken.json");
+    std::fs::write(&broken, "{").unwrap();
+    assert!(matches!(
+        AccountDump::load(ID, &dir),
+        Err(AccountJsonError::InvalidJson { path: Some(path), .. }) if path == broken
+    ));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,260 @@
+#![cfg(feature = "client")]
+
+use plasma_sdk::plasma::{
+    ID, InitializePoolParams,
+    aggregator::{
+        AccountMap, AggregatorAmm, AggregatorError, KeyedAccount, PlasmaPool, QuoteParams,
+        SwapAccountsParams, SwapMode,
+    },
+    slot_window,
+};
+use solana_account::Account;
+use solana_program::{clock::Clock, pubkey::Pubkey, sysvar};
+
+mod common;
+
+use common::{BASE_AMOUNT, Fixture, QUOTE_AMOUNT, Wallet};
+
+fn setup() -> (Fixture, Wallet) {
+    let mut fixture = Fixture::new(InitializePoolParams {
+        lp_fee_in_bps: 30,
+        protocol_fee_allocation_in_pct: 20,
+        fee_recipients_params: Default::default(),
+        num_slots_to_vest_lp_shares: None,
+    });
+    let trader = fixture.wallet(BASE_AMOUNT, QUOTE_AMOUNT);
+    (fixture, trader)
+}
+
+fn clock(fixture: &Fixture) -> Clock {
+    Clock {
+        slot: fixture.runtime.slot(),
+        ..Clock::default()
+    }
+}
+
+fn plasma_pool(fixture: &Fixture) -> PlasmaPool {
+    let keyed_account = KeyedAccount {
+        key: fixture.pool_key,
+        account: fixture
+            .runtime
+            .get_account(&fixture.pool_key)
+            .unwrap()
+            .clone(),
+    };
+    PlasmaPool::from_keyed_account(&keyed_account, &clock(fixture)).unwrap()
+}
+
+/// What an aggregator would fetch for `accounts_to_update`
+fn fetch(fixture: &Fixture, keys: &[Pubkey]) -> AccountMap {
+    keys.iter()
+        .map(|key| {
+            let account = if *key == sysvar::clock::ID {
+                Account {
+                    lamports: 1,
+                    data: bincode::serialize(&clock(fixture)).unwrap(),
+                    owner: sysvar::ID,
+                    executable: false,
+                    rent_epoch: 0,
+                }
+            } else {
+                fixture.runtime.get_account(key).unwrap().clone()
+            };
+            (*key, account)
+        })
+        .collect()
+}
+
+/// Executes the adapter's swap and returns the trader's (amount in, amount out)
+fn execute(
+    fixture: &mut Fixture,
+    trader: &Wallet,
+    adapter: &PlasmaPool,
+    params: &QuoteParams,
+    other_amount_threshold: u64,
+) -> (u64, u64) {
+    let account_for = |mint: &Pubkey| {
+        if *mint == fixture.base_mint {
+            trader.base_account
+        } else {
+            trader.quote_account
+        }
+    };
+    let source = account_for(&params.input_mint);
+    let destination = account_for(&params.output_mint);
+    let instruction = adapter
+        .swap_instruction(
+            &SwapAccountsParams {
+                source_mint: params.input_mint,
+                destination_mint: params.output_mint,
+                source_token_account: source,
+                destination_token_account: destination,
+                token_transfer_authority: trader.key,
+            },
+            params.swap_mode,
+            params.amount,
+            other_amount_threshold,
+        )
+        .unwrap();
+    let before = (
+        fixture.runtime.token_balance(&source).unwrap(),
+        fixture.runtime.token_balance(&destination).unwrap(),
+    );
+    fixture
+        .runtime
+        .process_transaction(&[instruction], &[trader.key])
+        .unwrap();
+    (
+        before.0 - fixture.runtime.token_balance(&source).unwrap(),
+        fixture.runtime.token_balance(&destination).unwrap() - before.1,
+    )
+}
+
+#[test]
+fn quotes_match_executed_swaps_in_every_direction() {
+    let (mut fixture, trader) = setup();
+    let mut adapter = plasma_pool(&fixture);
+    assert_eq!(adapter.program_id(), ID);
+    assert_eq!(adapter.key(), fixture.pool_key);
+    assert_eq!(
+        adapter.reserve_mints(),
+        vec![fixture.base_mint, fixture.quote_mint]
+    );
+    assert_eq!(
+        adapter.accounts_to_update(),
+        vec![fixture.pool_key, sysvar::clock::ID]
+    );
+
+    let (base_mint, quote_mint) = (fixture.base_mint, fixture.quote_mint);
+    let cases = [
+        (quote_mint, base_mint, SwapMode::ExactIn, 2_000_000_000),
+        (quote_mint, base_mint, SwapMode::ExactOut, 5_000_000_000),
+        (base_mint, quote_mint, SwapMode::ExactIn, 7_000_000_000),
+        (base_mint, quote_mint, SwapMode::ExactOut, 300_000_000),
+    ];
+    for (i, (input_mint, output_mint, swap_mode, amount)) in cases.into_iter().enumerate() {
+        // Move into a new slot window so the snapshot is refreshed by the quote and the swap alike
+        fixture.runtime.advance_slots(4 * i as u64);
+        adapter
+            .update(&fetch(&fixture, &adapter.accounts_to_update()))
+            .unwrap();
+        assert_eq!(adapter.slot_window(), slot_window(fixture.runtime.slot()));
+
+        let params = QuoteParams {
+            amount,
+            input_mint,
+            output_mint,
+            swap_mode,
+        };
+        let quote = adapter.quote(&params).unwrap();
+        assert_eq!(quote.fee_mint, quote_mint);
+        assert!(quote.fee_amount > 0);
+        match swap_mode {
+            SwapMode::ExactIn => assert_eq!(quote.in_amount, amount),
+            SwapMode::ExactOut => assert_eq!(quote.out_amount, amount),
+        }
+        let threshold = match swap_mode {
+            SwapMode::ExactIn => quote.out_amount,
+            SwapMode::ExactOut => quote.in_amount,
+        };
+        assert_eq!(
+            execute(&mut fixture, &trader, &adapter, &params, threshold),
+            (quote.in_amount, quote.out_amount)
+        );
+    }
+}
+
+#[test]
+fn quotes_within_a_window_include_the_snapshot_limit_order() {
+    let (fixture, _) = setup();
+    let adapter = plasma_pool(&fixture);
+    let buy = QuoteParams {
+        amount: 20_000_000_000,
+        input_mint: fixture.quote_mint,
+        output_mint: fixture.base_mint,
+        swap_mode: SwapMode::ExactIn,
+    };
+    let quote = adapter.quote(&buy).unwrap();
+    assert_eq!(quote.out_amount_from_limit_order, 0);
+
+    // Simulate the buy landing, then sell back in the same window against the snapshot bid
+    let mut pool = *adapter.pool();
+    pool.amm
+        .buy_exact_in(adapter.slot_window(), buy.amount)
+        .unwrap();
+    let mut account = fixture
+        .runtime
+        .get_account(&fixture.pool_key)
+        .unwrap()
+        .clone();
+    account.data = borsh::to_vec(&pool).unwrap();
+    let moved = PlasmaPool::from_keyed_account(
+        &KeyedAccount {
+            key: fixture.pool_key,
+            account,
+        },
+        &clock(&fixture),
+    )
+    .unwrap();
+    let sell = moved
+        .quote(&QuoteParams {
+            amount: quote.out_amount,
+            input_mint: fixture.base_mint,
+            output_mint: fixture.quote_mint,
+            swap_mode: SwapMode::ExactIn,
+        })
+        .unwrap();
+    assert!(sell.out_amount_from_limit_order > 0);
+    assert!(sell.out_amount < buy.amount);
+}
+
+#[test]
+fn adapter_rejects_invalid_inputs() {
+    let (fixture, _) = setup();
+    let mut adapter = plasma_pool(&fixture);
+    let other_mint = Pubkey::new_unique();
+    assert_eq!(
+        adapter.quote(&QuoteParams {
+            amount: 1,
+            input_mint: fixture.base_mint,
+            output_mint: other_mint,
+            swap_mode: SwapMode::ExactIn,
+        }),
+        Err(AggregatorError::UnsupportedMints {
+            input_mint: fixture.base_mint,
+            output_mint: other_mint,
+        })
+    );
+    assert_eq!(
+        adapter.quote(&QuoteParams {
+            amount: 1,
+            input_mint: fixture.base_mint,
+            output_mint: fixture.base_mint,
+            swap_mode: SwapMode::ExactIn,
+        }),
+        Err(AggregatorError::UnsupportedMints {
+            input_mint: fixture.base_mint,
+            output_mint: fixture.base_mint,
+        })
+    );
+
+    let mut accounts = fetch(&fixture, &adapter.accounts_to_update());
+    accounts.remove(&sysvar::clock::ID);
+    assert_eq!(
+        adapter.update(&accounts),
+        Err(AggregatorError::MissingAccount(sysvar::clock::ID))
+    );
+
+    let mint = KeyedAccount {
+        key: fixture.base_mint,
+        account: fixture
+            .runtime
+            .get_account(&fixture.base_mint)
+            .unwrap()
+            .clone(),
+    };
+    assert!(matches!(
+        PlasmaPool::from_keyed_account(&mint, &clock(&fixture)),
+        Err(AggregatorError::InvalidPoolAccount(key)) if key == fixture.base_mint
+    ));
+}
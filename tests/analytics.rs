@@ -0,0 +1,388 @@
+#![cfg(feature = "client")]
+
+use plasma_sdk::plasma::{
+    I80F48, InitializePoolParams, PlasmaStateError, Side, SwapParams, SwapType,
+    analytics::{
+        AnalyticsError, LpEntry, LpPnl, PoolSnapshot, SECONDS_PER_YEAR, compute_lp_pnl,
+        estimate_fee_rates,
+    },
+    slot_window,
+};
+
+use solana_program::pubkey::Pubkey;
+
+mod common;
+
+use common::{Fixture, Wallet};
+
+/// A pool at a price of 4 quote per base with 2_000_000 LP shares and an LP that deposited
+/// 100_000 base and 400_000 quote for 200_000 shares
+fn setup() -> (Fixture, Wallet, LpEntry) {
+    let mut fixture = Fixture::with_liquidity(
+        InitializePoolParams {
+            lp_fee_in_bps: 30,
+            protocol_fee_allocation_in_pct: 20,
+            ..Default::default()
+        },
+        1_000_000,
+        4_000_000,
+    );
+    let lp = fixture.wallet(100_000, 400_000);
+    fixture.initialize_lp_position(&lp.key);
+    let slot = slot_window(fixture.runtime.slot());
+    let preview = fixture.amm().preview_mint(slot, 100_000, 400_000).unwrap();
+    fixture.add_liquidity(&lp, 100_000, 400_000).unwrap();
+    (fixture, lp, LpEntry::from_mint_preview(slot, &preview))
+}
+
+fn lp_pnl(fixture: &Fixture, lp: &Wallet, entry: &LpEntry) -> Result<LpPnl, AnalyticsError> {
+    let pool = fixture.runtime.pool(&fixture.pool_key).unwrap();
+    let position = fixture
+        .runtime
+        .lp_position(&fixture.pool_key, &lp.key)
+        .unwrap();
+    compute_lp_pnl(entry, &pool, &position)
+}
+
+#[test]
+fn lp_pnl_of_a_fresh_deposit_is_flat() {
+    let (fixture, lp, entry) = setup();
+    assert_eq!(
+        (
+            entry.base_amount_deposited,
+            entry.quote_amount_deposited,
+            entry.lp_shares
+        ),
+        (100_000, 400_000, 200_000)
+    );
+
+    let pnl = lp_pnl(&fixture, &lp, &entry).unwrap();
+    assert_eq!(
+        (pnl.base_amount_redeemable, pnl.quote_amount_redeemable),
+        (100_000, 400_000)
+    );
+    assert_eq!(pnl.fees_earned_in_quote, 0);
+    assert_eq!(
+        (
+            pnl.entry_value_in_quote,
+            pnl.hold_value_in_quote,
+            pnl.lp_value_in_quote
+        ),
+        (800_000, 800_000, 800_000)
+    );
+    assert_eq!(
+        (
+            pnl.impermanent_loss_in_quote,
+            pnl.net_pnl_in_quote,
+            pnl.net_pnl_versus_hold_in_quote
+        ),
+        (0, 0, 0)
+    );
+}
+
+#[test]
+fn lp_pnl_values_only_the_shares_still_held() {
+    let (mut fixture, lp, entry) = setup();
+    // 50_000 of 2_200_000 shares withdraw 25_000 base and 100_000 quote
+    fixture.remove_liquidity(&lp, 50_000).unwrap();
+    assert_eq!(fixture.balances(&lp), (25_000, 100_000));
+
+    // The remaining 150_000 shares are backed by three quarters of the deposit
+    let pnl = lp_pnl(&fixture, &lp, &entry).unwrap();
+    assert_eq!(
+        (pnl.base_amount_redeemable, pnl.quote_amount_redeemable),
+        (75_000, 300_000)
+    );
+    assert_eq!(
+        (
+            pnl.entry_value_in_quote,
+            pnl.hold_value_in_quote,
+            pnl.lp_value_in_quote
+        ),
+        (600_000, 600_000, 600_000)
+    );
+    assert_eq!(pnl.net_pnl_in_quote, 0);
+}
+
+#[test]
+fn lp_pnl_includes_accrued_fees() {
+    let (mut fixture, lp, entry) = setup();
+    let trader = fixture.wallet(0, 400_000);
+    // Swap in a later slot window so the snapshot matches the reserves and the whole swap goes
+    // through the curve
+    fixture.runtime.advance_slots(8);
+    fixture
+        .swap(
+            &trader,
+            SwapParams {
+                side: Side::Buy,
+                swap_type: SwapType::ExactIn {
+                    amount_in: 400_000,
+                    min_amount_out: 0,
+                },
+            },
+        )
+        .unwrap();
+
+    // The 1_200 quote fee leaves 960 for LPs after the 20% protocol cut. 398_800 quote buys
+    // 1_100_000 * 398_800 / 4_798_800 = 91_414 base.
+    let amm = fixture.amm();
+    assert_eq!(
+        (
+            amm.cumulative_quote_lp_fees,
+            amm.cumulative_quote_protocol_fees
+        ),
+        (960, 240)
+    );
+    assert_eq!(
+        (amm.base_reserves, amm.quote_reserves),
+        (1_008_586, 4_798_800)
+    );
+
+    // 200_000 of 2_200_000 shares earn floor(960 / 11) = 87 and redeem floor(1_008_586 / 11)
+    // base and floor(4_798_800 / 11) quote. Base is valued at 4_798_800 / 1_008_586.
+    let pnl = lp_pnl(&fixture, &lp, &entry).unwrap();
+    assert_eq!(pnl.fees_earned_in_quote, 87);
+    assert_eq!(
+        (pnl.base_amount_redeemable, pnl.quote_amount_redeemable),
+        (91_689, 436_254)
+    );
+    assert_eq!(
+        (
+            pnl.entry_value_in_quote,
+            pnl.hold_value_in_quote,
+            pnl.lp_value_in_quote
+        ),
+        (800_000, 475_794 + 400_000, 436_251 + 436_254)
+    );
+    assert_eq!(
+        (
+            pnl.impermanent_loss_in_quote,
+            pnl.net_pnl_in_quote,
+            pnl.net_pnl_versus_hold_in_quote
+        ),
+        (-3_289, 72_505 + 87, -3_289 + 87)
+    );
+
+    // Fees settled by a withdrawal still count, while the values shrink to the 150_000 shares
+    // left: 75_000 base and 300_000 quote of the deposit
+    fixture.remove_liquidity(&lp, 50_000).unwrap();
+    let pnl = lp_pnl(&fixture, &lp, &entry).unwrap();
+    assert_eq!(pnl.fees_earned_in_quote, 87);
+    assert_eq!(
+        (pnl.base_amount_redeemable, pnl.quote_amount_redeemable),
+        (68_767, 327_190)
+    );
+    assert_eq!(
+        (
+            pnl.entry_value_in_quote,
+            pnl.hold_value_in_quote,
+            pnl.lp_value_in_quote
+        ),
+        (600_000, 656_846, 654_379)
+    );
+    assert_eq!(pnl.impermanent_loss_in_quote, -2_467);
+}
+
+#[test]
+fn lp_pnl_rejects_inconsistent_inputs() {
+    let (fixture, lp, entry) = setup();
+    let topped_up = LpEntry {
+        lp_shares: 150_000,
+        ..entry
+    };
+    assert_eq!(
+        lp_pnl(&fixture, &lp, &topped_up).unwrap_err(),
+        AnalyticsError::PositionExceedsEntry {
+            entry_lp_shares: 150_000,
+            position_lp_shares: 200_000,
+        }
+    );
+    let empty = LpEntry {
+        lp_shares: 0,
+        ..entry
+    };
+    assert_eq!(
+        lp_pnl(&fixture, &lp, &empty).unwrap_err(),
+        AnalyticsError::EmptyEntry
+    );
+
+    let mut pool = fixture.runtime.pool(&fixture.pool_key).unwrap();
+    let position = fixture
+        .runtime
+        .lp_position(&fixture.pool_key, &lp.key)
+        .unwrap();
+    pool.amm.total_lp_shares = 0;
+    assert_eq!(
+        compute_lp_pnl(&entry, &pool, &position).unwrap_err(),
+        AnalyticsError::EmptyPool
+    );
+}
+
+#[test]
+fn pending_fees_that_overflow_are_reported() {
+    let (fixture, lp, entry) = setup();
+    let mut pool = fixture.runtime.pool(&fixture.pool_key).unwrap();
+    let mut position = fixture
+        .runtime
+        .lp_position(&fixture.pool_key, &lp.key)
+        .unwrap();
+    // 2_000 quote per share for 200_000 shares
+    pool.amm.reward_factor = position.reward_factor_snapshot() + I80F48::from_num(2_000);
+    assert_eq!(position.pending_fees(&pool.amm), Some(400_000_000));
+
+    // u64::MAX shares earning u64::MAX each exceed the 80 integer bits of the reward factor
+    pool.amm.reward_factor = I80F48::from_num(u64::MAX);
+    position.lp_shares = u64::MAX;
+    assert_eq!(position.pending_fees(&pool.amm), None);
+    // Fees that fit the reward factor but not a u64
+    position.lp_shares = 1 << 20;
+    assert_eq!(position.pending_fees(&pool.amm), None);
+    let entry = LpEntry {
+        lp_shares: position.lp_shares,
+        ..entry
+    };
+    assert_eq!(
+        compute_lp_pnl(&entry, &pool, &position).unwrap_err(),
+        AnalyticsError::State(PlasmaStateError::Overflow)
+    );
+}
+
+/// A snapshot of a 30 bps pool holding 1_000_000 quote, so 2_000_000 TVL, with the given
+/// cumulative LP and protocol fees
+fn snapshot(
+    fixture: &Fixture,
+    slot: u64,
+    unix_timestamp: i64,
+    lp_fees: u64,
+    protocol_fees: u64,
+) -> PoolSnapshot {
+    let mut pool = fixture.runtime.pool(&fixture.pool_key).unwrap();
+    pool.amm.quote_reserves = 1_000_000;
+    pool.amm.cumulative_quote_lp_fees = lp_fees;
+    pool.amm.cumulative_quote_protocol_fees = protocol_fees;
+    PoolSnapshot {
+        pool_key: fixture.pool_key,
+        slot,
+        unix_timestamp,
+        pool,
+    }
+}
+
+#[test]
+fn fee_rates_skip_gaps_and_use_the_remaining_intervals() {
+    let (fixture, _, _) = setup();
+    assert_eq!(fixture.amm().fee_in_bps, 30);
+    let snapshots = [
+        snapshot(&fixture, 400, 10_200, 1_200, 300),
+        snapshot(&fixture, 100, 0, 0, 0),
+        // The 10_000 second gap to the next snapshot exceeds the maximum and is skipped
+        snapshot(&fixture, 300, 10_100, 900, 225),
+        snapshot(&fixture, 200, 100, 600, 150),
+        // Refetched at the same slot
+        snapshot(&fixture, 200, 100, 600, 150),
+    ];
+
+    // 100 seconds earn 600 + 150 and another 100 seconds earn 300 + 75
+    let estimate = estimate_fee_rates(&snapshots, Some(3_600)).unwrap();
+    assert_eq!((estimate.start_slot, estimate.end_slot), (100, 400));
+    assert_eq!(
+        (estimate.elapsed_seconds, estimate.skipped_intervals),
+        (200, 1)
+    );
+    assert_eq!(
+        (estimate.lp_fees_in_quote, estimate.protocol_fees_in_quote),
+        (900, 225)
+    );
+    // 750 * 10_000 / 30 + 375 * 10_000 / 30
+    assert_eq!(estimate.implied_volume_in_quote, 375_000);
+    assert_eq!(estimate.average_tvl_in_quote, 2_000_000.0);
+    // 900 / 2_000_000 per 200 seconds
+    let periods_per_year = SECONDS_PER_YEAR / 200.0;
+    assert!((estimate.lp_fee_apr - 0.00045 * periods_per_year).abs() < 1e-9);
+    assert!((estimate.lp_fee_apy - (1.00045_f64.powf(periods_per_year) - 1.0)).abs() < 1e-6);
+    assert_eq!(
+        estimate.protocol_revenue_per_year_in_quote,
+        225.0 / 200.0 * SECONDS_PER_YEAR
+    );
+
+    // Without a maximum gap every interval is used
+    let estimate = estimate_fee_rates(&snapshots, None).unwrap();
+    assert_eq!(
+        (estimate.elapsed_seconds, estimate.skipped_intervals),
+        (10_200, 0)
+    );
+    assert_eq!(estimate.lp_fees_in_quote, 1_200);
+}
+
+#[test]
+fn fee_rates_reject_unusable_snapshots() {
+    let (fixture, _, _) = setup();
+    assert_eq!(
+        estimate_fee_rates(&[], None).unwrap_err(),
+        AnalyticsError::NotEnoughSnapshots
+    );
+    let single = snapshot(&fixture, 100, 0, 0, 0);
+    assert_eq!(
+        estimate_fee_rates(&[single, single], None).unwrap_err(),
+        AnalyticsError::NotEnoughSnapshots
+    );
+
+    // A second pool of the same pair is still another pool
+    let mut other_pool = snapshot(&fixture, 200, 100, 0, 0);
+    other_pool.pool_key = Pubkey::new_unique();
+    assert_eq!(
+        estimate_fee_rates(&[single, other_pool], None).unwrap_err(),
+        AnalyticsError::MixedPools
+    );
+
+    let decreased = [
+        single,
+        snapshot(&fixture, 200, 100, 600, 150),
+        snapshot(&fixture, 300, 200, 500, 150),
+    ];
+    assert_eq!(
+        estimate_fee_rates(&decreased, None).unwrap_err(),
+        AnalyticsError::FeeCountersDecreased {
+            start_slot: 200,
+            end_slot: 300,
+        }
+    );
+
+    // The clock went backwards and then jumped past the maximum gap
+    let skipped = [
+        single,
+        snapshot(&fixture, 200, -10, 600, 150),
+        snapshot(&fixture, 300, 5_000, 900, 225),
+    ];
+    assert_eq!(
+        estimate_fee_rates(&skipped, Some(3_600)).unwrap_err(),
+        AnalyticsError::NoUsableIntervals {
+            skipped_intervals: 2
+        }
+    );
+
+    let overflowing = [
+        snapshot(&fixture, 100, i64::MIN, 0, 0),
+        snapshot(&fixture, 200, i64::MAX, 600, 150),
+    ];
+    assert_eq!(
+        estimate_fee_rates(&overflowing, None).unwrap_err(),
+        AnalyticsError::TimestampOverflow {
+            start_slot: 100,
+            end_slot: 200,
+        }
+    );
+    let overflowing_total = [
+        snapshot(&fixture, 100, -1, 0, 0),
+        snapshot(&fixture, 200, i64::MAX - 1, 600, 150),
+        snapshot(&fixture, 300, i64::MAX, 900, 225),
+    ];
+    assert_eq!(
+        estimate_fee_rates(&overflowing_total, None).unwrap_err(),
+        AnalyticsError::TimestampOverflow {
+            start_slot: 200,
+            end_slot: 300,
+        }
+    );
+}
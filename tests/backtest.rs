@@ -0,0 +1,226 @@
+#![cfg(feature = "client")]
+
+use plasma_sdk::plasma::{
+    SLOTS_PER_WINDOW,
+    backtest::{
+        BacktestConfig, BacktestError, NoiseOrder, PricePoint, RandomOrderFlow, WindowStats,
+        run_backtest,
+    },
+    plasma_amm::Side,
+};
+
+const BASE_LIQUIDITY: u64 = 1_000_000_000_000;
+const QUOTE_LIQUIDITY: u64 = 150_000_000_000;
+const PRICE: f64 = QUOTE_LIQUIDITY as f64 / BASE_LIQUIDITY as f64;
+
+fn config() -> BacktestConfig {
+    BacktestConfig {
+        fee_in_bps: 30,
+        protocol_allocation_in_pct: 20,
+        lp_vesting_window: 0,
+        base_liquidity: BASE_LIQUIDITY,
+        quote_liquidity: QUOTE_LIQUIDITY,
+        min_arbitrage_profit_in_quote: 0.0,
+        jit_liquidity_in_bps: 0,
+    }
+}
+
+fn no_orders(_: &PricePoint) -> Vec<NoiseOrder> {
+    vec![]
+}
+
+/// LP shares of the fees are rounded down at both ends of each window
+fn assert_fees_split(stats: &WindowStats) {
+    let split = stats.passive_lp_fees_in_quote + stats.jit_lp_fees_in_quote;
+    assert!(split.abs_diff(stats.lp_fees_in_quote) <= 3);
+}
+
+#[test]
+fn flat_price_without_flow_leaves_pools_untouched() {
+    let prices = (0..10)
+        .map(|i| PricePoint {
+            slot: i * SLOTS_PER_WINDOW,
+            price: PRICE,
+        })
+        .collect::<Vec<_>>();
+    let report = run_backtest(&config(), &prices, &mut no_orders).unwrap();
+
+    assert_eq!(report.windows.len(), 10);
+    for summary in [report.plasma, report.constant_product] {
+        assert_eq!(summary.arbitrage_volume_in_quote, 0);
+        assert_eq!(summary.lp_fees_in_quote, 0);
+        assert!(summary.passive_lp_pnl_versus_hold_in_quote.abs() < 1.0);
+    }
+}
+
+#[test]
+fn snapshot_limit_order_blocks_reverting_arbitrage_within_a_window() {
+    // The price spikes and reverts inside one slot window, then again in the next one
+    let prices = [
+        (0, PRICE),
+        (1, PRICE * 1.05),
+        (2, PRICE),
+        (4, PRICE * 0.95),
+        (5, PRICE),
+    ]
+    .map(|(slot, price)| PricePoint { slot, price });
+    let report = run_backtest(&config(), &prices, &mut no_orders).unwrap();
+
+    assert_eq!(
+        report
+            .windows
+            .iter()
+            .map(|window| window.slot_window)
+            .collect::<Vec<_>>(),
+        vec![0, 1]
+    );
+    let spot_price = |stats: &WindowStats| stats.quote_reserves as f64 / stats.base_reserves as f64;
+    let first = &report.windows[0];
+    // Selling back into the pool hits the bid at the pre-spike snapshot price, which leaves no
+    // profit after fees, so the Plasma pool keeps the spike price until the next window
+    assert!((spot_price(&first.constant_product) / PRICE - 1.0).abs() < 0.005);
+    assert!(spot_price(&first.plasma) / PRICE - 1.0 > 0.02);
+    assert!(
+        first.plasma.arbitrage_volume_in_quote < first.constant_product.arbitrage_volume_in_quote
+    );
+    assert!(first.plasma.lp_fees_in_quote < first.constant_product.lp_fees_in_quote);
+    for window in report.windows.iter() {
+        assert_fees_split(&window.plasma);
+        assert_fees_split(&window.constant_product);
+    }
+}
+
+#[test]
+fn noise_flow_pays_fees_and_vesting_keeps_jit_liquidity_in_the_pool() {
+    let prices = (0..40)
+        .map(|i| PricePoint {
+            slot: i * 2,
+            price: PRICE * (1.0 + 0.02 * ((i as f64) / 3.0).sin()),
+        })
+        .collect::<Vec<_>>();
+    let run = |config: BacktestConfig| {
+        run_backtest(
+            &config,
+            &prices,
+            &mut RandomOrderFlow::new(7, 3, 500_000_000.0),
+        )
+        .unwrap()
+    };
+
+    let without_jit = run(config());
+    assert!(without_jit.plasma.noise_volume_in_quote > 0);
+    assert!(without_jit.plasma.passive_lp_fees_in_quote > 0);
+    assert_eq!(without_jit.plasma.jit_lp_fees_in_quote, 0);
+    for window in without_jit.windows.iter() {
+        assert_fees_split(&window.plasma);
+    }
+
+    let with_jit = run(BacktestConfig {
+        jit_liquidity_in_bps: 10_000,
+        ..config()
+    });
+    assert!(with_jit.plasma.jit_lp_fees_in_quote > 0);
+    assert!(with_jit.plasma.passive_lp_fees_in_quote < without_jit.plasma.passive_lp_fees_in_quote);
+
+    // Vested JIT liquidity stays through later arbitrage and keeps earning on it
+    let vesting = run(BacktestConfig {
+        jit_liquidity_in_bps: 10_000,
+        lp_vesting_window: 4,
+        ..config()
+    });
+    assert!(vesting.plasma.jit_lp_fees_in_quote > with_jit.plasma.jit_lp_fees_in_quote);
+    assert_eq!(
+        vesting.constant_product.jit_lp_fees_in_quote,
+        with_jit.constant_product.jit_lp_fees_in_quote
+    );
+    for window in vesting.windows.iter() {
+        assert_fees_split(&window.plasma);
+        assert_fees_split(&window.constant_product);
+    }
+}
+
+#[test]
+fn run_backtest_rejects_invalid_prices() {
+    assert_eq!(
+        run_backtest(&config(), &[], &mut no_orders),
+        Err(BacktestError::NoPrices)
+    );
+    let unordered = [(5, PRICE), (1, PRICE)].map(|(slot, price)| PricePoint { slot, price });
+    assert_eq!(
+        run_backtest(&config(), &unordered, &mut no_orders),
+        Err(BacktestError::UnorderedPrices)
+    );
+    let negative = [(1, PRICE), (5, -PRICE)].map(|(slot, price)| PricePoint { slot, price });
+    assert_eq!(
+        run_backtest(&config(), &negative, &mut no_orders),
+        Err(BacktestError::InvalidPrice { slot: 5 })
+    );
+    // Errors of the pool itself are kept apart from invalid input
+    let empty = BacktestConfig {
+        base_liquidity: 0,
+        ..config()
+    };
+    assert!(matches!(
+        run_backtest(
+            &empty,
+            &[PricePoint {
+                slot: 0,
+                price: PRICE
+            }],
+            &mut no_orders
+        ),
+        Err(BacktestError::State(_))
+    ));
+    let mut orders = |_: &PricePoint| {
+        vec![NoiseOrder {
+            side: Side::Sell,
+            amount_in: u64::MAX,
+        }]
+    };
+    let report = run_backtest(
+        &config(),
+        &[PricePoint {
+            slot: 0,
+            price: PRICE,
+        }],
+        &mut orders,
+    )
+    .unwrap();
+    assert_eq!(report.plasma.rejected_orders, 1);
+    assert_eq!(report.constant_product.rejected_orders, 1);
+}
+
+#[test]
+fn run_backtest_rejects_jit_positions_that_overflow() {
+    let prices = [1, 2].map(|window| PricePoint {
+        slot: window * SLOTS_PER_WINDOW,
+        price: PRICE,
+    });
+    let mut orders = |_: &PricePoint| {
+        vec![NoiseOrder {
+            side: Side::Buy,
+            amount_in: 1_000_000,
+        }]
+    };
+
+    let oversized = BacktestConfig {
+        base_liquidity: u64::MAX / 2,
+        quote_liquidity: u64::MAX / 2,
+        jit_liquidity_in_bps: 30_000,
+        ..config()
+    };
+    assert_eq!(
+        run_backtest(&oversized, &prices, &mut orders),
+        Err(BacktestError::JitLiquidityOverflow)
+    );
+
+    let never_vesting = BacktestConfig {
+        lp_vesting_window: u64::MAX,
+        jit_liquidity_in_bps: 100,
+        ..config()
+    };
+    assert_eq!(
+        run_backtest(&never_vesting, &prices, &mut orders),
+        Err(BacktestError::VestingOverflow)
+    );
+}
@@ -0,0 +1,211 @@
+#![cfg(feature = "cli")]
+
+use std::{path::PathBuf, process::Command};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use plasma_sdk::{
+    PoolAccount,
+    plasma::{
+        ID, InitializePoolParams, Side, SwapParams, SwapType, get_vault_address,
+        initialize_pool_with_liquidity_with_rent, reference::ReferenceRuntime, swap,
+    },
+};
+use solana_sdk::{pubkey::Pubkey, transaction::Transaction};
+
+fn cli(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_plasma-cli"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "plasma-cli {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn write_dump(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("plasma-cli-{}-{}", std::process::id(), name));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+/// A pool launched through the reference runtime and its raw account data
+fn launched_pool() -> (Pubkey, PoolAccount, Vec<u8>) {
+    let mut runtime = ReferenceRuntime::new();
+    runtime.set_slot(100);
+    let (pool_key, creator) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (base_mint, quote_mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (base_account, quote_account) = (Pubkey::new_unique(), Pubkey::new_unique());
+    runtime.create_mint(base_mint, 9);
+    runtime.create_mint(quote_mint, 6);
+    runtime.airdrop(&creator, 10_000_000_000);
+    runtime.create_token_account(base_account, base_mint, creator, 1_000_000_000_000);
+    runtime.create_token_account(quote_account, quote_mint, creator, 150_000_000_000);
+    let instructions = initialize_pool_with_liquidity_with_rent(
+        &pool_key,
+        &creator,
+        &base_mint,
+        &base_account,
+        &quote_mint,
+        &quote_account,
+        InitializePoolParams {
+            lp_fee_in_bps: 25,
+            ..Default::default()
+        },
+        1_000_000_000_000,
+        150_000_000_000,
+        runtime.rent(),
+    );
+    runtime
+        .process_transaction(&instructions, &[creator, pool_key])
+        .unwrap();
+    let pool = runtime.pool(&pool_key).unwrap();
+    (pool_key, pool, borsh::to_vec(&pool).unwrap())
+}
+
+#[test]
+fn decode_reads_base64_hex_and_json_dumps() {
+    let (pool_key, pool, data) = launched_pool();
+    let base64 = STANDARD.encode(&data);
+    let hex = data
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    let json = format!(
+        r#"{{"pubkey": "{}", "account": {{"lamports": 1, "data": ["{}", "base64"], "owner": "{}", "executable": false, "rentEpoch": 0}}}}"#,
+        pool_key, base64, ID
+    );
+    for (name, dump) in [("base64", base64), ("hex", hex), ("json", json)] {
+        let path = write_dump(name, &dump);
+        let output = cli(&["decode", path.to_str().unwrap()]);
+        assert!(output.contains(&pool.header.base_params.mint_key.to_string()));
+        assert!(output.contains("spot price: 150"));
+    }
+}
+
+#[test]
+fn pda_and_quote_match_the_sdk() {
+    let (pool_key, pool, data) = launched_pool();
+    let mint = pool.header.base_params.mint_key;
+    let (vault, bump) = get_vault_address(&ID, &pool_key, &mint);
+    assert_eq!(
+        cli(&[
+            "pda",
+            "vault",
+            "--pool",
+            &pool_key.to_string(),
+            "--mint",
+            &mint.to_string()
+        ])
+        .trim(),
+        format!("{} {}", vault, bump)
+    );
+
+    let path = write_dump("quote", &STANDARD.encode(&data));
+    let expected = pool.amm.simulate_buy_exact_in(1_000_000).unwrap();
+    let output = cli(&["quote", path.to_str().unwrap(), "--quote-amount", "1000000"]);
+    assert!(output.contains("buy exact in 1000000"));
+    assert!(output.contains("sell exact out 1000000"));
+    assert!(!output.contains("sell exact in"));
+    assert!(output.contains(&format!(
+        "base_amount_to_transfer: {},",
+        expected.base_amount_to_transfer
+    )));
+}
+
+#[test]
+fn tx_emits_an_unsigned_transaction() {
+    let (pool_key, pool, _) = launched_pool();
+    let (trader, base_account, quote_account) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let (base_mint, quote_mint) = (
+        pool.header.base_params.mint_key,
+        pool.header.quote_params.mint_key,
+    );
+    let output = cli(&[
+        "tx",
+        "--payer",
+        &trader.to_string(),
+        "swap",
+        "--pool",
+        &pool_key.to_string(),
+        "--base-mint",
+        &base_mint.to_string(),
+        "--quote-mint",
+        &quote_mint.to_string(),
+        "--base-account",
+        &base_account.to_string(),
+        "--quote-account",
+        &quote_account.to_string(),
+        "--type",
+        "sell-exact-out",
+        "--amount",
+        "500",
+        "--limit",
+        "10000",
+    ]);
+    let transaction: Transaction =
+        bincode::deserialize(&STANDARD.decode(output.trim()).unwrap()).unwrap();
+    let expected = Transaction::new_with_payer(
+        &[swap(
+            &pool_key,
+            &trader,
+            &base_mint,
+            &quote_mint,
+            &base_account,
+            &quote_account,
+            SwapParams {
+                side: Side::Sell,
+                swap_type: SwapType::ExactOut {
+                    amount_out: 500,
+                    max_amount_in: 10_000,
+                },
+            },
+        )],
+        Some(&trader),
+    );
+    assert_eq!(transaction, expected);
+}
+
+#[test]
+fn tx_rejects_more_than_three_fee_recipients() {
+    let fee_recipient = format!("{}:1", Pubkey::new_unique());
+    let mut args = vec![
+        "tx".to_string(),
+        "--payer".to_string(),
+        Pubkey::new_unique().to_string(),
+        "initialize-pool".to_string(),
+        "--pool".to_string(),
+        Pubkey::new_unique().to_string(),
+        "--base-mint".to_string(),
+        Pubkey::new_unique().to_string(),
+        "--quote-mint".to_string(),
+        Pubkey::new_unique().to_string(),
+        "--lp-fee-in-bps".to_string(),
+        "30".to_string(),
+    ];
+    for _ in 0..3 {
+        args.extend(["--fee-recipient".to_string(), fee_recipient.clone()]);
+    }
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+    cli(&args);
+
+    let mut args = args;
+    args.extend(["--fee-recipient", &fee_recipient]);
+    let output = Command::new(env!("CARGO_BIN_EXE_plasma-cli"))
+        .args(&args)
+        .output()
+        .unwrap();
+    // A usage error rather than a panic
+    assert_eq!(output.status.code(), Some(2));
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .contains("at most 3 `--fee-recipient` values are allowed, got 4")
+    );
+}
@@ -0,0 +1,303 @@
+#![cfg(feature = "mock")]
+
+use plasma_sdk::plasma::{
+    AddLiquidityParams, ID, InitializePoolParams, Side, SwapParams, SwapType,
+    client::{ClientError, MockRpc, PlasmaClient, TransactionSender},
+    get_lp_position_address, initialize_pool_with_liquidity_with_rent,
+    processor::ProcessorError,
+    reference::ReferenceRuntime,
+};
+use solana_program::pubkey::Pubkey;
+use solana_sdk::{signature::Keypair, signer::Signer, transaction::Transaction};
+use solana_system_interface::instruction::transfer;
+
+const BASE_AMOUNT: u64 = 1_000_000_000_000;
+const QUOTE_AMOUNT: u64 = 150_000_000_000;
+
+struct Fixture {
+    client: PlasmaClient<MockRpc>,
+    pool_key: Pubkey,
+    base_account: Pubkey,
+    quote_account: Pubkey,
+}
+
+async fn setup() -> Fixture {
+    let mut runtime = ReferenceRuntime::new();
+    runtime.set_slot(100);
+    let payer = Keypair::new();
+    let pool = Keypair::new();
+    let base_mint = Pubkey::new_unique();
+    let quote_mint = Pubkey::new_unique();
+    let base_account = Pubkey::new_unique();
+    let quote_account = Pubkey::new_unique();
+    runtime.create_mint(base_mint, 9);
+    runtime.create_mint(quote_mint, 6);
+    runtime.airdrop(&payer.pubkey(), 10_000_000_000);
+    runtime.create_token_account(base_account, base_mint, payer.pubkey(), BASE_AMOUNT * 2);
+    runtime.create_token_account(quote_account, quote_mint, payer.pubkey(), QUOTE_AMOUNT * 2);
+    let pool_key = pool.pubkey();
+    let instructions = initialize_pool_with_liquidity_with_rent(
+        &pool_key,
+        &payer.pubkey(),
+        &base_mint,
+        &base_account,
+        &quote_mint,
+        &quote_account,
+        InitializePoolParams {
+            lp_fee_in_bps: 30,
+            protocol_fee_allocation_in_pct: 20,
+            fee_recipients_params: Default::default(),
+            num_slots_to_vest_lp_shares: None,
+        },
+        BASE_AMOUNT,
+        QUOTE_AMOUNT,
+        runtime.rent(),
+    );
+    let client = PlasmaClient::new(MockRpc::new(runtime), payer);
+    client.send(&instructions, &[&pool]).await.unwrap();
+    Fixture {
+        client,
+        pool_key,
+        base_account,
+        quote_account,
+    }
+}
+
+#[tokio::test]
+async fn client_fetches_quotes_and_swaps_against_the_mock() {
+    let fixture = setup().await;
+    let client = &fixture.client;
+    let pool = client.fetch_pool(&fixture.pool_key).await.unwrap();
+    assert_eq!(pool.amm.base_reserves, BASE_AMOUNT);
+    assert_eq!(pool.amm.quote_reserves, QUOTE_AMOUNT);
+    let lp_position = client
+        .fetch_lp_position(&fixture.pool_key, &client.payer())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(lp_position.lp_shares > 0);
+    assert!(
+        client
+            .fetch_lp_position(&fixture.pool_key, &Pubkey::new_unique())
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    let params = SwapParams {
+        side: Side::Buy,
+        swap_type: SwapType::ExactIn {
+            amount_in: 1_000_000_000,
+            min_amount_out: 0,
+        },
+    };
+    let quote = client.quote(&fixture.pool_key, &params).await.unwrap();
+    let base_before = client
+        .backend()
+        .runtime()
+        .token_balance(&fixture.base_account);
+    client
+        .swap(
+            &fixture.pool_key,
+            &fixture.base_account,
+            &fixture.quote_account,
+            params,
+        )
+        .await
+        .unwrap();
+    let base_after = client
+        .backend()
+        .runtime()
+        .token_balance(&fixture.base_account);
+    assert_eq!(
+        base_after.unwrap() - base_before.unwrap(),
+        quote.base_amount_to_transfer
+    );
+    assert_eq!(client.backend().transactions().len(), 2);
+
+    // Slippage limits are enforced by the program
+    let error = client
+        .swap(
+            &fixture.pool_key,
+            &fixture.base_account,
+            &fixture.quote_account,
+            SwapParams {
+                side: Side::Sell,
+                swap_type: SwapType::ExactIn {
+                    amount_in: 1_000_000_000,
+                    min_amount_out: u64::MAX,
+                },
+            },
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(error, ClientError::TransactionFailed(_)));
+    assert_eq!(client.backend().transactions().len(), 2);
+
+    client
+        .swap_with_slippage(
+            &fixture.pool_key,
+            &fixture.base_account,
+            &fixture.quote_account,
+            Side::Sell,
+            SwapType::ExactOut {
+                amount_out: 100_000_000,
+                max_amount_in: 0,
+            },
+            50,
+        )
+        .await
+        .unwrap();
+    // Slippage past 100% is capped rather than overflowing the limit
+    for swap_type in [
+        SwapType::ExactIn {
+            amount_in: 1_000_000,
+            min_amount_out: u64::MAX,
+        },
+        SwapType::ExactOut {
+            amount_out: 100_000,
+            max_amount_in: 0,
+        },
+    ] {
+        client
+            .swap_with_slippage(
+                &fixture.pool_key,
+                &fixture.base_account,
+                &fixture.quote_account,
+                Side::Sell,
+                swap_type,
+                u64::MAX,
+            )
+            .await
+            .unwrap();
+    }
+    assert_eq!(client.backend().transactions().len(), 5);
+}
+
+#[tokio::test]
+async fn client_adds_and_removes_liquidity() {
+    let fixture = setup().await;
+    let client = &fixture.client;
+    let shares = |client: &PlasmaClient<MockRpc>| {
+        let owner = client.payer();
+        client
+            .backend()
+            .runtime()
+            .lp_position(&fixture.pool_key, &owner)
+            .unwrap()
+            .lp_shares
+    };
+    let initial_shares = shares(client);
+
+    let preview = client
+        .preview_add_liquidity(&fixture.pool_key, 10_000_000_000, 1_500_000_000)
+        .await
+        .unwrap();
+    client
+        .add_liquidity(
+            &fixture.pool_key,
+            &fixture.base_account,
+            &fixture.quote_account,
+            AddLiquidityParams {
+                desired_base_amount_in: 10_000_000_000,
+                desired_quote_amount_in: 1_500_000_000,
+                initial_lp_shares: None,
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(shares(client), initial_shares + preview.lp_shares);
+
+    let quote_before = client
+        .backend()
+        .runtime()
+        .token_balance(&fixture.quote_account)
+        .unwrap();
+    let burn = client
+        .preview_remove_liquidity(&fixture.pool_key, preview.lp_shares)
+        .await
+        .unwrap();
+    client
+        .remove_liquidity(
+            &fixture.pool_key,
+            &fixture.base_account,
+            &fixture.quote_account,
+            preview.lp_shares,
+        )
+        .await
+        .unwrap();
+    assert_eq!(shares(client), initial_shares);
+    assert_eq!(
+        client
+            .backend()
+            .runtime()
+            .token_balance(&fixture.quote_account)
+            .unwrap()
+            - quote_before,
+        burn.quote_amount_withdrawn
+    );
+}
+
+#[tokio::test]
+async fn client_reports_missing_and_invalid_accounts() {
+    let fixture = setup().await;
+    let client = &fixture.client;
+    let missing = Pubkey::new_unique();
+    assert_eq!(
+        client.fetch_pool(&missing).await.unwrap_err(),
+        ClientError::AccountNotFound(missing)
+    );
+    assert_eq!(
+        client.fetch_pool(&fixture.base_account).await.unwrap_err(),
+        ClientError::InvalidAccount(fixture.base_account)
+    );
+    assert_eq!(
+        client
+            .fetch_pools(&[fixture.pool_key, missing])
+            .await
+            .unwrap_err(),
+        ClientError::AccountNotFound(missing)
+    );
+
+    // Transactions rejected by the program leave no trace in the mock
+    let other = Keypair::new();
+    let error = client
+        .transfer_liquidity(&fixture.pool_key, &other.pubkey())
+        .await
+        .unwrap_err();
+    let (destination_key, _) = get_lp_position_address(&ID, &fixture.pool_key, &other.pubkey());
+    assert_eq!(
+        error,
+        ClientError::TransactionFailed(
+            ProcessorError::AccountNotFound(destination_key).to_string()
+        )
+    );
+    assert_eq!(client.backend().transactions().len(), 1);
+}
+
+#[tokio::test]
+async fn mock_rejects_malformed_transactions_without_executing_them() {
+    let rpc = MockRpc::new(ReferenceRuntime::new());
+    let payer = Keypair::new();
+    rpc.runtime().airdrop(&payer.pubkey(), 10_000_000_000);
+    let blockhash = rpc.get_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[transfer(&payer.pubkey(), &Pubkey::new_unique(), 1)],
+        Some(&payer.pubkey()),
+        &[&payer],
+        blockhash,
+    );
+    rpc.send_transaction(&transaction).await.unwrap();
+
+    let mut out_of_bounds = transaction.clone();
+    out_of_bounds.message.instructions[0].accounts[1] = u8::MAX;
+    let mut missing_signer = transaction.clone();
+    missing_signer.message.header.num_required_signatures = 2;
+    for transaction in [out_of_bounds, missing_signer] {
+        assert!(matches!(
+            rpc.send_transaction(&transaction).await,
+            Err(ClientError::Rpc(_))
+        ));
+    }
+    assert_eq!(rpc.transactions().len(), 1);
+}
@@ -0,0 +1,227 @@
+//! Shared setup for tests that run instructions against the reference runtime
+
+#![allow(dead_code)]
+
+use plasma_sdk::plasma::{
+    AddLiquidityParams, ID, InitializePoolParams, SwapParams, add_liquidity, get_vault_address,
+    initialize_lp_position, initialize_pool_with_liquidity_with_rent, plasma_amm::Amm,
+    processor::ProcessorError, reference::ReferenceRuntime, remove_liquidity, swap,
+};
+use solana_program::pubkey::Pubkey;
+
+pub const LAMPORTS: u64 = 10_000_000_000;
+pub const BASE_AMOUNT: u64 = 1_000_000_000_000;
+pub const QUOTE_AMOUNT: u64 = 150_000_000_000;
+
+#[derive(Clone, Copy)]
+pub struct Wallet {
+    pub key: Pubkey,
+    pub base_account: Pubkey,
+    pub quote_account: Pubkey,
+}
+
+pub struct Fixture {
+    pub runtime: ReferenceRuntime,
+    pub pool_key: Pubkey,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub creator: Wallet,
+}
+
+impl Fixture {
+    /// Launches a pool with `BASE_AMOUNT` and `QUOTE_AMOUNT` at slot 100
+    pub fn new(params: InitializePoolParams) -> Self {
+        Self::with_liquidity(params, BASE_AMOUNT, QUOTE_AMOUNT)
+    }
+
+    /// Launches a pool with the given initial liquidity at slot 100. The creator deposits all of
+    /// its tokens, so trades need another [`Fixture::wallet`].
+    pub fn with_liquidity(
+        params: InitializePoolParams,
+        base_amount: u64,
+        quote_amount: u64,
+    ) -> Self {
+        let mut runtime = ReferenceRuntime::new();
+        runtime.set_slot(100);
+        let base_mint = Pubkey::new_unique();
+        let quote_mint = Pubkey::new_unique();
+        runtime.create_mint(base_mint, 9);
+        runtime.create_mint(quote_mint, 6);
+
+        let mut fixture = Self {
+            runtime,
+            pool_key: Pubkey::default(),
+            base_mint,
+            quote_mint,
+            creator: Wallet {
+                key: Pubkey::default(),
+                base_account: Pubkey::default(),
+                quote_account: Pubkey::default(),
+            },
+        };
+        (fixture.pool_key, fixture.creator) =
+            fixture.launch(base_mint, quote_mint, params, base_amount, quote_amount);
+        fixture
+    }
+
+    /// Launches another pool between two mints of the runtime, funded by a new creator
+    pub fn launch_pool(
+        &mut self,
+        base_mint: Pubkey,
+        quote_mint: Pubkey,
+        params: InitializePoolParams,
+        base_amount: u64,
+        quote_amount: u64,
+    ) -> Pubkey {
+        self.launch(base_mint, quote_mint, params, base_amount, quote_amount)
+            .0
+    }
+
+    fn launch(
+        &mut self,
+        base_mint: Pubkey,
+        quote_mint: Pubkey,
+        params: InitializePoolParams,
+        base_amount: u64,
+        quote_amount: u64,
+    ) -> (Pubkey, Wallet) {
+        let pool_key = Pubkey::new_unique();
+        let creator = self.wallet_for(base_mint, quote_mint, base_amount, quote_amount);
+        let instructions = initialize_pool_with_liquidity_with_rent(
+            &pool_key,
+            &creator.key,
+            &base_mint,
+            &creator.base_account,
+            &quote_mint,
+            &creator.quote_account,
+            params,
+            base_amount,
+            quote_amount,
+            self.runtime.rent(),
+        );
+        self.runtime
+            .process_transaction(&instructions, &[creator.key, pool_key])
+            .unwrap();
+        (pool_key, creator)
+    }
+
+    pub fn wallet(&mut self, base_amount: u64, quote_amount: u64) -> Wallet {
+        self.wallet_for(self.base_mint, self.quote_mint, base_amount, quote_amount)
+    }
+
+    /// A wallet holding tokens of any two mints, such as those of another pool
+    pub fn wallet_for(
+        &mut self,
+        base_mint: Pubkey,
+        quote_mint: Pubkey,
+        base_amount: u64,
+        quote_amount: u64,
+    ) -> Wallet {
+        let wallet = Wallet {
+            key: Pubkey::new_unique(),
+            base_account: Pubkey::new_unique(),
+            quote_account: Pubkey::new_unique(),
+        };
+        self.runtime.airdrop(&wallet.key, LAMPORTS);
+        self.runtime
+            .create_token_account(wallet.base_account, base_mint, wallet.key, base_amount);
+        self.runtime.create_token_account(
+            wallet.quote_account,
+            quote_mint,
+            wallet.key,
+            quote_amount,
+        );
+        wallet
+    }
+
+    pub fn amm(&self) -> Amm {
+        self.runtime.pool(&self.pool_key).unwrap().amm
+    }
+
+    pub fn balances(&self, wallet: &Wallet) -> (u64, u64) {
+        (
+            self.runtime.token_balance(&wallet.base_account).unwrap(),
+            self.runtime.token_balance(&wallet.quote_account).unwrap(),
+        )
+    }
+
+    pub fn vault_balances(&self) -> (u64, u64) {
+        (
+            self.runtime
+                .token_balance(&get_vault_address(&ID, &self.pool_key, &self.base_mint).0)
+                .unwrap(),
+            self.runtime
+                .token_balance(&get_vault_address(&ID, &self.pool_key, &self.quote_mint).0)
+                .unwrap(),
+        )
+    }
+
+    pub fn swap(&mut self, wallet: &Wallet, params: SwapParams) -> Result<(), ProcessorError> {
+        let instruction = swap(
+            &self.pool_key,
+            &wallet.key,
+            &self.base_mint,
+            &self.quote_mint,
+            &wallet.base_account,
+            &wallet.quote_account,
+            params,
+        );
+        self.runtime
+            .process_transaction(&[instruction], &[wallet.key])
+    }
+
+    pub fn add_liquidity(
+        &mut self,
+        wallet: &Wallet,
+        base_amount: u64,
+        quote_amount: u64,
+    ) -> Result<(), ProcessorError> {
+        let instruction = add_liquidity(
+            &self.pool_key,
+            &wallet.key,
+            &self.base_mint,
+            &wallet.base_account,
+            &self.quote_mint,
+            &wallet.quote_account,
+            AddLiquidityParams {
+                desired_base_amount_in: base_amount,
+                desired_quote_amount_in: quote_amount,
+                initial_lp_shares: None,
+            },
+        );
+        self.runtime
+            .process_transaction(&[instruction], &[wallet.key])
+    }
+
+    pub fn remove_liquidity(&mut self, wallet: &Wallet, shares: u64) -> Result<(), ProcessorError> {
+        let instruction = remove_liquidity(
+            &self.pool_key,
+            &wallet.key,
+            &self.base_mint,
+            &self.quote_mint,
+            &wallet.base_account,
+            &wallet.quote_account,
+            shares,
+        );
+        self.runtime
+            .process_transaction(&[instruction], &[wallet.key])
+    }
+
+    pub fn initialize_lp_position(&mut self, owner: &Pubkey) {
+        let instruction = initialize_lp_position(&self.pool_key, &self.creator.key, owner);
+        self.runtime
+            .process_transaction(&[instruction], &[self.creator.key])
+            .unwrap();
+    }
+
+    /// Vault balances must cover the reserves plus every fee that has not left the pool
+    pub fn assert_vaults_cover_reserves(&self) {
+        let amm = self.amm();
+        let (base_vault, quote_vault) = self.vault_balances();
+        assert_eq!(base_vault, amm.base_reserves);
+        assert_eq!(
+            quote_vault,
+            amm.quote_reserves + amm.cumulative_quote_lp_fees + amm.cumulative_quote_protocol_fees
+        );
+    }
+}
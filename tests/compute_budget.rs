@@ -0,0 +1,205 @@
+#![cfg(feature = "client")]
+
+use plasma_sdk::plasma::{
+    InitializePoolParams, Side, SwapParams, SwapType,
+    compute_budget::{
+        ComputeUnitTable, CpiCosts, DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT, FixedPriorityFee,
+        MAX_BUILTIN_INSTRUCTION_COMPUTE_UNIT_LIMIT, MAX_COMPUTE_UNIT_LIMIT, TotalPriorityFee,
+        priority_fee, simulate_compute_units, with_compute_budget,
+    },
+    initialize_lp_position, initialize_pool_with_liquidity_with_rent,
+    processor::ProcessorError,
+    reference::ReferenceRuntime,
+    remove_liquidity, swap, transfer_liquidity,
+};
+use solana_program::{instruction::Instruction, pubkey::Pubkey};
+use solana_sdk::compute_budget::{self, ComputeBudgetInstruction};
+
+const AMOUNT: u64 = 1_000_000_000_000;
+
+struct Fixture {
+    runtime: ReferenceRuntime,
+    creator: Pubkey,
+    pool_key: Pubkey,
+    base_mint: Pubkey,
+    quote_mint: Pubkey,
+    base_account: Pubkey,
+    quote_account: Pubkey,
+    setup: Vec<Instruction>,
+}
+
+fn setup() -> Fixture {
+    let mut runtime = ReferenceRuntime::new();
+    runtime.set_slot(100);
+    let creator = Pubkey::new_unique();
+    let pool_key = Pubkey::new_unique();
+    let (base_mint, quote_mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (base_account, quote_account) = (Pubkey::new_unique(), Pubkey::new_unique());
+    runtime.airdrop(&creator, 10_000_000_000);
+    runtime.create_mint(base_mint, 9);
+    runtime.create_mint(quote_mint, 6);
+    runtime.create_token_account(base_account, base_mint, creator, AMOUNT);
+    runtime.create_token_account(quote_account, quote_mint, creator, AMOUNT);
+    let setup = initialize_pool_with_liquidity_with_rent(
+        &pool_key,
+        &creator,
+        &base_mint,
+        &base_account,
+        &quote_mint,
+        &quote_account,
+        InitializePoolParams {
+            lp_fee_in_bps: 30,
+            protocol_fee_allocation_in_pct: 20,
+            fee_recipients_params: Default::default(),
+            num_slots_to_vest_lp_shares: None,
+        },
+        AMOUNT / 2,
+        AMOUNT / 2,
+        runtime.rent(),
+    );
+    Fixture {
+        runtime,
+        creator,
+        pool_key,
+        base_mint,
+        quote_mint,
+        base_account,
+        quote_account,
+        setup,
+    }
+}
+
+#[test]
+fn simulated_estimates_stay_within_the_table() {
+    let mut fixture = setup();
+    let table = ComputeUnitTable::CONSERVATIVE;
+    let costs = CpiCosts {
+        instruction: 10_000,
+        token_transfer: 6_500,
+        create_account: 3_500,
+        create_token_account: 9_000,
+    };
+    let signers = [fixture.creator, fixture.pool_key];
+    let units =
+        simulate_compute_units(&fixture.runtime, &fixture.setup, &signers, &costs, &table).unwrap();
+    assert_eq!(units.len(), fixture.setup.len());
+    for (instruction, units) in fixture.setup.iter().zip(units.iter()) {
+        assert!(*units <= table.estimate(instruction));
+    }
+    // Simulation does not modify the runtime
+    assert!(fixture.runtime.pool(&fixture.pool_key).is_none());
+    fixture
+        .runtime
+        .process_transaction(&fixture.setup, &signers)
+        .unwrap();
+
+    let other = Pubkey::new_unique();
+    let flow = [
+        swap(
+            &fixture.pool_key,
+            &fixture.creator,
+            &fixture.base_mint,
+            &fixture.quote_mint,
+            &fixture.base_account,
+            &fixture.quote_account,
+            SwapParams {
+                side: Side::Buy,
+                swap_type: SwapType::ExactIn {
+                    amount_in: 1_000_000,
+                    min_amount_out: 0,
+                },
+            },
+        ),
+        remove_liquidity(
+            &fixture.pool_key,
+            &fixture.creator,
+            &fixture.base_mint,
+            &fixture.quote_mint,
+            &fixture.base_account,
+            &fixture.quote_account,
+            1_000,
+        ),
+        initialize_lp_position(&fixture.pool_key, &fixture.creator, &other),
+        transfer_liquidity(&fixture.pool_key, &fixture.creator, &other),
+    ];
+    let units = simulate_compute_units(&fixture.runtime, &flow, &[fixture.creator], &costs, &table)
+        .unwrap();
+    // A swap makes two token transfers
+    assert_eq!(units[0], costs.instruction + 2 * costs.token_transfer);
+    for (instruction, units) in flow.iter().zip(units.iter()) {
+        assert!(*units <= table.estimate(instruction));
+    }
+    assert_eq!(
+        table.estimate_transaction(&with_compute_budget(
+            &flow,
+            1,
+            &FixedPriorityFee {
+                micro_lamports_per_compute_unit: 1,
+            }
+        )),
+        flow.iter().map(|ix| table.estimate(ix)).sum::<u32>() + 2 * table.builtin
+    );
+
+    // Failing transactions have no estimate
+    assert_eq!(
+        simulate_compute_units(&fixture.runtime, &flow[1..], &[], &costs, &table).unwrap_err(),
+        ProcessorError::MissingRequiredSignature(fixture.creator)
+    );
+    assert_eq!(
+        table.estimate(&Instruction::new_with_bytes(
+            Pubkey::new_unique(),
+            &[],
+            vec![]
+        )),
+        table.other
+    );
+}
+
+#[test]
+fn compute_budget_instructions_follow_the_policy() {
+    let fixture = setup();
+    let limit = ComputeUnitTable::CONSERVATIVE.estimate_transaction(&fixture.setup);
+    // The two budget instructions and `create_account`, then three Plasma instructions
+    assert_eq!(
+        limit,
+        3 * MAX_BUILTIN_INSTRUCTION_COMPUTE_UNIT_LIMIT + 3 * DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT
+    );
+
+    let instructions = with_compute_budget(
+        &fixture.setup,
+        limit,
+        &TotalPriorityFee { lamports: 10_000 },
+    );
+    assert_eq!(
+        instructions[0],
+        ComputeBudgetInstruction::set_compute_unit_limit(limit)
+    );
+    let price = 10_000 * 1_000_000 / limit as u64;
+    assert_eq!(
+        instructions[1],
+        ComputeBudgetInstruction::set_compute_unit_price(price)
+    );
+    assert!(priority_fee(limit, price) <= 10_000);
+    assert_eq!(&instructions[2..], &fixture.setup[..]);
+
+    // Existing budget instructions are replaced, and a zero price is left out
+    let instructions = with_compute_budget(&instructions, MAX_COMPUTE_UNIT_LIMIT + 1, &|_| 0);
+    assert_eq!(
+        instructions[0],
+        ComputeBudgetInstruction::set_compute_unit_limit(MAX_COMPUTE_UNIT_LIMIT)
+    );
+    assert_eq!(
+        instructions
+            .iter()
+            .filter(|ix| ix.program_id == compute_budget::ID)
+            .count(),
+        1
+    );
+
+    // The reference runtime accepts budgeted transactions
+    let mut runtime = fixture.runtime.clone();
+    runtime
+        .process_transaction(&instructions, &[fixture.creator, fixture.pool_key])
+        .unwrap();
+    assert!(runtime.pool(&fixture.pool_key).is_some());
+}
@@ -0,0 +1,207 @@
+#![cfg(feature = "client")]
+
+use plasma_sdk::plasma::{
+    InitializePoolParams, Side, SwapParams, SwapType,
+    explain::{Action, ExplainError, TokenAmount, TokenSymbols, explain_instruction},
+    processor::ProcessorError,
+    remove_liquidity, swap,
+    validation::Issue,
+};
+use solana_program::pubkey::Pubkey;
+
+const SOL: u64 = 1_000_000_000;
+const USDC: u64 = 1_000_000;
+
+mod common;
+
+use common::{Fixture, Wallet};
+
+/// A pool of 1,000 SOL and 150,000 USDC, and a trader holding 10,000 SOL and 1,000,000 USDC
+fn setup() -> (Fixture, Wallet) {
+    let mut fixture = Fixture::with_liquidity(
+        InitializePoolParams {
+            lp_fee_in_bps: 30,
+            protocol_fee_allocation_in_pct: 20,
+            ..Default::default()
+        },
+        1_000 * SOL,
+        150_000 * USDC,
+    );
+    let trader = fixture.wallet(10_000 * SOL, 1_000_000 * USDC);
+    (fixture, trader)
+}
+
+#[test]
+fn token_amounts_use_the_token_decimals() {
+    let amount = |amount, decimals| {
+        TokenAmount {
+            mint: Pubkey::default(),
+            amount,
+            decimals,
+        }
+        .to_string()
+    };
+    assert_eq!(amount(2_000 * USDC, 6), "2,000");
+    assert_eq!(amount(12 * SOL + SOL / 2, 9), "12.5");
+    assert_eq!(amount(1_234_567 * USDC + 10, 6), "1,234,567.00001");
+    assert_eq!(amount(1, 6), "0.000001");
+    assert_eq!(amount(0, 9), "0");
+    assert_eq!(amount(999, 0), "999");
+
+    // Scales past `u128` leave only a fraction
+    assert_eq!(amount(u64::MAX, 38), format!("0.{:0>38}", u64::MAX));
+    assert_eq!(amount(1_200, 39), format!("0.{}12", "0".repeat(35)));
+    assert_eq!(amount(u64::MAX, 60), format!("0.{:0>60}", u64::MAX));
+    let ui_amount = |amount, decimals| {
+        TokenAmount {
+            mint: Pubkey::default(),
+            amount,
+            decimals,
+        }
+        .ui_amount()
+    };
+    assert_eq!(ui_amount(12 * SOL + SOL / 2, 9), 12.5);
+    assert_eq!(ui_amount(u64::MAX, 400), 0.0);
+    assert_eq!(ui_amount(u64::MAX, u32::MAX), 0.0);
+}
+
+#[test]
+fn swaps_and_withdrawals_are_explained_with_simulated_amounts() {
+    let (mut fixture, trader) = setup();
+    let symbols = TokenSymbols::new("SOL", "USDC");
+    let pool = fixture.runtime.pool(&fixture.pool_key).unwrap();
+    let instruction = swap(
+        &fixture.pool_key,
+        &trader.key,
+        &fixture.base_mint,
+        &fixture.quote_mint,
+        &trader.base_account,
+        &trader.quote_account,
+        SwapParams {
+            side: Side::Buy,
+            swap_type: SwapType::ExactIn {
+                amount_in: 2_000 * USDC,
+                min_amount_out: 12 * SOL + SOL / 2,
+            },
+        },
+    );
+    let explanation =
+        explain_instruction(&instruction, &pool, fixture.runtime.slot(), &symbols).unwrap();
+    assert_eq!(explanation.signer, trader.key);
+    let Action::Swap {
+        expected_out, fee, ..
+    } = explanation.action
+    else {
+        panic!("expected a swap, got {:?}", explanation.action);
+    };
+    assert_eq!(
+        explanation.text,
+        format!(
+            "Buy at least 12.5 SOL for exactly 2,000 USDC from pool {} (≈ {} SOL expected), \
+             fee ≈ {} USDC",
+            fixture.pool_key, expected_out, fee
+        )
+    );
+    assert!(fee.ui_amount() > 5.0 && fee.ui_amount() < 7.0);
+
+    // The simulation matches what the program transfers
+    let base_before = fixture.runtime.token_balance(&trader.base_account).unwrap();
+    fixture
+        .runtime
+        .process_transaction(&[instruction], &[trader.key])
+        .unwrap();
+    assert_eq!(
+        fixture.runtime.token_balance(&trader.base_account).unwrap() - base_before,
+        expected_out.amount
+    );
+
+    // The creator holds every LP share
+    let creator = fixture.creator;
+    let pool = fixture.runtime.pool(&fixture.pool_key).unwrap();
+    let instruction = remove_liquidity(
+        &fixture.pool_key,
+        &creator.key,
+        &fixture.base_mint,
+        &fixture.quote_mint,
+        &creator.base_account,
+        &creator.quote_account,
+        1_000,
+    );
+    let explanation =
+        explain_instruction(&instruction, &pool, fixture.runtime.slot(), &symbols).unwrap();
+    let Action::RemoveLiquidity {
+        expected_base,
+        expected_quote,
+        ..
+    } = explanation.action
+    else {
+        panic!("expected a withdrawal, got {:?}", explanation.action);
+    };
+    assert_eq!(
+        explanation.to_string(),
+        format!(
+            "Remove 1,000 LP shares ≈ {} SOL + {} USDC from pool {}",
+            expected_base, expected_quote, fixture.pool_key
+        )
+    );
+    let quote_before = fixture
+        .runtime
+        .token_balance(&creator.quote_account)
+        .unwrap();
+    fixture
+        .runtime
+        .process_transaction(&[instruction], &[creator.key])
+        .unwrap();
+    assert_eq!(
+        fixture
+            .runtime
+            .token_balance(&creator.quote_account)
+            .unwrap()
+            - quote_before,
+        expected_quote.amount
+    );
+
+    // Symbols default to abbreviated mints
+    let mint = fixture.base_mint.to_string();
+    assert_eq!(
+        TokenSymbols::from_mints(&pool).base,
+        format!("{}…{}", &mint[..4], &mint[mint.len() - 4..])
+    );
+}
+
+#[test]
+fn rejected_instructions_are_not_explained() {
+    let (fixture, trader) = setup();
+    let symbols = TokenSymbols::new("SOL", "USDC");
+    let pool = fixture.runtime.pool(&fixture.pool_key).unwrap();
+    let mut instruction = swap(
+        &fixture.pool_key,
+        &trader.key,
+        &fixture.base_mint,
+        &fixture.quote_mint,
+        &trader.base_account,
+        &trader.quote_account,
+        SwapParams {
+            side: Side::Sell,
+            swap_type: SwapType::ExactOut {
+                amount_out: 100 * USDC,
+                max_amount_in: 1,
+            },
+        },
+    );
+    let error = explain_instruction(&instruction, &pool, 100, &symbols).unwrap_err();
+    assert!(matches!(
+        error,
+        ExplainError::Simulation(ProcessorError::SlippageExceeded { limit: 1, .. })
+    ));
+
+    instruction.accounts[3].is_signer = false;
+    assert_eq!(
+        explain_instruction(&instruction, &pool, 100, &symbols).unwrap_err(),
+        ExplainError::Invalid(vec![Issue::SignerMismatch {
+            index: 3,
+            pubkey: trader.key,
+            expected: true,
+        }])
+    );
+}
@@ -0,0 +1,282 @@
+#![cfg(feature = "client")]
+
+use plasma_sdk::plasma::{
+    ID, InitializePoolParams, ProtocolFeeRecipientParams,
+    filters::{
+        AccountFilter, lp_position_filters, lp_position_keys, matches_all, pool_filters,
+        pools_by_base_mint, pools_by_mint, pools_by_mints, pools_by_quote_mint,
+    },
+    get_lp_position_address, initialize_pool_with_liquidity_with_rent,
+    layout::*,
+    reference::ReferenceRuntime,
+};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_program::pubkey::Pubkey;
+
+struct Pool {
+    key: Pubkey,
+    base_mint: Pubkey,
+    quote_mint: Pubkey,
+}
+
+fn create_pool(
+    runtime: &mut ReferenceRuntime,
+    creator: &Pubkey,
+    base_mint: Pubkey,
+    quote_mint: Pubkey,
+) -> Pool {
+    let key = Pubkey::new_unique();
+    let base_account = Pubkey::new_unique();
+    let quote_account = Pubkey::new_unique();
+    runtime.create_token_account(base_account, base_mint, *creator, 1_000_000_000);
+    runtime.create_token_account(quote_account, quote_mint, *creator, 1_000_000_000);
+    let mut fee_recipients_params = [ProtocolFeeRecipientParams::default(); 3];
+    fee_recipients_params[1] = ProtocolFeeRecipientParams {
+        recipient: Pubkey::new_unique(),
+        shares: 7,
+    };
+    let instructions = initialize_pool_with_liquidity_with_rent(
+        &key,
+        creator,
+        &base_mint,
+        &base_account,
+        &quote_mint,
+        &quote_account,
+        InitializePoolParams {
+            lp_fee_in_bps: 30,
+            protocol_fee_allocation_in_pct: 20,
+            fee_recipients_params,
+            num_slots_to_vest_lp_shares: Some(8),
+        },
+        1_000_000_000,
+        1_000_000_000,
+        runtime.rent(),
+    );
+    runtime
+        .process_transaction(&instructions, &[*creator, key])
+        .unwrap();
+    Pool {
+        key,
+        base_mint,
+        quote_mint,
+    }
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_pubkey(data: &[u8], offset: usize) -> Pubkey {
+    Pubkey::try_from(&data[offset..offset + 32]).unwrap()
+}
+
+fn to_rpc(filter: &AccountFilter) -> RpcFilterType {
+    match filter {
+        AccountFilter::DataSize(size) => RpcFilterType::DataSize(*size),
+        AccountFilter::Memcmp { offset, bytes } => {
+            RpcFilterType::Memcmp(Memcmp::new_raw_bytes(*offset, bytes.clone()))
+        }
+    }
+}
+
+/// How an RPC node evaluates the converted filter
+fn rpc_allows(filter: &RpcFilterType, data: &[u8]) -> bool {
+    match filter {
+        RpcFilterType::DataSize(size) => data.len() as u64 == *size,
+        RpcFilterType::Memcmp(memcmp) => memcmp.bytes_match(data),
+        RpcFilterType::TokenAccountState => unreachable!(),
+    }
+}
+
+#[test]
+fn offsets_read_the_same_values_as_borsh() {
+    let mut runtime = ReferenceRuntime::new();
+    runtime.set_slot(100);
+    let creator = Pubkey::new_unique();
+    let (base_mint, quote_mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+    runtime.airdrop(&creator, 10_000_000_000);
+    runtime.create_mint(base_mint, 9);
+    runtime.create_mint(quote_mint, 6);
+    let pool = create_pool(&mut runtime, &creator, base_mint, quote_mint);
+    let data = &runtime.get_account(&pool.key).unwrap().data;
+    let account = runtime.pool(&pool.key).unwrap();
+    let (header, amm) = (&account.header, &account.amm);
+
+    assert_eq!(data[DISCRIMINATOR_OFFSET..][..8], header.discriminator);
+    assert_eq!(
+        read_u64(data, SEQUENCE_NUMBER_OFFSET),
+        header.sequence_number
+    );
+    for (params, decimals, bump, mint, vault) in [
+        (
+            &header.base_params,
+            BASE_DECIMALS_OFFSET,
+            BASE_VAULT_BUMP_OFFSET,
+            BASE_MINT_OFFSET,
+            BASE_VAULT_OFFSET,
+        ),
+        (
+            &header.quote_params,
+            QUOTE_DECIMALS_OFFSET,
+            QUOTE_VAULT_BUMP_OFFSET,
+            QUOTE_MINT_OFFSET,
+            QUOTE_VAULT_OFFSET,
+        ),
+    ] {
+        assert_eq!(read_u32(data, decimals), params.decimals);
+        assert_eq!(read_u32(data, bump), params.vault_bump);
+        assert_eq!(read_pubkey(data, mint), params.mint_key);
+        assert_eq!(read_pubkey(data, vault), params.vault_key);
+    }
+    for (index, recipient) in header.fee_recipients.recipients.iter().enumerate() {
+        let offset = fee_recipient_offset(index);
+        assert_eq!(
+            read_pubkey(data, offset + FEE_RECIPIENT_RECIPIENT_OFFSET),
+            recipient.recipient
+        );
+        assert_eq!(
+            read_u64(data, offset + FEE_RECIPIENT_SHARES_OFFSET),
+            recipient.shares
+        );
+    }
+    assert_eq!(
+        read_u64(data, SWAP_SEQUENCE_NUMBER_OFFSET),
+        header.swap_sequence_number
+    );
+
+    assert_eq!(read_u32(data, FEE_IN_BPS_OFFSET), amm.fee_in_bps);
+    assert_eq!(
+        read_u32(data, PROTOCOL_ALLOCATION_IN_PCT_OFFSET),
+        amm.protocol_allocation_in_pct()
+    );
+    assert_eq!(
+        read_u64(data, LP_VESTING_WINDOW_OFFSET),
+        amm.lp_vesting_window
+    );
+    assert_eq!(
+        i128::from_le_bytes(data[REWARD_FACTOR_OFFSET..][..16].try_into().unwrap()),
+        amm.reward_factor.to_bits()
+    );
+    assert_eq!(read_u64(data, TOTAL_LP_SHARES_OFFSET), amm.total_lp_shares);
+    assert_eq!(read_u64(data, SLOT_SNAPSHOT_OFFSET), amm.get_slot());
+    for (offset, value) in [
+        (BASE_RESERVES_SNAPSHOT_OFFSET, amm.base_reserves_snapshot),
+        (QUOTE_RESERVES_SNAPSHOT_OFFSET, amm.quote_reserves_snapshot),
+        (BASE_RESERVES_OFFSET, amm.base_reserves),
+        (QUOTE_RESERVES_OFFSET, amm.quote_reserves),
+        (
+            CUMULATIVE_QUOTE_LP_FEES_OFFSET,
+            amm.cumulative_quote_lp_fees,
+        ),
+        (
+            CUMULATIVE_QUOTE_PROTOCOL_FEES_OFFSET,
+            amm.cumulative_quote_protocol_fees,
+        ),
+    ] {
+        assert_eq!(read_u64(data, offset), value);
+    }
+
+    let lp_position_key = get_lp_position_address(&ID, &pool.key, &creator).0;
+    let data = &runtime.get_account(&lp_position_key).unwrap().data;
+    let lp_position = runtime.lp_position(&pool.key, &creator).unwrap();
+    assert_eq!(
+        i128::from_le_bytes(
+            data[LP_REWARD_FACTOR_SNAPSHOT_OFFSET..][..16]
+                .try_into()
+                .unwrap()
+        ),
+        lp_position.reward_factor_snapshot().to_bits()
+    );
+    assert_eq!(read_u64(data, LP_SHARES_OFFSET), lp_position.lp_shares);
+    assert_eq!(
+        read_u64(data, LP_WITHDRAWABLE_LP_SHARES_OFFSET),
+        lp_position.withdrawable_lp_shares
+    );
+    assert_eq!(
+        read_u64(data, LP_UNCOLLECTED_FEES_OFFSET),
+        lp_position.uncollected_fees()
+    );
+    assert_eq!(
+        read_u64(data, LP_COLLECTED_FEES_OFFSET),
+        lp_position.collected_fees()
+    );
+    assert_eq!(
+        (
+            read_u64(data, LP_PENDING_SHARES_TO_VEST_OFFSET),
+            read_u64(data, LP_PENDING_SHARES_TO_VEST_OFFSET + 8)
+        ),
+        lp_position.pending_shares_to_vest
+    );
+}
+
+#[test]
+fn filters_select_pools_by_mint() {
+    let mut runtime = ReferenceRuntime::new();
+    let creator = Pubkey::new_unique();
+    runtime.airdrop(&creator, 10_000_000_000);
+    let mints = [(); 3].map(|_| Pubkey::new_unique());
+    for mint in mints {
+        runtime.create_mint(mint, 6);
+    }
+    let [a, b, c] = mints;
+    let pools = [
+        create_pool(&mut runtime, &creator, a, b),
+        create_pool(&mut runtime, &creator, b, c),
+        create_pool(&mut runtime, &creator, a, c),
+    ];
+
+    // Every account the program owns, the way `getProgramAccounts` would see them
+    let mut program_accounts = pools.iter().map(|pool| pool.key).collect::<Vec<_>>();
+    program_accounts.extend(lp_position_keys(
+        &pools.iter().map(|pool| pool.key).collect::<Vec<_>>(),
+        &[creator],
+    ));
+    let scan = |filters: &[AccountFilter]| {
+        let mut keys = vec![];
+        for key in program_accounts.iter() {
+            let account = runtime.get_account(key).unwrap();
+            assert_eq!(account.owner, ID);
+            let allowed = matches_all(filters, &account.data);
+            assert_eq!(
+                filters
+                    .iter()
+                    .map(to_rpc)
+                    .all(|filter| rpc_allows(&filter, &account.data)),
+                allowed
+            );
+            if allowed {
+                keys.push(*key);
+            }
+        }
+        keys
+    };
+
+    assert_eq!(scan(&pool_filters()).len(), 3);
+    assert_eq!(scan(&lp_position_filters()).len(), 3);
+    assert_eq!(
+        scan(&pools_by_base_mint(&a)),
+        vec![pools[0].key, pools[2].key]
+    );
+    assert_eq!(
+        scan(&pools_by_quote_mint(&c)),
+        vec![pools[1].key, pools[2].key]
+    );
+    assert_eq!(scan(&pools_by_mints(&b, &c)), vec![pools[1].key]);
+    assert!(scan(&pools_by_mints(&c, &b)).is_empty());
+    let [as_base, as_quote] = pools_by_mint(&b);
+    assert_eq!(scan(&as_base), vec![pools[1].key]);
+    assert_eq!(scan(&as_quote), vec![pools[0].key]);
+    for pool in pools.iter() {
+        assert_eq!(
+            scan(&pools_by_mints(&pool.base_mint, &pool.quote_mint)),
+            vec![pool.key]
+        );
+    }
+
+    // A memcmp past the end of the data never matches
+    assert!(!AccountFilter::memcmp(620, &[0; 8]).matches(&[0; 624]));
+}
@@ -0,0 +1,246 @@
+#![cfg(feature = "client")]
+
+use plasma_sdk::plasma::{
+    ID, InitializePoolParams, Side, SwapParams, SwapType,
+    lookup_table::{
+        MAX_ADDRESSES_PER_EXTEND, compile_v0_message, decode_lookup_table, lookup_table_addresses,
+        lookup_table_instructions, missing_lookup_table_addresses,
+    },
+    swap,
+};
+use solana_address_lookup_table_interface as address_lookup_table;
+use solana_program::{
+    hash::Hash,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+use solana_sdk::{
+    message::{AddressLookupTableAccount, Message, VersionedMessage},
+    transaction::Transaction,
+};
+
+mod common;
+
+use common::{BASE_AMOUNT, Fixture};
+
+fn params() -> InitializePoolParams {
+    InitializePoolParams {
+        lp_fee_in_bps: 30,
+        protocol_fee_allocation_in_pct: 20,
+        ..Default::default()
+    }
+}
+
+/// Rebuilds the instructions of a v0 message the way the runtime loads them
+fn resolve(
+    message: &VersionedMessage,
+    lookup_tables: &[AddressLookupTableAccount],
+) -> Vec<Instruction> {
+    let VersionedMessage::V0(message) = message else {
+        panic!("expected a v0 message");
+    };
+    let mut writable = vec![];
+    let mut readonly = vec![];
+    for lookup in message.address_table_lookups.iter() {
+        let table = lookup_tables
+            .iter()
+            .find(|table| table.key == lookup.account_key)
+            .unwrap();
+        let load = |indexes: &[u8]| {
+            indexes
+                .iter()
+                .map(|index| table.addresses[*index as usize])
+                .collect::<Vec<_>>()
+        };
+        writable.extend(load(&lookup.writable_indexes));
+        readonly.extend(load(&lookup.readonly_indexes));
+    }
+    let num_static = message.account_keys.len();
+    let keys = [message.account_keys.clone(), writable.clone(), readonly].concat();
+    message
+        .instructions
+        .iter()
+        .map(|ix| Instruction {
+            program_id: keys[ix.program_id_index as usize],
+            accounts: ix
+                .accounts
+                .iter()
+                .map(|index| {
+                    let index = *index as usize;
+                    let is_writable = if index < num_static {
+                        message.is_maybe_writable(index, None)
+                    } else {
+                        index < num_static + writable.len()
+                    };
+                    let meta = if is_writable {
+                        AccountMeta::new
+                    } else {
+                        AccountMeta::new_readonly
+                    };
+                    meta(
+                        keys[index],
+                        index < message.header.num_required_signatures as usize,
+                    )
+                })
+                .collect(),
+            data: ix.data.clone(),
+        })
+        .collect()
+}
+
+#[test]
+fn multi_pool_route_compiles_to_a_smaller_v0_message() {
+    // Three pools chained by their mints
+    let mut fixture = Fixture::with_liquidity(params(), BASE_AMOUNT, BASE_AMOUNT);
+    let mints = [
+        fixture.base_mint,
+        fixture.quote_mint,
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    ];
+    for mint in &mints[2..] {
+        fixture.runtime.create_mint(*mint, 6);
+    }
+    let pools = [
+        (fixture.pool_key, 0),
+        (
+            fixture.launch_pool(mints[1], mints[2], params(), BASE_AMOUNT, BASE_AMOUNT),
+            1,
+        ),
+        (
+            fixture.launch_pool(mints[2], mints[3], params(), BASE_AMOUNT, BASE_AMOUNT),
+            2,
+        ),
+    ];
+    let wallet = fixture.wallet(BASE_AMOUNT, BASE_AMOUNT);
+    let trader = wallet.key;
+    let trader_accounts = [
+        wallet.base_account,
+        wallet.quote_account,
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    ];
+    for (account, mint) in trader_accounts[2..].iter().zip(&mints[2..]) {
+        fixture
+            .runtime
+            .create_token_account(*account, *mint, trader, BASE_AMOUNT);
+    }
+    let runtime = &mut fixture.runtime;
+
+    let headers = pools
+        .iter()
+        .map(|(key, _)| (*key, runtime.pool(key).unwrap().header))
+        .collect::<Vec<_>>();
+    let addresses = lookup_table_addresses(
+        &headers
+            .iter()
+            .map(|(key, header)| (*key, header))
+            .collect::<Vec<_>>(),
+    );
+    // Shared accounts plus the pool and vaults of every pool
+    assert_eq!(addresses.len(), 2 + 3 * 3);
+    assert!(!addresses.contains(&ID));
+    let lookup_tables = [AddressLookupTableAccount {
+        key: Pubkey::new_unique(),
+        addresses,
+    }];
+
+    // Sell the base of each pool for its quote, which is the base of the next pool
+    let route = pools
+        .iter()
+        .map(|(key, hop)| {
+            swap(
+                key,
+                &trader,
+                &mints[*hop],
+                &mints[*hop + 1],
+                &trader_accounts[*hop],
+                &trader_accounts[*hop + 1],
+                SwapParams {
+                    side: Side::Sell,
+                    swap_type: SwapType::ExactIn {
+                        amount_in: 1_000_000,
+                        min_amount_out: 0,
+                    },
+                },
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        missing_lookup_table_addresses(&route, &lookup_tables),
+        trader_accounts
+    );
+
+    let blockhash = Hash::new_unique();
+    let message = compile_v0_message(&trader, &route, &lookup_tables, blockhash).unwrap();
+    let VersionedMessage::V0(v0) = &message else {
+        unreachable!()
+    };
+    // The payer, the program and the trader token accounts stay static
+    assert_eq!(v0.account_keys.len(), 2 + 4);
+    assert_eq!(v0.account_keys[0], trader);
+    assert!(v0.account_keys.contains(&ID));
+    let legacy = Message::new_with_blockhash(&route, Some(&trader), &blockhash);
+    // Each looked up account costs a one byte index instead of 32 bytes. The lookup itself costs
+    // the table key, the two index lengths and the lookup count, and v0 adds a version byte.
+    assert_eq!(
+        legacy.serialize().len() - message.serialize().len(),
+        11 * 31 - 32 - 4
+    );
+
+    // The same instructions, except that the fee payer is always writable
+    let resolved = resolve(&message, &lookup_tables);
+    let mut expected = route.clone();
+    for meta in expected.iter_mut().flat_map(|ix| ix.accounts.iter_mut()) {
+        meta.is_writable |= meta.pubkey == trader;
+    }
+    assert_eq!(resolved, expected);
+    runtime.process_transaction(&resolved, &[trader]).unwrap();
+
+    // Without tables the route is compiled with static keys only
+    let message = compile_v0_message(&trader, &route[..1], &[], blockhash).unwrap();
+    assert!(message.address_table_lookups().unwrap().is_empty());
+    assert_eq!(
+        message.static_account_keys().len(),
+        Transaction::new_unsigned(Message::new(&route[..1], Some(&trader)))
+            .message
+            .account_keys
+            .len()
+    );
+}
+
+#[test]
+fn lookup_table_instructions_chunk_the_addresses() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let addresses = (0..45).map(|_| Pubkey::new_unique()).collect::<Vec<_>>();
+    let (lookup_table, instructions) =
+        lookup_table_instructions(&authority, &payer, 1_000, &addresses);
+    assert_eq!(
+        lookup_table,
+        address_lookup_table::instruction::derive_lookup_table_address(&authority, 1_000).0
+    );
+    assert_eq!(instructions.len(), 1 + 3);
+    for ix in instructions.iter() {
+        assert_eq!(ix.program_id, address_lookup_table::program::ID);
+        assert_eq!(ix.accounts[0].pubkey, lookup_table);
+    }
+    // Extend data is the discriminator and the length-prefixed addresses
+    let extended = instructions[1..]
+        .iter()
+        .flat_map(|ix| {
+            ix.data[12..]
+                .chunks(32)
+                .map(|key| Pubkey::try_from(key).unwrap())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(extended, addresses);
+    assert!(
+        instructions[1..]
+            .iter()
+            .all(|ix| ix.data.len() <= 12 + 32 * MAX_ADDRESSES_PER_EXTEND)
+    );
+
+    assert!(decode_lookup_table(&lookup_table, &[0; 8]).is_none());
+}
@@ -0,0 +1,244 @@
+#![cfg(feature = "client")]
+
+use plasma_sdk::plasma::oracle::{
+    IngestOutcome, OracleConfig, OracleError, OracleObservation, PriceOracle,
+};
+
+fn observation(sequence_number: u64, slot: u64, spot_price: f64) -> OracleObservation {
+    OracleObservation {
+        slot,
+        unix_timestamp: slot as i64,
+        sequence_number,
+        swap_sequence_number: sequence_number,
+        spot_price,
+        snapshot_price: spot_price / 2.0,
+    }
+}
+
+fn sequence_numbers(oracle: &PriceOracle) -> Vec<u64> {
+    oracle
+        .observations()
+        .map(|observation| observation.sequence_number)
+        .collect()
+}
+
+#[test]
+fn observations_ingested_out_of_order_are_sorted_by_sequence_number() {
+    let mut oracle = PriceOracle::default();
+    for (sequence_number, slot) in [(3, 30), (1, 10), (2, 20)] {
+        assert_eq!(
+            oracle.ingest_observation(observation(sequence_number, slot, 1.0)),
+            IngestOutcome::Accepted
+        );
+    }
+    assert_eq!(sequence_numbers(&oracle), [1, 2, 3]);
+    assert_eq!(oracle.latest().unwrap().slot, 30);
+
+    assert_eq!(
+        oracle.ingest_observation(observation(2, 20, 1.0)),
+        IngestOutcome::Duplicate
+    );
+    // Sequence number 4 cannot be older than sequence number 3, and 0 cannot be newer than 1
+    assert_eq!(
+        oracle.ingest_observation(observation(4, 29, 1.0)),
+        IngestOutcome::Inconsistent
+    );
+    assert_eq!(
+        oracle.ingest_observation(observation(0, 11, 1.0)),
+        IngestOutcome::Inconsistent
+    );
+    let mut swapped_ahead = observation(4, 40, 1.0);
+    swapped_ahead.swap_sequence_number = 2;
+    assert_eq!(
+        oracle.ingest_observation(swapped_ahead),
+        IngestOutcome::Inconsistent
+    );
+    assert_eq!(sequence_numbers(&oracle), [1, 2, 3]);
+}
+
+#[test]
+fn the_last_sequence_number_is_ingested() {
+    let mut oracle = PriceOracle::default();
+    assert_eq!(
+        oracle.ingest_observation(observation(u64::MAX, 20, 1.0)),
+        IngestOutcome::Accepted
+    );
+    assert_eq!(
+        oracle.ingest_observation(observation(u64::MAX - 1, 10, 1.0)),
+        IngestOutcome::Accepted
+    );
+    assert_eq!(
+        oracle.ingest_observation(observation(u64::MAX, 20, 1.0)),
+        IngestOutcome::Duplicate
+    );
+    assert_eq!(sequence_numbers(&oracle), [u64::MAX - 1, u64::MAX]);
+}
+
+#[test]
+fn spot_prices_far_from_the_recent_median_are_rejected() {
+    let mut oracle = PriceOracle::new(OracleConfig {
+        max_deviation_in_bps: Some(1_000),
+        outlier_lookback: 3,
+        ..Default::default()
+    });
+    for (sequence_number, price) in [(2, 100.0), (3, 101.0), (4, 99.0)] {
+        let outcome =
+            oracle.ingest_observation(observation(sequence_number, sequence_number, price));
+        assert_eq!(outcome, IngestOutcome::Accepted);
+    }
+
+    // The median of the last three prices is 100
+    assert_eq!(
+        oracle.ingest_observation(observation(5, 5, 111.0)),
+        IngestOutcome::Outlier
+    );
+    assert_eq!(
+        oracle.ingest_observation(observation(5, 5, 89.0)),
+        IngestOutcome::Outlier
+    );
+    assert_eq!(
+        oracle.ingest_observation(observation(5, 5, 109.0)),
+        IngestOutcome::Accepted
+    );
+    // Only observations preceding the sequence number count, and none precede a late first one
+    assert_eq!(
+        oracle.ingest_observation(observation(0, 0, 50.0)),
+        IngestOutcome::Accepted
+    );
+}
+
+#[test]
+fn observations_older_than_the_maximum_age_are_pruned() {
+    let mut oracle = PriceOracle::new(OracleConfig {
+        max_age_in_slots: 100,
+        ..Default::default()
+    });
+    for (sequence_number, slot) in [(1, 0), (2, 50), (3, 100)] {
+        oracle.ingest_observation(observation(sequence_number, slot, 1.0));
+    }
+    assert_eq!(sequence_numbers(&oracle), [1, 2, 3]);
+
+    // Slot 120 moves the cutoff to slot 20
+    assert_eq!(
+        oracle.ingest_observation(observation(4, 120, 1.0)),
+        IngestOutcome::Accepted
+    );
+    assert_eq!(sequence_numbers(&oracle), [2, 3, 4]);
+    assert_eq!(
+        oracle.ingest_observation(observation(1, 19, 1.0)),
+        IngestOutcome::Expired
+    );
+}
+
+#[test]
+fn any_price_deviates_from_a_zero_median() {
+    let mut oracle = PriceOracle::new(OracleConfig {
+        max_deviation_in_bps: Some(1_000),
+        outlier_lookback: 3,
+        ..Default::default()
+    });
+    for sequence_number in 1..=3 {
+        let outcome = oracle.ingest_observation(observation(sequence_number, sequence_number, 0.0));
+        assert_eq!(outcome, IngestOutcome::Accepted);
+    }
+    assert_eq!(
+        oracle.ingest_observation(observation(4, 4, 1.0)),
+        IngestOutcome::Outlier
+    );
+    assert_eq!(
+        oracle.ingest_observation(observation(4, 4, 0.0)),
+        IngestOutcome::Accepted
+    );
+}
+
+#[test]
+fn averages_weight_each_price_until_the_next_observation() {
+    let mut oracle = PriceOracle::default();
+    assert_eq!(
+        oracle.slot_weighted_average(100, 100),
+        Err(OracleError::NoObservations)
+    );
+
+    // Slots 0, 10 and 40 at 100, 120 and 130 seconds
+    for (sequence_number, slot, unix_timestamp, price) in
+        [(1, 0, 100, 1.0), (2, 10, 120, 2.0), (3, 40, 130, 4.0)]
+    {
+        let observation = OracleObservation {
+            unix_timestamp,
+            ..observation(sequence_number, slot, price)
+        };
+        oracle.ingest_observation(observation);
+    }
+
+    // 1.0 for 10 slots and 2.0 for 30 slots
+    let average = oracle.slot_weighted_average(40, 40).unwrap();
+    assert_eq!(average.spot_price, (1.0 * 10.0 + 2.0 * 30.0) / 40.0);
+    assert_eq!(average.snapshot_price, average.spot_price / 2.0);
+    // A longer window adds no weight before the first observation
+    assert_eq!(oracle.slot_weighted_average(40, 1_000).unwrap(), average);
+    // The last 20 slots only saw 2.0
+    assert_eq!(
+        oracle.slot_weighted_average(40, 20).unwrap().spot_price,
+        2.0
+    );
+    // The latest price holds until the query slot
+    assert_eq!(
+        oracle.slot_weighted_average(60, 60).unwrap().spot_price,
+        (1.0 * 10.0 + 2.0 * 30.0 + 4.0 * 20.0) / 60.0
+    );
+    // Observations after the query slot are not used
+    assert_eq!(
+        oracle.slot_weighted_average(20, 20).unwrap().spot_price,
+        (1.0 * 10.0 + 2.0 * 10.0) / 20.0
+    );
+
+    // 1.0 for 20 seconds and 2.0 for 10 seconds
+    assert_eq!(
+        oracle.time_weighted_average(130, 30).unwrap().spot_price,
+        (1.0 * 20.0 + 2.0 * 10.0) / 30.0
+    );
+    // 2.0 for the last 5 seconds
+    assert_eq!(
+        oracle.time_weighted_average(130, 5).unwrap().spot_price,
+        2.0
+    );
+    // 4.0 for the 10 seconds since the latest observation
+    assert_eq!(
+        oracle.time_weighted_average(140, 10).unwrap().spot_price,
+        4.0
+    );
+    assert_eq!(
+        oracle.time_weighted_average(99, 10),
+        Err(OracleError::NoObservations)
+    );
+    assert_eq!(
+        oracle.time_weighted_average(130, -1),
+        Err(OracleError::NegativeWindow(-1))
+    );
+
+    // An empty window returns the latest price known at its end
+    let mut oracle = PriceOracle::default();
+    oracle.ingest_observation(observation(1, 10, 1.0));
+    oracle.ingest_observation(observation(2, 10, 3.0));
+    assert_eq!(
+        oracle.slot_weighted_average(10, 100).unwrap().spot_price,
+        3.0
+    );
+    assert_eq!(oracle.slot_weighted_average(30, 0).unwrap().spot_price, 3.0);
+}
+
+#[test]
+fn a_refetch_of_unchanged_state_still_counts_towards_the_latest_price() {
+    let mut oracle = PriceOracle::default();
+    oracle.ingest_observation(observation(1, 0, 1.0));
+    oracle.ingest_observation(observation(2, 50, 3.0));
+    assert_eq!(
+        oracle.ingest_observation(observation(2, 100, 3.0)),
+        IngestOutcome::Duplicate
+    );
+    // 1.0 for 50 slots and 3.0 for 50 slots
+    assert_eq!(
+        oracle.slot_weighted_average(100, 100).unwrap().spot_price,
+        2.0
+    );
+}
@@ -0,0 +1,110 @@
+#![cfg(feature = "client")]
+
+use plasma_sdk::plasma::{
+    AddLiquidityParams, ID, InitializePoolParams, POOL_LEN, PlasmaStateError, add_liquidity,
+    initialize_lp_position, initialize_pool, initialize_pool_with_liquidity,
+    initialize_pool_with_liquidity_with_rent, plasma_amm::Amm,
+};
+use solana_program::{pubkey::Pubkey, rent::Rent};
+use solana_system_interface::instruction::create_account;
+
+#[test]
+fn initial_lp_shares_are_the_integer_square_root_of_k() {
+    for (base, quote, lp_shares) in [
+        (0, 1_000, 0),
+        (1, 1, 1),
+        (2, 3, 2),
+        (4, 9, 6),
+        (3, 5, 3),
+        // sqrt(1.5e23) = 387_298_334_620.74
+        (1_000_000_000_000, 150_000_000_000, 387_298_334_620),
+        (u64::MAX, 1, 4_294_967_295),
+        (u64::MAX, u64::MAX, u64::MAX),
+    ] {
+        assert_eq!(Amm::initial_lp_shares(base, quote), lp_shares);
+    }
+
+    // Only the root is accepted as the first deposit
+    let mut amm = Amm::new(30, 20, 0, 0);
+    assert_eq!(
+        amm.mint(0, 4, 9, Some(5)),
+        Err(PlasmaStateError::UnexpectedArgument)
+    );
+    assert_eq!(
+        amm.mint(0, 4, 9, Some(7)),
+        Err(PlasmaStateError::UnexpectedArgument)
+    );
+    assert_eq!(amm.mint(0, 4, 9, Some(6)), Ok((4, 9, 6)));
+}
+
+#[test]
+fn initialize_pool_with_liquidity_seeds_the_pool_in_one_transaction() {
+    let (pool_key, creator) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (base_mint, quote_mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (base_account, quote_account) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let params = InitializePoolParams {
+        lp_fee_in_bps: 30,
+        protocol_fee_allocation_in_pct: 20,
+        ..Default::default()
+    };
+    let instructions = initialize_pool_with_liquidity(
+        &pool_key,
+        &creator,
+        &base_mint,
+        &base_account,
+        &quote_mint,
+        &quote_account,
+        params,
+        4_000,
+        9_000,
+    );
+
+    let expected = |rent: &Rent| {
+        vec![
+            create_account(
+                &creator,
+                &pool_key,
+                rent.minimum_balance(POOL_LEN as usize),
+                POOL_LEN,
+                &ID,
+            ),
+            initialize_pool(&pool_key, &creator, &base_mint, &quote_mint, params),
+            initialize_lp_position(&pool_key, &creator, &creator),
+            add_liquidity(
+                &pool_key,
+                &creator,
+                &base_mint,
+                &base_account,
+                &quote_mint,
+                &quote_account,
+                AddLiquidityParams {
+                    desired_base_amount_in: 4_000,
+                    desired_quote_amount_in: 9_000,
+                    initial_lp_shares: Some(6_000),
+                },
+            ),
+        ]
+    };
+    assert_eq!(instructions, expected(&Rent::default()));
+
+    // The rent of another cluster only changes the funding of the pool account
+    let rent = Rent {
+        lamports_per_byte_year: 1_000,
+        ..Rent::default()
+    };
+    assert_eq!(
+        initialize_pool_with_liquidity_with_rent(
+            &pool_key,
+            &creator,
+            &base_mint,
+            &base_account,
+            &quote_mint,
+            &quote_account,
+            params,
+            4_000,
+            9_000,
+            &rent,
+        ),
+        expected(&rent)
+    );
+}
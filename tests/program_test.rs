@@ -0,0 +1,317 @@
+#![cfg(feature = "program-test")]
+
+use borsh::BorshDeserialize;
+use plasma_sdk::{
+    PoolAccount,
+    plasma::{
+        ID, InitializePoolParams, LpPosition, Side, SwapParams, SwapType, get_lp_position_address,
+        get_vault_address, initialize_pool_with_liquidity_with_rent,
+        plasma_amm::Amm,
+        program_test::{
+            SLIPPAGE_EXCEEDED_CODE, SeededPool, add_mint, add_seeded_pool, add_token_account,
+            plasma_program_test,
+        },
+        remove_liquidity, slot_window, swap,
+        token::TokenAccount,
+    },
+};
+use solana_account::Account;
+use solana_program::{clock::Clock, instruction::InstructionError, pubkey::Pubkey, system_program};
+use solana_program_test::{BanksClient, ProgramTest, ProgramTestContext, tokio};
+use solana_sdk::{
+    signature::Keypair,
+    signer::Signer,
+    transaction::{Transaction, TransactionError},
+};
+
+const LAMPORTS: u64 = 10_000_000_000;
+const BASE_AMOUNT: u64 = 1_000_000_000_000;
+const QUOTE_AMOUNT: u64 = 150_000_000_000;
+
+fn pool_params() -> InitializePoolParams {
+    InitializePoolParams {
+        lp_fee_in_bps: 25,
+        protocol_fee_allocation_in_pct: 20,
+        ..Default::default()
+    }
+}
+
+fn add_wallet(program_test: &mut ProgramTest) -> Keypair {
+    let wallet = Keypair::new();
+    program_test.add_account(
+        wallet.pubkey(),
+        Account::new(LAMPORTS, 0, &system_program::ID),
+    );
+    wallet
+}
+
+async fn token_balance(banks_client: &mut BanksClient, key: &Pubkey) -> u64 {
+    let account = banks_client.get_account(*key).await.unwrap().unwrap();
+    TokenAccount::unpack(&account.data).unwrap().amount
+}
+
+async fn pool(banks_client: &mut BanksClient, key: &Pubkey) -> PoolAccount {
+    let account = banks_client.get_account(*key).await.unwrap().unwrap();
+    PoolAccount::try_from_slice(&account.data).unwrap()
+}
+
+async fn lp_position(
+    banks_client: &mut BanksClient,
+    pool_key: &Pubkey,
+    owner: &Pubkey,
+) -> LpPosition {
+    let (key, _) = get_lp_position_address(&ID, pool_key, owner);
+    let account = banks_client.get_account(key).await.unwrap().unwrap();
+    LpPosition::try_from_slice(&account.data).unwrap()
+}
+
+async fn send(
+    context: &mut ProgramTestContext,
+    instructions: &[solana_program::instruction::Instruction],
+    signers: &[&Keypair],
+) -> Result<(), TransactionError> {
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+    let blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&context.payer.pubkey()),
+        &all_signers,
+        blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|error| error.unwrap())
+}
+
+struct Trader {
+    keypair: Keypair,
+    base_account: Pubkey,
+    quote_account: Pubkey,
+}
+
+async fn seeded_pool_with_trader() -> (ProgramTestContext, SeededPool, Trader) {
+    let mut program_test = plasma_program_test();
+    let lp_owner = Pubkey::new_unique();
+    let seeded_pool = add_seeded_pool(
+        &mut program_test,
+        &lp_owner,
+        9,
+        6,
+        pool_params(),
+        BASE_AMOUNT,
+        QUOTE_AMOUNT,
+    )
+    .unwrap();
+    let keypair = add_wallet(&mut program_test);
+    let trader = Trader {
+        base_account: add_token_account(
+            &mut program_test,
+            &seeded_pool.base_mint,
+            &keypair.pubkey(),
+            BASE_AMOUNT,
+        ),
+        quote_account: add_token_account(
+            &mut program_test,
+            &seeded_pool.quote_mint,
+            &keypair.pubkey(),
+            QUOTE_AMOUNT,
+        ),
+        keypair,
+    };
+    (program_test.start_with_context().await, seeded_pool, trader)
+}
+
+fn sell_exact_in(
+    seeded_pool: &SeededPool,
+    trader: &Trader,
+    amount_in: u64,
+    min_amount_out: u64,
+) -> solana_program::instruction::Instruction {
+    swap(
+        &seeded_pool.pool_key,
+        &trader.keypair.pubkey(),
+        &seeded_pool.base_mint,
+        &seeded_pool.quote_mint,
+        &trader.base_account,
+        &trader.quote_account,
+        SwapParams {
+            side: Side::Sell,
+            swap_type: SwapType::ExactIn {
+                amount_in,
+                min_amount_out,
+            },
+        },
+    )
+}
+
+#[tokio::test]
+async fn initialize_pool_with_liquidity_through_banks_client() {
+    let mut program_test = plasma_program_test();
+    let base_mint = add_mint(&mut program_test, 9);
+    let quote_mint = add_mint(&mut program_test, 6);
+    let creator = add_wallet(&mut program_test);
+    let base_account = add_token_account(
+        &mut program_test,
+        &base_mint,
+        &creator.pubkey(),
+        BASE_AMOUNT,
+    );
+    let quote_account = add_token_account(
+        &mut program_test,
+        &quote_mint,
+        &creator.pubkey(),
+        QUOTE_AMOUNT,
+    );
+    let mut context = program_test.start_with_context().await;
+
+    let pool_keypair = Keypair::new();
+    let pool_key = pool_keypair.pubkey();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let instructions = initialize_pool_with_liquidity_with_rent(
+        &pool_key,
+        &creator.pubkey(),
+        &base_mint,
+        &base_account,
+        &quote_mint,
+        &quote_account,
+        pool_params(),
+        BASE_AMOUNT,
+        QUOTE_AMOUNT,
+        &rent,
+    );
+    send(&mut context, &instructions, &[&creator, &pool_keypair])
+        .await
+        .unwrap();
+
+    let banks_client = &mut context.banks_client;
+    let pool = pool(banks_client, &pool_key).await;
+    assert_eq!(pool.header.base_params.mint_key, base_mint);
+    assert_eq!(pool.header.quote_params.decimals, 6);
+    assert_eq!(pool.amm.base_reserves, BASE_AMOUNT);
+    assert_eq!(pool.amm.quote_reserves, QUOTE_AMOUNT);
+    let position = lp_position(banks_client, &pool_key, &creator.pubkey()).await;
+    assert_eq!(
+        position.lp_shares,
+        Amm::initial_lp_shares(BASE_AMOUNT, QUOTE_AMOUNT)
+    );
+    let (base_vault, _) = get_vault_address(&ID, &pool_key, &base_mint);
+    let (quote_vault, _) = get_vault_address(&ID, &pool_key, &quote_mint);
+    assert_eq!(token_balance(banks_client, &base_vault).await, BASE_AMOUNT);
+    assert_eq!(
+        token_balance(banks_client, &quote_vault).await,
+        QUOTE_AMOUNT
+    );
+    assert_eq!(token_balance(banks_client, &base_account).await, 0);
+    assert_eq!(token_balance(banks_client, &quote_account).await, 0);
+}
+
+#[tokio::test]
+async fn swap_on_seeded_pool_matches_amm_simulation() {
+    let (mut context, seeded_pool, trader) = seeded_pool_with_trader().await;
+    let amount_in = 1_000_000_000;
+    let amm = pool(&mut context.banks_client, &seeded_pool.pool_key)
+        .await
+        .amm;
+    let clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    let expected = amm
+        .simulate_sell_exact_in_with_slot(slot_window(clock.slot), amount_in)
+        .unwrap();
+
+    let instruction = sell_exact_in(&seeded_pool, &trader, amount_in, 0);
+    send(&mut context, &[instruction], &[&trader.keypair])
+        .await
+        .unwrap();
+
+    let banks_client = &mut context.banks_client;
+    assert_eq!(
+        token_balance(banks_client, &trader.base_account).await,
+        BASE_AMOUNT - amount_in
+    );
+    assert_eq!(
+        token_balance(banks_client, &trader.quote_account).await,
+        QUOTE_AMOUNT + expected.quote_amount_to_transfer
+    );
+    let pool = pool(banks_client, &seeded_pool.pool_key).await;
+    assert_eq!(pool.header.swap_sequence_number, 1);
+    assert_eq!(pool.amm.base_reserves, BASE_AMOUNT + amount_in);
+}
+
+#[tokio::test]
+async fn slippage_failure_returns_custom_error() {
+    let (mut context, seeded_pool, trader) = seeded_pool_with_trader().await;
+    let instruction = sell_exact_in(&seeded_pool, &trader, 1_000_000_000, u64::MAX);
+    let error = send(&mut context, &[instruction], &[&trader.keypair])
+        .await
+        .unwrap_err();
+
+    assert_eq!(
+        error,
+        TransactionError::InstructionError(0, InstructionError::Custom(SLIPPAGE_EXCEEDED_CODE))
+    );
+    assert_eq!(
+        token_balance(&mut context.banks_client, &trader.base_account).await,
+        BASE_AMOUNT
+    );
+}
+
+#[tokio::test]
+async fn seeded_lp_position_can_be_withdrawn() {
+    let mut program_test = plasma_program_test();
+    let lp_owner = add_wallet(&mut program_test);
+    let seeded_pool = add_seeded_pool(
+        &mut program_test,
+        &lp_owner.pubkey(),
+        9,
+        6,
+        pool_params(),
+        BASE_AMOUNT,
+        QUOTE_AMOUNT,
+    )
+    .unwrap();
+    let base_account = add_token_account(
+        &mut program_test,
+        &seeded_pool.base_mint,
+        &lp_owner.pubkey(),
+        0,
+    );
+    let quote_account = add_token_account(
+        &mut program_test,
+        &seeded_pool.quote_mint,
+        &lp_owner.pubkey(),
+        0,
+    );
+    let mut context = program_test.start_with_context().await;
+
+    let amm = pool(&mut context.banks_client, &seeded_pool.pool_key)
+        .await
+        .amm;
+    let shares = seeded_pool.lp_shares / 2;
+    let preview = amm.preview_burn(amm.get_slot(), shares).unwrap();
+    let instruction = remove_liquidity(
+        &seeded_pool.pool_key,
+        &lp_owner.pubkey(),
+        &seeded_pool.base_mint,
+        &seeded_pool.quote_mint,
+        &base_account,
+        &quote_account,
+        shares,
+    );
+    send(&mut context, &[instruction], &[&lp_owner])
+        .await
+        .unwrap();
+
+    let banks_client = &mut context.banks_client;
+    assert_eq!(
+        token_balance(banks_client, &base_account).await,
+        preview.base_amount_withdrawn
+    );
+    assert_eq!(
+        token_balance(banks_client, &quote_account).await,
+        preview.quote_amount_withdrawn
+    );
+    let position = lp_position(banks_client, &seeded_pool.pool_key, &lp_owner.pubkey()).await;
+    assert_eq!(position.lp_shares, seeded_pool.lp_shares - shares);
+}
@@ -0,0 +1,728 @@
+#![cfg(feature = "client")]
+
+use plasma_sdk::plasma::{
+    AddLiquidityParams, CreatePoolError, ID, InitializePoolParams, POOL_LEN, PlasmaInstruction,
+    PlasmaStateError, Side, SwapParams, SwapType, TransferLiquidityError, add_liquidity,
+    create_pool, get_lp_position_address, initialize_lp_position,
+    initialize_pool_with_liquidity_with_rent,
+    plasma_amm::Amm,
+    processor::ProcessorError,
+    remove_liquidity,
+    replay::{Divergence, DivergenceKind, PoolReplay, ReplayStep, verify_replay},
+    slot_window, swap, transfer_liquidity, transfer_liquidity_checked,
+};
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
+use solana_system_interface::instruction::transfer;
+
+mod common;
+
+use common::{BASE_AMOUNT, Fixture, LAMPORTS, QUOTE_AMOUNT};
+
+fn pool_params(num_slots_to_vest_lp_shares: Option<u64>) -> InitializePoolParams {
+    InitializePoolParams {
+        lp_fee_in_bps: 25,
+        protocol_fee_allocation_in_pct: 20,
+        num_slots_to_vest_lp_shares,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn initialize_pool_with_liquidity_seeds_the_pool() {
+    let fixture = Fixture::new(pool_params(None));
+    let pool = fixture.runtime.pool(&fixture.pool_key).unwrap();
+
+    assert_eq!(pool.header.base_params.mint_key, fixture.base_mint);
+    assert_eq!(pool.header.quote_params.decimals, 6);
+    assert_eq!(pool.amm.base_reserves, BASE_AMOUNT);
+    assert_eq!(pool.amm.quote_reserves, QUOTE_AMOUNT);
+    assert_eq!(
+        pool.amm.total_lp_shares,
+        Amm::initial_lp_shares(BASE_AMOUNT, QUOTE_AMOUNT)
+    );
+
+    let position = fixture
+        .runtime
+        .lp_position(&fixture.pool_key, &fixture.creator.key)
+        .unwrap();
+    assert_eq!(position.lp_shares, pool.amm.total_lp_shares);
+    assert_eq!(position.withdrawable_lp_shares, pool.amm.total_lp_shares);
+    assert_eq!(fixture.balances(&fixture.creator), (0, 0));
+    fixture.assert_vaults_cover_reserves();
+}
+
+#[test]
+fn create_pool_allocates_a_rent_exempt_account() {
+    let mut fixture = Fixture::new(pool_params(None));
+    let rent = fixture.runtime.rent().clone();
+    let pool_key = Pubkey::new_unique();
+    let creator = fixture.creator.key;
+    let creation = create_pool(
+        &pool_key,
+        &creator,
+        &fixture.base_mint,
+        &fixture.quote_mint,
+        pool_params(Some(8)),
+        &rent,
+        fixture.runtime.get_account(&pool_key),
+    )
+    .unwrap();
+    assert_eq!(creation.signers, vec![creator, pool_key]);
+    assert_eq!(
+        creation.pool_lamports,
+        rent.minimum_balance(POOL_LEN as usize)
+    );
+
+    // Every signer is required
+    assert_eq!(
+        fixture
+            .runtime
+            .process_transaction(&creation.instructions, &[creator]),
+        Err(ProcessorError::MissingRequiredSignature(pool_key))
+    );
+    fixture
+        .runtime
+        .process_transaction(&creation.instructions, &creation.signers)
+        .unwrap();
+    let account = fixture.runtime.get_account(&pool_key).unwrap();
+    assert_eq!(account.owner, ID);
+    assert_eq!(account.lamports, creation.pool_lamports);
+    let pool = fixture.runtime.pool(&pool_key).unwrap();
+    assert_eq!(pool.amm.total_lp_shares, 0);
+    assert_eq!(pool.amm.lp_vesting_window, 2);
+
+    // The keypair cannot be used for a second pool, nor can the creator or a mint
+    let reuse = |key: &Pubkey| {
+        create_pool(
+            key,
+            &creator,
+            &fixture.base_mint,
+            &fixture.quote_mint,
+            pool_params(None),
+            &rent,
+            fixture.runtime.get_account(key),
+        )
+    };
+    assert_eq!(
+        reuse(&pool_key),
+        Err(CreatePoolError::PoolAccountInUse(pool_key))
+    );
+    assert_eq!(
+        reuse(&creator),
+        Err(CreatePoolError::PoolKeyReused(creator))
+    );
+    assert_eq!(
+        reuse(&fixture.base_mint),
+        Err(CreatePoolError::PoolKeyReused(fixture.base_mint))
+    );
+}
+
+#[test]
+fn swaps_match_amm_simulation() {
+    let mut fixture = Fixture::new(pool_params(None));
+    let trader = fixture.wallet(1_000_000_000_000, 1_000_000_000_000);
+
+    for (slots, side, swap_type) in [
+        (
+            0,
+            Side::Buy,
+            SwapType::ExactIn {
+                amount_in: 5_000_000_000,
+                min_amount_out: 0,
+            },
+        ),
+        (
+            4,
+            Side::Sell,
+            SwapType::ExactIn {
+                amount_in: 70_000_000_000,
+                min_amount_out: 0,
+            },
+        ),
+        (
+            1,
+            Side::Buy,
+            SwapType::ExactOut {
+                amount_out: 3_000_000_000,
+                max_amount_in: u64::MAX,
+            },
+        ),
+        (
+            8,
+            Side::Sell,
+            SwapType::ExactOut {
+                amount_out: 2_000_000_000,
+                max_amount_in: u64::MAX,
+            },
+        ),
+    ] {
+        fixture.runtime.advance_slots(slots);
+        let window = slot_window(fixture.runtime.slot());
+        let mut expected_amm = fixture.amm();
+        let expected = match (side, swap_type) {
+            (Side::Buy, SwapType::ExactIn { amount_in, .. }) => {
+                expected_amm.buy_exact_in(window, amount_in)
+            }
+            (Side::Buy, SwapType::ExactOut { amount_out, .. }) => {
+                expected_amm.buy_exact_out(window, amount_out)
+            }
+            (Side::Sell, SwapType::ExactIn { amount_in, .. }) => {
+                expected_amm.sell_exact_in(window, amount_in)
+            }
+            (Side::Sell, SwapType::ExactOut { amount_out, .. }) => {
+                expected_amm.sell_exact_out(window, amount_out)
+            }
+        }
+        .unwrap();
+
+        let (base_before, quote_before) = fixture.balances(&trader);
+        fixture
+            .swap(&trader, SwapParams { side, swap_type })
+            .unwrap();
+        let (base_after, quote_after) = fixture.balances(&trader);
+
+        match side {
+            Side::Buy => {
+                assert_eq!(base_after - base_before, expected.base_amount_to_transfer);
+                assert_eq!(
+                    quote_before - quote_after,
+                    expected.quote_amount_to_transfer
+                );
+            }
+            Side::Sell => {
+                assert_eq!(base_before - base_after, expected.base_amount_to_transfer);
+                assert_eq!(
+                    quote_after - quote_before,
+                    expected.quote_amount_to_transfer
+                );
+            }
+        }
+        let amm = fixture.amm();
+        assert_eq!(amm.base_reserves, expected_amm.base_reserves);
+        assert_eq!(amm.quote_reserves, expected_amm.quote_reserves);
+        fixture.assert_vaults_cover_reserves();
+    }
+
+    let pool = fixture.runtime.pool(&fixture.pool_key).unwrap();
+    assert_eq!(pool.header.swap_sequence_number, 4);
+}
+
+#[test]
+fn swap_enforces_slippage_and_rolls_back() {
+    let mut fixture = Fixture::new(pool_params(None));
+    let trader = fixture.wallet(0, 1_000_000_000);
+    let amm_before = fixture.amm();
+
+    let result = fixture.swap(
+        &trader,
+        SwapParams {
+            side: Side::Buy,
+            swap_type: SwapType::ExactIn {
+                amount_in: 1_000_000_000,
+                min_amount_out: u64::MAX,
+            },
+        },
+    );
+    assert!(matches!(
+        result,
+        Err(ProcessorError::SlippageExceeded { .. })
+    ));
+    assert_eq!(fixture.amm().quote_reserves, amm_before.quote_reserves);
+    assert_eq!(fixture.balances(&trader), (0, 1_000_000_000));
+}
+
+#[test]
+fn balance_overflows_are_rejected_and_roll_back() {
+    let mut fixture = Fixture::new(pool_params(None));
+    let trader = fixture.wallet(1_000_000, u64::MAX);
+    let amm_before = fixture.amm();
+
+    let result = fixture.swap(
+        &trader,
+        SwapParams {
+            side: Side::Sell,
+            swap_type: SwapType::ExactIn {
+                amount_in: 1_000_000,
+                min_amount_out: 0,
+            },
+        },
+    );
+    assert_eq!(
+        result,
+        Err(ProcessorError::Program(ProgramError::ArithmeticOverflow))
+    );
+    assert_eq!(fixture.amm().base_reserves, amm_before.base_reserves);
+    assert_eq!(fixture.balances(&trader), (1_000_000, u64::MAX));
+
+    let rich = Pubkey::new_unique();
+    fixture.runtime.airdrop(&rich, u64::MAX);
+    let result = fixture
+        .runtime
+        .process_transaction(&[transfer(&trader.key, &rich, 1)], &[trader.key]);
+    assert_eq!(
+        result,
+        Err(ProcessorError::Program(ProgramError::ArithmeticOverflow))
+    );
+    assert_eq!(
+        fixture.runtime.get_account(&trader.key).unwrap().lamports,
+        LAMPORTS
+    );
+}
+
+#[test]
+fn instructions_require_signatures() {
+    let mut fixture = Fixture::new(pool_params(None));
+    let trader = fixture.wallet(0, 1_000_000_000);
+    let instruction = swap(
+        &fixture.pool_key,
+        &trader.key,
+        &fixture.base_mint,
+        &fixture.quote_mint,
+        &trader.base_account,
+        &trader.quote_account,
+        SwapParams {
+            side: Side::Buy,
+            swap_type: SwapType::ExactIn {
+                amount_in: 1_000_000,
+                min_amount_out: 0,
+            },
+        },
+    );
+
+    assert_eq!(
+        fixture
+            .runtime
+            .process_transaction(std::slice::from_ref(&instruction), &[]),
+        Err(ProcessorError::MissingRequiredSignature(trader.key))
+    );
+
+    let mut unsigned = instruction;
+    unsigned.accounts[3].is_signer = false;
+    assert_eq!(
+        fixture.runtime.process_transaction(&[unsigned], &[]),
+        Err(ProcessorError::MissingRequiredSignature(trader.key))
+    );
+}
+
+#[test]
+fn swap_rejects_foreign_vaults() {
+    let mut fixture = Fixture::new(pool_params(None));
+    let trader = fixture.wallet(0, 1_000_000_000);
+    let mut instruction = swap(
+        &fixture.pool_key,
+        &trader.key,
+        &fixture.base_mint,
+        &fixture.quote_mint,
+        &trader.base_account,
+        &trader.quote_account,
+        SwapParams {
+            side: Side::Buy,
+            swap_type: SwapType::ExactIn {
+                amount_in: 1_000_000,
+                min_amount_out: 0,
+            },
+        },
+    );
+    instruction.accounts[6].pubkey = trader.base_account;
+
+    assert_eq!(
+        fixture
+            .runtime
+            .process_transaction(&[instruction], &[trader.key]),
+        Err(ProcessorError::InvalidSeeds(trader.base_account))
+    );
+}
+
+#[test]
+fn liquidity_round_trip() {
+    let mut fixture = Fixture::new(pool_params(None));
+    let lp = fixture.wallet(10_000_000_000, 10_000_000_000);
+    fixture.initialize_lp_position(&lp.key);
+
+    let preview = fixture
+        .amm()
+        .preview_mint(
+            slot_window(fixture.runtime.slot()),
+            10_000_000_000,
+            10_000_000_000,
+        )
+        .unwrap();
+    fixture
+        .add_liquidity(&lp, 10_000_000_000, 10_000_000_000)
+        .unwrap();
+    let position = fixture
+        .runtime
+        .lp_position(&fixture.pool_key, &lp.key)
+        .unwrap();
+    assert_eq!(position.lp_shares, preview.lp_shares);
+    assert_eq!(
+        fixture.balances(&lp),
+        (preview.base_amount_refunded, preview.quote_amount_refunded)
+    );
+
+    assert_eq!(
+        fixture.remove_liquidity(&lp, position.lp_shares + 1),
+        Err(ProcessorError::State(PlasmaStateError::TooManyShares))
+    );
+    fixture.remove_liquidity(&lp, position.lp_shares).unwrap();
+    let (base, quote) = fixture.balances(&lp);
+    assert!(base <= 10_000_000_000 && quote <= 10_000_000_000);
+    fixture.assert_vaults_cover_reserves();
+}
+
+#[test]
+fn vesting_blocks_withdrawals_until_the_window_passes() {
+    let mut fixture = Fixture::new(pool_params(Some(40)));
+    let shares = fixture.amm().total_lp_shares;
+    let creator = fixture.creator;
+
+    assert_eq!(
+        fixture.remove_liquidity(&creator, shares / 2),
+        Err(ProcessorError::State(PlasmaStateError::TooManyShares))
+    );
+    fixture.runtime.advance_slots(40);
+    fixture.remove_liquidity(&creator, shares / 2).unwrap();
+}
+
+#[test]
+fn transfer_liquidity_moves_the_whole_position() {
+    let mut fixture = Fixture::new(pool_params(None));
+    let recipient = Pubkey::new_unique();
+    let shares = fixture.amm().total_lp_shares;
+
+    let instruction = transfer_liquidity(&fixture.pool_key, &fixture.creator.key, &recipient);
+    assert!(matches!(
+        fixture
+            .runtime
+            .process_transaction(std::slice::from_ref(&instruction), &[fixture.creator.key]),
+        Err(ProcessorError::AccountNotFound(_))
+    ));
+
+    fixture.initialize_lp_position(&recipient);
+    fixture
+        .runtime
+        .process_transaction(&[instruction], &[fixture.creator.key])
+        .unwrap();
+
+    let source = fixture
+        .runtime
+        .lp_position(&fixture.pool_key, &fixture.creator.key)
+        .unwrap();
+    let destination = fixture
+        .runtime
+        .lp_position(&fixture.pool_key, &recipient)
+        .unwrap();
+    assert_eq!(source.lp_shares, 0);
+    assert_eq!(destination.lp_shares, shares);
+    assert_eq!(destination.withdrawable_lp_shares, shares);
+}
+
+#[test]
+fn checked_transfers_initialize_missing_destinations() {
+    let mut fixture = Fixture::new(pool_params(Some(40)));
+    let shares = fixture.amm().total_lp_shares;
+    let (creator, recipient) = (fixture.creator.key, Pubkey::new_unique());
+    let checked = |fixture: &Fixture, src: &Pubkey, dst: &Pubkey| {
+        let position = |owner| get_lp_position_address(&ID, &fixture.pool_key, owner).0;
+        transfer_liquidity_checked(
+            &fixture.pool_key,
+            src,
+            dst,
+            &fixture.amm(),
+            fixture.runtime.slot(),
+            fixture.runtime.get_account(&position(src)),
+            fixture.runtime.get_account(&position(dst)),
+        )
+    };
+
+    assert_eq!(
+        checked(&fixture, &creator, &creator),
+        Err(TransferLiquidityError::SameOwner(creator))
+    );
+    assert_eq!(
+        checked(&fixture, &recipient, &creator),
+        Err(TransferLiquidityError::SourceNotFound(
+            get_lp_position_address(&ID, &fixture.pool_key, &recipient).0
+        ))
+    );
+    assert_eq!(
+        checked(&fixture, &creator, &recipient),
+        Err(TransferLiquidityError::SharesStillVesting {
+            deposit_window: slot_window(100),
+            shares,
+        })
+    );
+
+    // Once vested, the destination position is created with the sender paying the rent
+    fixture.runtime.advance_slots(40);
+    let transfer = checked(&fixture, &creator, &recipient).unwrap();
+    assert!(transfer.initializes_destination);
+    assert_eq!(
+        transfer.instructions,
+        vec![
+            initialize_lp_position(&fixture.pool_key, &creator, &recipient),
+            transfer_liquidity(&fixture.pool_key, &creator, &recipient),
+        ]
+    );
+    assert_eq!(
+        (transfer.lp_shares, transfer.withdrawable_lp_shares),
+        (shares, shares)
+    );
+    fixture
+        .runtime
+        .process_transaction(&transfer.instructions, &[creator])
+        .unwrap();
+    let destination = fixture
+        .runtime
+        .lp_position(&fixture.pool_key, &recipient)
+        .unwrap();
+    assert_eq!(destination.lp_shares, shares);
+    assert_eq!(destination.withdrawable_lp_shares, shares);
+
+    // An existing destination is reused, and an emptied source is refused
+    let transfer = checked(&fixture, &recipient, &creator).unwrap();
+    assert!(!transfer.initializes_destination);
+    assert_eq!(transfer.instructions.len(), 1);
+    assert_eq!(
+        checked(&fixture, &creator, &recipient),
+        Err(TransferLiquidityError::NoShares(
+            get_lp_position_address(&ID, &fixture.pool_key, &creator).0
+        ))
+    );
+
+    // Accounts that are not LP positions are rejected
+    let token_account = fixture.runtime.get_account(&fixture.creator.base_account);
+    assert_eq!(
+        transfer_liquidity_checked(
+            &fixture.pool_key,
+            &recipient,
+            &creator,
+            &fixture.amm(),
+            fixture.runtime.slot(),
+            fixture
+                .runtime
+                .get_account(&get_lp_position_address(&ID, &fixture.pool_key, &recipient).0),
+            token_account,
+        ),
+        Err(TransferLiquidityError::InvalidLpPosition(
+            get_lp_position_address(&ID, &fixture.pool_key, &creator).0
+        ))
+    );
+
+    // A destination address that only holds lamports cannot be created
+    let funded = Pubkey::new_unique();
+    let funded_position = get_lp_position_address(&ID, &fixture.pool_key, &funded).0;
+    fixture.runtime.airdrop(&funded_position, 1);
+    assert_eq!(
+        checked(&fixture, &recipient, &funded),
+        Err(TransferLiquidityError::DestinationFunded(funded_position))
+    );
+}
+
+/// Drives a pool through every instruction and returns the replay steps after the launch, each
+/// with a snapshot of the pool after its transaction
+fn record_history(fixture: &mut Fixture) -> Vec<ReplayStep> {
+    let trader = fixture.wallet(50_000_000_000, 10_000_000_000);
+    let lp = fixture.wallet(100_000_000_000, 15_000_000_000);
+    let buy = SwapParams {
+        side: Side::Buy,
+        swap_type: SwapType::ExactIn {
+            amount_in: 1_000_000_000,
+            min_amount_out: 0,
+        },
+    };
+    let sell = SwapParams {
+        side: Side::Sell,
+        swap_type: SwapType::ExactOut {
+            amount_out: 500_000_000,
+            max_amount_in: u64::MAX,
+        },
+    };
+    let transactions = vec![
+        swap(
+            &fixture.pool_key,
+            &trader.key,
+            &fixture.base_mint,
+            &fixture.quote_mint,
+            &trader.base_account,
+            &trader.quote_account,
+            buy,
+        ),
+        initialize_lp_position(&fixture.pool_key, &lp.key, &lp.key),
+        add_liquidity(
+            &fixture.pool_key,
+            &lp.key,
+            &fixture.base_mint,
+            &lp.base_account,
+            &fixture.quote_mint,
+            &lp.quote_account,
+            AddLiquidityParams {
+                desired_base_amount_in: 100_000_000_000,
+                desired_quote_amount_in: 15_000_000_000,
+                initial_lp_shares: None,
+            },
+        ),
+        swap(
+            &fixture.pool_key,
+            &trader.key,
+            &fixture.base_mint,
+            &fixture.quote_mint,
+            &trader.base_account,
+            &trader.quote_account,
+            sell,
+        ),
+        remove_liquidity(
+            &fixture.pool_key,
+            &fixture.creator.key,
+            &fixture.base_mint,
+            &fixture.quote_mint,
+            &fixture.creator.base_account,
+            &fixture.creator.quote_account,
+            1_000_000,
+        ),
+    ];
+    let mut steps = vec![];
+    for (i, instruction) in transactions.into_iter().enumerate() {
+        fixture
+            .runtime
+            .set_slot(fixture.runtime.slot() + 3 * i as u64);
+        let signer = instruction
+            .accounts
+            .iter()
+            .find(|meta| meta.is_signer)
+            .unwrap()
+            .pubkey;
+        fixture
+            .runtime
+            .process_transaction(std::slice::from_ref(&instruction), &[signer])
+            .unwrap();
+        steps.push(ReplayStep {
+            slot: fixture.runtime.slot(),
+            instruction: PlasmaInstruction::unpack(&instruction.data).unwrap(),
+            snapshot: fixture.runtime.pool(&fixture.pool_key),
+        });
+    }
+    steps
+}
+
+#[test]
+fn replaying_instructions_rebuilds_the_pool() {
+    let params = pool_params(Some(8));
+    let mut fixture = Fixture::new(params);
+    let launch_slot = fixture.runtime.slot();
+    let launched = fixture.runtime.pool(&fixture.pool_key).unwrap();
+    let steps = record_history(&mut fixture);
+
+    let report = verify_replay(PoolReplay::from_snapshot(&launched), &steps);
+    assert_eq!(report.first_divergence, None);
+    assert_eq!(report.steps_applied, steps.len());
+    assert_eq!(report.snapshots_checked, steps.len());
+    let pool = fixture.runtime.pool(&fixture.pool_key).unwrap();
+    report.replay.check_snapshot(&pool).unwrap();
+
+    // The launch transaction decodes into the steps that follow `initialize_pool`
+    let launch = initialize_pool_with_liquidity_with_rent(
+        &fixture.pool_key,
+        &fixture.creator.key,
+        &fixture.base_mint,
+        &fixture.creator.base_account,
+        &fixture.quote_mint,
+        &fixture.creator.quote_account,
+        params,
+        BASE_AMOUNT,
+        QUOTE_AMOUNT,
+        fixture.runtime.rent(),
+    )
+    .iter()
+    .filter(|instruction| instruction.program_id == ID)
+    .map(|instruction| PlasmaInstruction::unpack(&instruction.data).unwrap())
+    .filter(|instruction| !matches!(instruction, PlasmaInstruction::InitializePool(_)))
+    .map(|instruction| ReplayStep {
+        slot: launch_slot,
+        instruction,
+        snapshot: None,
+    })
+    .collect::<Vec<_>>();
+    let mut from_params = PoolReplay::new(&params, launch_slot);
+    for step in launch.iter().chain(steps.iter()) {
+        from_params.apply(step.slot, &step.instruction).unwrap();
+    }
+    from_params.check_snapshot(&pool).unwrap();
+}
+
+#[test]
+fn replay_reports_the_first_divergence() {
+    let mut fixture = Fixture::new(pool_params(None));
+    let launched = fixture.runtime.pool(&fixture.pool_key).unwrap();
+    let steps = record_history(&mut fixture);
+    let replay = PoolReplay::from_snapshot(&launched);
+
+    let mut tampered = steps.clone();
+    let tampered_index = steps.len() - 2;
+    tampered[tampered_index]
+        .snapshot
+        .as_mut()
+        .unwrap()
+        .amm
+        .quote_reserves += 1;
+    tampered
+        .last_mut()
+        .unwrap()
+        .snapshot
+        .as_mut()
+        .unwrap()
+        .amm
+        .base_reserves += 1;
+    let report = verify_replay(replay, &tampered);
+    let divergence = report.first_divergence.unwrap();
+    assert_eq!(divergence.step_index, tampered_index);
+    let DivergenceKind::SnapshotMismatch {
+        field,
+        replayed,
+        snapshot,
+    } = divergence.kind
+    else {
+        panic!("expected a snapshot mismatch, got {:?}", divergence.kind);
+    };
+    assert_eq!(field, "quote_reserves");
+    assert_eq!(snapshot, replayed + 1);
+    assert_eq!(report.steps_applied, tampered_index + 1);
+
+    // A missing swap shows up at the next snapshot
+    let report = verify_replay(replay, &steps[1..]);
+    let divergence = report.first_divergence.unwrap();
+    assert_eq!(divergence.step_index, 0);
+    assert!(matches!(
+        divergence.kind,
+        DivergenceKind::SnapshotMismatch { .. }
+    ));
+
+    let mut overdrawn = steps.clone();
+    overdrawn.last_mut().unwrap().instruction =
+        PlasmaInstruction::RemoveLiquidity { shares: u64::MAX };
+    let report = verify_replay(replay, &overdrawn);
+    let divergence = report.first_divergence.unwrap();
+    assert_eq!(divergence.step_index, steps.len() - 1);
+    assert!(matches!(divergence.kind, DivergenceKind::Rejected(_)));
+    assert_eq!(report.steps_applied, steps.len() - 1);
+
+    // A second `initialize_pool` has its own divergence rather than a state error
+    let mut reinitialized = steps.clone();
+    reinitialized.insert(
+        1,
+        ReplayStep {
+            slot: steps[0].slot,
+            instruction: PlasmaInstruction::InitializePool(pool_params(None)),
+            snapshot: None,
+        },
+    );
+    let report = verify_replay(replay, &reinitialized);
+    assert_eq!(
+        report.first_divergence,
+        Some(Divergence {
+            step_index: 1,
+            kind: DivergenceKind::UnexpectedInitializePool,
+        })
+    );
+    assert_eq!(report.steps_applied, 1);
+}
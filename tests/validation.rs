@@ -0,0 +1,233 @@
+#![cfg(feature = "client")]
+
+use plasma_sdk::{
+    PoolAccount,
+    plasma::{
+        AddLiquidityParams, ID, InitializePoolParams, Side, SwapParams, SwapType, add_liquidity,
+        get_lp_position_address, initialize_lp_position, initialize_pool, remove_liquidity,
+        spl_token, swap, transfer_liquidity,
+        validation::{Issue, ValidatedInstruction, validate_instruction},
+    },
+};
+use solana_program::{instruction::AccountMeta, pubkey::Pubkey};
+
+mod common;
+
+use common::{BASE_AMOUNT, Fixture, QUOTE_AMOUNT, Wallet};
+
+fn setup() -> (Fixture, Wallet, PoolAccount) {
+    let mut fixture = Fixture::new(InitializePoolParams::default());
+    let trader = fixture.wallet(BASE_AMOUNT, QUOTE_AMOUNT);
+    let pool = fixture.runtime.pool(&fixture.pool_key).unwrap();
+    (fixture, trader, pool)
+}
+
+#[test]
+fn canonical_instructions_are_valid() {
+    let (fixture, trader, pool) = setup();
+    let params = SwapParams {
+        side: Side::Sell,
+        swap_type: SwapType::ExactIn {
+            amount_in: 1_000,
+            min_amount_out: 1,
+        },
+    };
+    let swap_instruction = swap(
+        &fixture.pool_key,
+        &trader.key,
+        &fixture.base_mint,
+        &fixture.quote_mint,
+        &trader.base_account,
+        &trader.quote_account,
+        params,
+    );
+    let validated = validate_instruction(&swap_instruction, Some(&pool)).unwrap();
+    let ValidatedInstruction::Swap {
+        base_account,
+        quote_account,
+        params: validated_params,
+        ..
+    } = validated
+    else {
+        panic!("expected a swap, got {:?}", validated);
+    };
+    assert_eq!(
+        (base_account, quote_account, validated_params),
+        (trader.base_account, trader.quote_account, params)
+    );
+    assert_eq!(validated.pool(), fixture.pool_key);
+    assert_eq!(validated.signer(), trader.key);
+
+    let other = Pubkey::new_unique();
+    let instructions = [
+        add_liquidity(
+            &fixture.pool_key,
+            &trader.key,
+            &fixture.base_mint,
+            &trader.base_account,
+            &fixture.quote_mint,
+            &trader.quote_account,
+            AddLiquidityParams::default(),
+        ),
+        remove_liquidity(
+            &fixture.pool_key,
+            &trader.key,
+            &fixture.base_mint,
+            &fixture.quote_mint,
+            &trader.base_account,
+            &trader.quote_account,
+            10,
+        ),
+        initialize_lp_position(&fixture.pool_key, &trader.key, &other),
+        transfer_liquidity(&fixture.pool_key, &trader.key, &other),
+    ];
+    for instruction in instructions.iter() {
+        let validated = validate_instruction(instruction, Some(&pool)).unwrap();
+        assert_eq!(validated.signer(), trader.key);
+    }
+    let ValidatedInstruction::TransferLiquidity {
+        destination_lp_position,
+        ..
+    } = validate_instruction(&instructions[3], None).unwrap()
+    else {
+        unreachable!()
+    };
+    assert_eq!(
+        destination_lp_position,
+        get_lp_position_address(&ID, &fixture.pool_key, &other).0
+    );
+
+    // Pool initialization declares its mints, so it needs no pool account
+    let new_pool = Pubkey::new_unique();
+    let validated = validate_instruction(
+        &initialize_pool(
+            &new_pool,
+            &trader.key,
+            &fixture.base_mint,
+            &fixture.quote_mint,
+            InitializePoolParams::default(),
+        ),
+        None,
+    )
+    .unwrap();
+    assert!(matches!(
+        validated,
+        ValidatedInstruction::InitializePool { base_mint, quote_mint, .. }
+            if (base_mint, quote_mint) == (fixture.base_mint, fixture.quote_mint)
+    ));
+}
+
+#[test]
+fn tampered_instructions_are_rejected() {
+    let (mut fixture, trader, pool) = setup();
+    let canonical = swap(
+        &fixture.pool_key,
+        &trader.key,
+        &fixture.base_mint,
+        &fixture.quote_mint,
+        &trader.base_account,
+        &trader.quote_account,
+        SwapParams {
+            side: Side::Buy,
+            swap_type: SwapType::ExactOut {
+                amount_out: 1_000,
+                max_amount_in: 2_000,
+            },
+        },
+    );
+    assert_eq!(
+        validate_instruction(&canonical, None).unwrap_err(),
+        vec![Issue::MissingPoolAccount]
+    );
+    let other_pool_key = fixture.launch_pool(
+        fixture.base_mint,
+        fixture.quote_mint,
+        InitializePoolParams::default(),
+        BASE_AMOUNT,
+        QUOTE_AMOUNT,
+    );
+    let other_pool = fixture.runtime.pool(&other_pool_key).unwrap();
+    assert_eq!(
+        validate_instruction(&canonical, Some(&other_pool)).unwrap_err(),
+        vec![Issue::PoolMismatch(fixture.pool_key)]
+    );
+
+    // A vault swapped for an account of the attacker, with flags changed on the way
+    let attacker = Pubkey::new_unique();
+    let mut instruction = canonical.clone();
+    let vault = instruction.accounts[6].pubkey;
+    instruction.accounts[6] = AccountMeta::new(attacker, false);
+    instruction.accounts[3].is_writable = true;
+    instruction.accounts[4].is_signer = true;
+    assert_eq!(
+        validate_instruction(&instruction, Some(&pool)).unwrap_err(),
+        vec![
+            Issue::WritableMismatch {
+                index: 3,
+                pubkey: trader.key,
+                expected: false,
+            },
+            Issue::SignerMismatch {
+                index: 4,
+                pubkey: trader.base_account,
+                expected: false,
+            },
+            Issue::InvalidVault {
+                index: 6,
+                expected: vault,
+                actual: attacker,
+            },
+        ]
+    );
+
+    // An LP position that does not belong to the signer
+    let mut instruction = remove_liquidity(
+        &fixture.pool_key,
+        &trader.key,
+        &fixture.base_mint,
+        &fixture.quote_mint,
+        &trader.base_account,
+        &trader.quote_account,
+        10,
+    );
+    let victim_position = get_lp_position_address(&ID, &fixture.pool_key, &attacker).0;
+    let expected = std::mem::replace(&mut instruction.accounts[4].pubkey, victim_position);
+    instruction.accounts[9].pubkey = attacker;
+    assert_eq!(
+        validate_instruction(&instruction, Some(&pool)).unwrap_err(),
+        vec![
+            Issue::InvalidLpPosition {
+                index: 4,
+                expected,
+                actual: victim_position,
+            },
+            Issue::UnexpectedAccount {
+                index: 9,
+                expected: spl_token::ID,
+                actual: attacker,
+            },
+        ]
+    );
+
+    let mut instruction = canonical.clone();
+    instruction.accounts.pop();
+    assert_eq!(
+        validate_instruction(&instruction, Some(&pool)).unwrap_err(),
+        vec![Issue::AccountCount {
+            expected: 9,
+            actual: 8,
+        }]
+    );
+    let mut instruction = canonical.clone();
+    instruction.data.push(0);
+    assert_eq!(
+        validate_instruction(&instruction, Some(&pool)).unwrap_err(),
+        vec![Issue::InvalidData]
+    );
+    let mut instruction = canonical.clone();
+    instruction.program_id = attacker;
+    assert_eq!(
+        validate_instruction(&instruction, None).unwrap_err(),
+        vec![Issue::NotPlasmaInstruction(attacker)]
+    );
+}
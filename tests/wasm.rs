@@ -0,0 +1,92 @@
+#![cfg(all(feature = "wasm", feature = "client"))]
+
+use plasma_sdk::plasma::{
+    InitializePoolParams, Side, SwapParams, SwapType,
+    plasma_amm::Side as AmmSide,
+    slot_window,
+    wasm::{DecodeError, Pool, decode_amm},
+};
+
+mod common;
+
+use common::{BASE_AMOUNT, Fixture};
+
+#[test]
+fn wasm_quotes_match_the_program() {
+    let mut fixture = Fixture::with_liquidity(
+        InitializePoolParams {
+            lp_fee_in_bps: 30,
+            protocol_fee_allocation_in_pct: 20,
+            ..Default::default()
+        },
+        BASE_AMOUNT / 4,
+        BASE_AMOUNT / 8,
+    );
+    let trader = fixture.wallet(BASE_AMOUNT, 0);
+    fixture.runtime.advance_slots(8);
+
+    let data = fixture
+        .runtime
+        .get_account(&fixture.pool_key)
+        .unwrap()
+        .data
+        .clone();
+    let pool = Pool::decode(&data).unwrap();
+    let amm = fixture.amm();
+    assert_eq!(decode_amm(&data).unwrap().base_reserves, amm.base_reserves);
+    assert_eq!(
+        (
+            pool.base_reserves(),
+            pool.quote_reserves(),
+            pool.total_lp_shares()
+        ),
+        (amm.base_reserves, amm.quote_reserves, amm.total_lp_shares)
+    );
+
+    let slot = fixture.runtime.slot();
+    let quote = pool.quote(slot, AmmSide::Sell, false, 1_000_000).unwrap();
+    assert_eq!(quote.quote_amount_to_transfer, 1_000_000);
+    assert_eq!(
+        pool.quote(slot, AmmSide::Sell, true, 5_000_000).unwrap(),
+        amm.simulate_sell_exact_in_with_slot(slot_window(slot), 5_000_000)
+            .unwrap()
+    );
+    assert_eq!(
+        pool.preview_mint(slot, 1_000, 1_000).unwrap(),
+        amm.preview_mint(slot_window(slot), 1_000, 1_000).unwrap()
+    );
+    assert_eq!(
+        pool.preview_burn(slot, 1_000).unwrap(),
+        amm.preview_burn(slot_window(slot), 1_000).unwrap()
+    );
+
+    // The program transfers what was quoted
+    let (base_before, _) = fixture.balances(&trader);
+    fixture
+        .swap(
+            &trader,
+            SwapParams {
+                side: Side::Sell,
+                swap_type: SwapType::ExactOut {
+                    amount_out: 1_000_000,
+                    max_amount_in: u64::MAX,
+                },
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        base_before - fixture.balances(&trader).0,
+        quote.base_amount_to_transfer
+    );
+
+    assert_eq!(
+        decode_amm(&data[1..]).unwrap_err(),
+        DecodeError::InvalidLength(data.len() - 1)
+    );
+    let mut corrupt = data.clone();
+    corrupt[0] ^= 1;
+    assert_eq!(
+        decode_amm(&corrupt).unwrap_err(),
+        DecodeError::InvalidDiscriminator
+    );
+}